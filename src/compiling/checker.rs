@@ -0,0 +1,724 @@
+use crate::error::{suggest, Diagnostic, KlangError, Span};
+use crate::interpreter::expr::Expr;
+use crate::interpreter::modules::ReadOnly;
+use crate::interpreter::stmt::{Param, Pattern, Stmt};
+use crate::messages::{self, Message};
+use crate::scanner::{Token, TokenType, Type, Value};
+use std::collections::{HashMap, HashSet};
+
+// walks the ast before compiling and rejects assignments to constants, and to variables
+// declared without mut when the program opts in with // klang:immutable.
+// with // klang:typecheck it also reports type annotations that can never be satisfied.
+// imported are the read only names of the other modules, so they can't be assigned from this one.
+// declared are the names declared or assigned anywhere in the program, reading any other name fails
+pub fn check(
+    stmts: &[Stmt],
+    directives: &[String],
+    imported: &[ReadOnly],
+    declared: &HashSet<String>,
+) -> Result<(), Vec<Diagnostic>> {
+    let globals = imported
+        .iter()
+        .map(|x| {
+            let binding = Binding {
+                constant: x.constant,
+                mutable: false,
+                initialized: true,
+                ty: None,
+                span: x.span,
+            };
+            (x.name.clone(), binding)
+        })
+        .collect();
+    //the top level names are visible from functions declared above them
+    let top_level = stmts
+        .iter()
+        .flat_map(|stmt| match stmt {
+            Stmt::Var { pattern, .. } => pattern.names(),
+            Stmt::Const { name, .. } => vec![name.lexeme.clone()],
+            _ => vec![],
+        })
+        .collect();
+    let mut checker = Checker {
+        scopes: vec![globals],
+        top_level,
+        declared,
+        functions: HashMap::new(),
+        returns: Vec::new(),
+        immutable: directives.iter().any(|d| d == "immutable"),
+        typecheck: directives.iter().any(|d| d == "typecheck"),
+        errors: Vec::new(),
+    };
+    checker.stmts(stmts);
+    if checker.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(checker.errors)
+    }
+}
+
+struct Binding {
+    constant: bool,
+    mutable: bool, // false when it was declared without mut under // klang:immutable
+    initialized: bool,
+    ty: Option<Type>,
+    span: Span,
+}
+
+// what is statically known about the type of an expression
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Int,
+    Number, // might or might not be an int
+    Fraction,
+    String,
+    Bool,
+    Vec,
+    Range,
+    Nada,
+    Unknown,
+}
+
+impl Kind {
+    fn of(ty: Type) -> Kind {
+        match ty {
+            Type::Int => Kind::Int,
+            Type::Float => Kind::Number,
+            Type::String => Kind::String,
+            Type::Bool => Kind::Bool,
+        }
+    }
+    fn fits(&self, ty: Type) -> bool {
+        //false only when the value can never be of the type
+        matches!(
+            (ty, self),
+            (_, Kind::Unknown)
+                | (Type::Int, Kind::Int | Kind::Number)
+                | (Type::Float, Kind::Int | Kind::Number | Kind::Fraction)
+                | (Type::String, Kind::String)
+                | (Type::Bool, Kind::Bool)
+        )
+    }
+    fn name(&self) -> &'static str {
+        match self {
+            Kind::Int => "int",
+            Kind::Number | Kind::Fraction => "float",
+            Kind::String => "string",
+            Kind::Bool => "bool",
+            Kind::Vec => "vector",
+            Kind::Range => "range",
+            Kind::Nada => "nada",
+            Kind::Unknown => "unknown",
+        }
+    }
+}
+
+struct Checker<'a> {
+    scopes: Vec<HashMap<String, Binding>>,
+    top_level: Vec<String>,
+    declared: &'a HashSet<String>,
+    functions: HashMap<String, (Vec<Param>, Option<Type>)>,
+    returns: Vec<(String, Option<Type>, bool)>, // the functions being checked, and if they are generators
+    immutable: bool,
+    typecheck: bool,
+    errors: Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+    fn stmts(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.stmt(stmt);
+        }
+    }
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Print(Value::String { printables, .. }, _) => {
+                for i in printables {
+                    self.expr(i);
+                }
+            }
+            Stmt::Print(..) => (),
+            Stmt::Block(stmts, _) => {
+                self.scopes.push(HashMap::new());
+                self.stmts(stmts);
+                self.scopes.pop();
+            }
+            Stmt::Expression(expr) => {
+                self.expr(expr);
+            }
+            Stmt::If {
+                condition,
+                block,
+                elseblock,
+                ..
+            } => {
+                self.expr(condition);
+                self.stmt(block);
+                if let Some(elseblock) = elseblock {
+                    self.stmt(elseblock);
+                }
+            }
+            Stmt::Var { pattern, value, .. } => {
+                let kind = match value {
+                    Some(value) => self.expr(value),
+                    None => Kind::Unknown,
+                };
+                self.pattern(pattern, value.is_some(), kind);
+            }
+            Stmt::Const {
+                name, ty, value, ..
+            } => {
+                let kind = self.expr(value);
+                if let Some(ty) = ty {
+                    self.mismatch(&format!("\"{}\"", name.lexeme), *ty, kind, name.span);
+                }
+                self.declare(name, true, false, true, *ty);
+            }
+            Stmt::While {
+                condition, block, ..
+            } => {
+                self.expr(condition);
+                self.stmt(block);
+            }
+            Stmt::For {
+                pattern,
+                iterable,
+                block,
+                ..
+            } => {
+                self.expr(iterable);
+                self.scopes.push(HashMap::new());
+                self.pattern(pattern, true, Kind::Unknown);
+                self.stmt(block);
+                self.scopes.pop();
+            }
+            Stmt::Fn {
+                name,
+                params,
+                ret,
+                body,
+                generator,
+                ..
+            } => {
+                //calling a generator gives an iterator, its type is the type of the values it yields
+                let call = if *generator { None } else { *ret };
+                self.functions
+                    .insert(name.lexeme.clone(), (params.clone(), call));
+                self.returns.push((name.lexeme.clone(), *ret, *generator));
+                self.scopes.push(HashMap::new());
+                for i in params {
+                    if let Some(default) = &i.default {
+                        let kind = self.expr(default);
+                        if let Some(ty) = i.ty {
+                            self.mismatch(&format!("\"{}\"", i.name.lexeme), ty, kind, i.name.span);
+                        }
+                    }
+                    self.declare(&i.name, false, i.mutable, true, i.ty);
+                }
+                self.stmt(body);
+                self.scopes.pop();
+                self.returns.pop();
+            }
+            Stmt::Try { block, name, catch } => {
+                self.stmt(block);
+                self.scopes.push(HashMap::new());
+                self.declare(name, false, false, true, None);
+                self.stmt(catch);
+                self.scopes.pop();
+            }
+            Stmt::Throw(expr, _) => {
+                self.expr(expr);
+            }
+            Stmt::Import(_) | Stmt::Use { .. } => (),
+            Stmt::Return(expr, span) => {
+                let kind = match expr {
+                    Some(expr) => self.expr(expr),
+                    None => Kind::Nada,
+                };
+                match self.returns.last().cloned() {
+                    Some((name, _, true)) if expr.is_some() => {
+                        self.error(&messages::RETURN_IN_GENERATOR, &[&name], *span)
+                    }
+                    Some((name, Some(ty), false)) => {
+                        self.mismatch(&format!("the return value of \"{name}\""), ty, kind, *span)
+                    }
+                    _ => (),
+                }
+            }
+            Stmt::Yield(expr, span) => {
+                let kind = self.expr(expr);
+                match self.returns.last().cloned() {
+                    Some((name, Some(ty), true)) => {
+                        self.mismatch(&format!("the values of \"{name}\""), ty, kind, *span)
+                    }
+                    Some((_, _, true)) => (),
+                    _ => self.error(&messages::YIELD_OUTSIDE_GENERATOR, &[], *span),
+                }
+            }
+        }
+    }
+    fn pattern(&mut self, pattern: &Pattern, initialized: bool, kind: Kind) {
+        match pattern {
+            Pattern::Identifier { name, mutable, ty } => {
+                if let Some(ty) = ty {
+                    self.mismatch(&format!("\"{}\"", name.lexeme), *ty, kind, name.span);
+                }
+                self.declare(name, false, *mutable, initialized, *ty)
+            }
+            Pattern::Vec { elements, rest, .. } => {
+                for i in elements {
+                    self.pattern(i, initialized, Kind::Unknown);
+                }
+                if let Some(rest) = rest {
+                    self.declare(rest, false, false, initialized, None);
+                }
+            }
+        }
+    }
+    fn expr(&mut self, expr: &Expr) -> Kind {
+        match expr {
+            Expr::Assign { name, value } => {
+                let kind = self.expr(value);
+                self.assign(name, kind);
+                kind
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.expr(left);
+                let right = self.expr(right);
+                match operator.tt {
+                    TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Modulo => {
+                        match (left, right) {
+                            (Kind::Int, Kind::Int) => Kind::Int,
+                            (Kind::Int | Kind::Number | Kind::Fraction, _)
+                            | (_, Kind::Int | Kind::Number | Kind::Fraction) => Kind::Number,
+                            _ => Kind::Unknown,
+                        }
+                    }
+                    TokenType::Slash => Kind::Number,
+                    _ => Kind::Bool,
+                }
+            }
+            Expr::Call {
+                callee,
+                arguments,
+                named,
+                native,
+                ..
+            } => {
+                let arguments: Vec<Kind> = arguments.iter().map(|i| self.expr(i)).collect();
+                let named: Vec<(&Token, Kind)> =
+                    named.iter().map(|(n, i)| (n, self.expr(i))).collect();
+                let name = match &**callee {
+                    Expr::Variable(t) => t,
+                    _ => return Kind::Unknown,
+                };
+                if *native {
+                    return Kind::Unknown;
+                }
+                let (params, ret) = match self.functions.get(&name.lexeme) {
+                    Some(x) => x.clone(),
+                    None => return Kind::Unknown,
+                };
+                let positional = params.iter().take_while(|p| !p.rest);
+                for (param, kind) in positional.zip(arguments) {
+                    if let Some(ty) = param.ty {
+                        self.mismatch(
+                            &format!("parameter \"{}\" of \"{}\"", param.name.lexeme, name.lexeme),
+                            ty,
+                            kind,
+                            name.span,
+                        );
+                    }
+                }
+                for (arg, kind) in named {
+                    if let Some(ty) = params
+                        .iter()
+                        .find(|p| p.name.lexeme == arg.lexeme)
+                        .and_then(|p| p.ty)
+                    {
+                        self.mismatch(
+                            &format!("parameter \"{}\" of \"{}\"", arg.lexeme, name.lexeme),
+                            ty,
+                            kind,
+                            arg.span,
+                        );
+                    }
+                }
+                match ret {
+                    Some(ty) => Kind::of(ty),
+                    None => Kind::Unknown,
+                }
+            }
+            Expr::Grouping(expr, _) => self.expr(expr),
+            Expr::Literal(Value::String { printables, .. }, _) => {
+                for i in printables {
+                    self.expr(i);
+                }
+                Kind::String
+            }
+            Expr::Literal(Value::Number(x), _) if x.fract() == 0.0 => Kind::Int,
+            Expr::Literal(Value::Number(_), _) => Kind::Fraction,
+            Expr::Literal(Value::Bool(_), _) => Kind::Bool,
+            Expr::Literal(..) => Kind::Unknown,
+            Expr::Unary {
+                operator,
+                expression,
+            } => {
+                let kind = self.expr(expression);
+                match operator.tt {
+                    TokenType::Bang => Kind::Bool,
+                    _ => kind,
+                }
+            }
+            Expr::Variable(name) => match self.binding(&name.lexeme) {
+                Some(binding) => binding.ty.map_or(Kind::Unknown, Kind::of),
+                None => {
+                    //scoping is dynamic, a name declared anywhere might be there when this runs
+                    if !self.declared.contains(&name.lexeme) {
+                        self.unknown(name);
+                    }
+                    Kind::Unknown
+                }
+            },
+            Expr::Range { min, max, step, .. } => {
+                self.expr(min);
+                self.expr(max);
+                if let Some(step) = step {
+                    self.expr(step);
+                }
+                Kind::Range
+            }
+            Expr::Vec(vec, _) => {
+                for i in vec {
+                    self.expr(i);
+                }
+                Kind::Vec
+            }
+            Expr::Wrap { value, .. } => {
+                self.expr(value);
+                Kind::Unknown
+            }
+            Expr::Unwrap(expr, _) => {
+                self.expr(expr);
+                Kind::Unknown
+            }
+        }
+    }
+    fn declare(
+        &mut self,
+        name: &Token,
+        constant: bool,
+        mutable: bool,
+        initialized: bool,
+        ty: Option<Type>,
+    ) {
+        if let Some(span) = self.constant(&name.lexeme) {
+            let message = &messages::REDECLARE_CONSTANT;
+            self.report(
+                KlangError::at(
+                    KlangError::CompileError,
+                    message,
+                    &[&name.lexeme],
+                    name.span,
+                )
+                .label(span, "declared"),
+            );
+            return;
+        }
+        self.scopes.last_mut().unwrap().insert(
+            name.lexeme.clone(),
+            Binding {
+                constant,
+                mutable: mutable || !self.immutable,
+                initialized,
+                ty,
+                span: name.span,
+            },
+        );
+    }
+    fn assign(&mut self, name: &Token, kind: Kind) {
+        let binding = match self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(&name.lexeme))
+        {
+            Some(x) => x,
+            None => return,
+        };
+        let diagnostic = if binding.constant {
+            let message = &messages::ASSIGN_TO_CONSTANT;
+            KlangError::at(
+                KlangError::CompileError,
+                message,
+                &[&name.lexeme],
+                name.span,
+            )
+        } else if !binding.mutable && binding.initialized {
+            let message = &messages::ASSIGN_TO_IMMUTABLE;
+            KlangError::at(
+                KlangError::CompileError,
+                message,
+                &[&name.lexeme],
+                name.span,
+            )
+            .note("// klang:immutable makes variables declared without mut immutable")
+            .help("declare it with mut to allow this")
+        } else {
+            binding.initialized = true;
+            if let Some(ty) = binding.ty {
+                self.mismatch(&format!("\"{}\"", name.lexeme), ty, kind, name.span);
+            }
+            return;
+        };
+        let span = binding.span;
+        self.report(diagnostic.label(span, "declared"));
+    }
+    fn unknown(&mut self, name: &Token) {
+        let visible = self.scopes.iter().flat_map(|scope| scope.keys());
+        let names = visible.chain(self.top_level.iter()).map(|x| x.as_str());
+        let found = suggest(&name.lexeme, names);
+        let message = &messages::UNKNOWN_VARIABLE;
+        let error = KlangError::at(
+            KlangError::CompileError,
+            message,
+            &[&name.lexeme],
+            name.span,
+        );
+        self.report(error.did_you_mean(found));
+    }
+    fn mismatch(&mut self, what: &str, ty: Type, kind: Kind, span: Span) {
+        if self.typecheck && !kind.fits(ty) {
+            let ty = ty.to_string();
+            self.error(&messages::MISMATCHED_TYPES, &[&ty, what, kind.name()], span);
+        }
+    }
+    fn binding(&self, name: &str) -> Option<&Binding> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+    fn constant(&self, name: &str) -> Option<Span> {
+        self.scopes
+            .iter()
+            .filter_map(|scope| scope.get(name))
+            .find(|binding| binding.constant)
+            .map(|binding| binding.span)
+    }
+    fn error(&mut self, message: &Message, args: &[&str], span: Span) {
+        self.report(KlangError::at(
+            KlangError::CompileError,
+            message,
+            args,
+            span,
+        ));
+    }
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.errors.push(diagnostic);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Note;
+    use crate::interpreter::modules::{link, MemoryLoader};
+
+    // checks the main file of a playground post with the directives given
+    fn check_errors(source: &str, directives: &[&str]) -> Result<(), Vec<Diagnostic>> {
+        let loader = MemoryLoader::split(source);
+        let (modules, _) = link(MemoryLoader::MAIN, &loader, 20).unwrap();
+        let main = &modules[0];
+        let directives: Vec<String> = directives.iter().map(|d| d.to_string()).collect();
+        check(&main.stmts, &directives, &[], &main.declared)
+    }
+
+    fn check_source(source: &str, directives: &[&str]) -> Result<(), String> {
+        check_errors(source, directives).map_err(lines)
+    }
+
+    // the errors one per line, the way the command line used to print them
+    fn lines(errors: Vec<Diagnostic>) -> String {
+        errors.iter().map(|error| format!("{error}\n")).collect()
+    }
+
+    // checks the source with the directives written in its own comments
+    fn typecheck(source: &str) -> Result<(), String> {
+        let (modules, _) = link(MemoryLoader::MAIN, &MemoryLoader::split(source), 20).unwrap();
+        let main = &modules[0];
+        check(&main.stmts, &main.directives, &[], &main.declared).map_err(lines)
+    }
+
+    // the span of the nth time name is written in the source
+    fn span(source: &str, name: &str, nth: usize) -> Span {
+        let start = source.match_indices(name).nth(nth).unwrap().0;
+        let line = source[..start].matches('\n').count() + 1;
+        Span::new(line, start, start + name.len())
+    }
+
+    #[test]
+    fn const_cant_be_assigned() {
+        let source = "const LIMIT = 10;\nLIMIT = 11;";
+        assert_eq!(
+            check_source(source, &[]).unwrap_err(),
+            "[CompileError] at line 2: cannot assign to constant \"LIMIT\" (declared at line 1)\n"
+        );
+    }
+
+    #[test]
+    fn const_cant_be_assigned_from_an_inner_scope() {
+        let source = "const LIMIT = 10;\nfn f() {\n    if true { LIMIT = 11; }\n}";
+        assert_eq!(
+            check_source(source, &[]).unwrap_err(),
+            "[CompileError] at line 3: cannot assign to constant \"LIMIT\" (declared at line 1)\n"
+        );
+    }
+
+    #[test]
+    fn const_cant_be_declared_again() {
+        let error =
+            "[CompileError] at line 2: cannot redeclare constant \"LIMIT\" (declared at line 1)\n";
+        let source = "const LIMIT = 10;\nlet LIMIT = 11;";
+        assert_eq!(check_source(source, &[]).unwrap_err(), error);
+        let source = "const LIMIT = 10;\nfn f(LIMIT) {}";
+        assert_eq!(check_source(source, &[]).unwrap_err(), error);
+    }
+
+    #[test]
+    fn immutable_let_cant_be_assigned() {
+        let source = "let count = 0;\ncount = 1;";
+        assert_eq!(
+            check_source(source, &["immutable"]).unwrap_err(),
+            "[CompileError] at line 2: cannot assign twice to immutable variable \"count\" \
+            (declared at line 1)\n  note: // klang:immutable makes variables declared without mut immutable\n  \
+            help: declare it with mut to allow this\n"
+        );
+    }
+
+    #[test]
+    fn let_mut_can_be_assigned() {
+        let source = "let mut count = 0;\ncount = 1;";
+        assert!(check_source(source, &["immutable"]).is_ok());
+    }
+
+    #[test]
+    fn let_can_be_assigned_without_the_directive() {
+        let source = "let count = 0;\ncount = 1;";
+        assert!(check_source(source, &[]).is_ok());
+    }
+
+    #[test]
+    fn immutable_let_can_be_initialized_later() {
+        let source = "let count;\ncount = 1;\ncount = 2;";
+        let error = check_source(source, &["immutable"]).unwrap_err();
+        assert!(error.starts_with("[CompileError] at line 3: cannot assign twice"));
+        assert_eq!(error.matches("[CompileError]").count(), 1);
+    }
+
+    #[test]
+    fn let_annotations_are_typechecked() {
+        let source = "// klang:typecheck\nlet x: int = \"hi\";";
+        assert_eq!(
+            typecheck(source).unwrap_err(),
+            "[CompileError] at line 2: mismatched types: expected int for \"x\" but found string\n"
+        );
+        let source = "// klang:typecheck\nlet x: float = true;";
+        assert_eq!(
+            typecheck(source).unwrap_err(),
+            "[CompileError] at line 2: mismatched types: expected float for \"x\" but found bool\n"
+        );
+        let source = "// klang:typecheck\nlet x: string = 2.5;";
+        assert_eq!(
+            typecheck(source).unwrap_err(),
+            "[CompileError] at line 2: mismatched types: expected string for \"x\" but found float\n"
+        );
+        let source = "// klang:typecheck\nlet x: int = 2.5;";
+        assert!(typecheck(source).is_err());
+        let source = "// klang:typecheck\nlet x: float = 2;\nlet y: int = 4 / 2;";
+        assert!(typecheck(source).is_ok());
+    }
+
+    #[test]
+    fn parameter_annotations_are_typechecked() {
+        let source = "// klang:typecheck\nfn add(a: int, b: int) {}\nadd(1, \"2\");";
+        assert_eq!(
+            typecheck(source).unwrap_err(),
+            "[CompileError] at line 3: mismatched types: expected int for parameter \"b\" of \"add\" but found string\n"
+        );
+        let source = "// klang:typecheck\nfn greet(name: string) {}\ngreet(name = 1.5);";
+        assert_eq!(
+            typecheck(source).unwrap_err(),
+            "[CompileError] at line 3: mismatched types: expected string for parameter \"name\" of \"greet\" but found float\n"
+        );
+        let source = "// klang:typecheck\nfn half(x: float = \"one\") {}";
+        assert_eq!(
+            typecheck(source).unwrap_err(),
+            "[CompileError] at line 2: mismatched types: expected float for \"x\" but found string\n"
+        );
+    }
+
+    #[test]
+    fn return_annotations_are_typechecked() {
+        let source = "// klang:typecheck\nfn name() -> string {\n    return 1;\n}";
+        assert_eq!(
+            typecheck(source).unwrap_err(),
+            "[CompileError] at line 3: mismatched types: expected string for the return value of \"name\" but found int\n"
+        );
+        let source =
+            "// klang:typecheck\nfn count() -> int {\n    return 1;\n}\nlet x: string = count();";
+        assert_eq!(
+            typecheck(source).unwrap_err(),
+            "[CompileError] at line 5: mismatched types: expected string for \"x\" but found int\n"
+        );
+    }
+
+    #[test]
+    fn annotations_arent_typechecked_without_the_directive() {
+        let source =
+            "let x: int = \"hi\";\nfn add(a: int) -> string {\n    return 1;\n}\nadd(2.5);";
+        assert!(typecheck(source).is_ok());
+        assert!(typecheck(&format!("// klang:typecheck\n{source}")).is_err());
+    }
+
+    // the help lines under the only error of the source
+    fn unknown_variable(source: &str, name: &str) -> Vec<String> {
+        let errors = check_errors(source, &[]).unwrap_err();
+        assert_eq!(errors.len(), 1, "{errors:?}");
+        assert_eq!(errors[0].code, Some(messages::UNKNOWN_VARIABLE.code));
+        assert_eq!(errors[0].span, span(source, name, 0));
+        errors[0]
+            .notes
+            .iter()
+            .map(|note| match note {
+                Note::Help(help) => help.clone(),
+                note => panic!("{note:?} is not a help"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unknown_variables_are_found_before_running() {
+        let source = "fn f(values) {\n    return valeus;\n}";
+        assert_eq!(
+            unknown_variable(source, "valeus"),
+            vec!["did you mean \"values\"?"]
+        );
+        let source = "fn f() { return totl; }\nlet total = 0;";
+        assert_eq!(
+            unknown_variable(source, "totl"),
+            vec!["did you mean \"total\"?"]
+        );
+        assert!(unknown_variable("print(\"{nothing}\");", "nothing").is_empty());
+    }
+
+    #[test]
+    fn names_declared_anywhere_might_be_read() {
+        //show reads the other of whichever function calls it
+        let source = "fn show() { return other; }\nfn f() { let other = 1; show(); }";
+        assert!(check_source(source, &[]).is_ok());
+        let source = "fn f() { return later; }\nlater = 1;";
+        assert!(check_source(source, &[]).is_ok());
+    }
+}
@@ -1,296 +1,472 @@
-use super::opcode::OpCode;
+use super::native::NativeRegistry;
+use super::opcode::{OpCode, Param};
+use crate::error::{suggest, Diagnostic, KlangError, Span};
 use crate::interpreter::expr::Expr;
-use crate::interpreter::stmt::Stmt;
+use crate::interpreter::stmt::{Pattern, Stmt};
+use crate::messages;
 use crate::scanner::{TokenType, Value};
 
 #[derive(Debug, Clone)]
 pub struct Chunk {
     pub code: Vec<OpCode>,
-    pub lines: Vec<usize>,
+    pub spans: Vec<Span>,
 }
 
 impl Chunk {
-    pub fn new(stuff: (Vec<OpCode>, Vec<usize>)) -> Chunk {
+    pub fn new(stuff: (Vec<OpCode>, Vec<Span>)) -> Chunk {
         Chunk {
             code: stuff.0,
-            lines: stuff.1,
+            spans: stuff.1,
         }
     }
 }
 
-pub fn compile(stmts: Vec<Stmt>) -> (Vec<OpCode>, Vec<usize>) {
+pub fn resolve(chunk: &mut Chunk, natives: &NativeRegistry) -> Result<(), Vec<Diagnostic>> {
+    //replaces the names of native calls with the index of the native, and finds calls to functions no one declared
+    let functions = functions(&chunk.code);
+    let mut errors = Vec::new();
+    for (op, span) in chunk.code.iter_mut().zip(&chunk.spans) {
+        match op {
+            OpCode::NativeCall(name, args) => match natives.resolve(name) {
+                Some(i) => *op = OpCode::Native(i, *args),
+                None => {
+                    let message = &messages::UNKNOWN_NATIVE;
+                    let error = KlangError::at(KlangError::CompileError, message, &[name], *span);
+                    errors.push(if functions.contains(name) {
+                        error.help(&messages::OWN_FUNCTION.text(&[name]))
+                    } else {
+                        let native = suggest_native(name, natives).map(|x| format!("std::{x}"));
+                        error.did_you_mean(native.as_deref())
+                    });
+                }
+            },
+            OpCode::Call(name, ..) if !functions.contains(name) => {
+                let message = &messages::UNKNOWN_FUNCTION;
+                let error = KlangError::at(KlangError::CompileError, message, &[name], *span);
+                errors.push(match natives.resolve(name) {
+                    Some(i) => error.help(&messages::CALL_NATIVE.text(&[&natives.get(i).name])),
+                    None => error.did_you_mean(suggest(name, functions.iter().map(|f| f.as_str()))),
+                });
+            }
+            _ => (),
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+fn functions(code: &[OpCode]) -> Vec<String> {
+    //the name of every function the program declares, the store right after its body
+    let mut names = Vec::new();
+    for (i, op) in code.iter().enumerate() {
+        if !matches!(op, OpCode::Fn(..)) {
+            continue;
+        }
+        let mut depth = 0;
+        for (j, op) in code.iter().enumerate().skip(i + 1) {
+            match op {
+                OpCode::Scope => depth += 1,
+                OpCode::EndScope => depth -= 1,
+                _ => continue,
+            }
+            if depth == 0 {
+                if let Some(OpCode::Store(name)) = code.get(j + 1) {
+                    names.push(name.clone());
+                }
+                break;
+            }
+        }
+    }
+    names
+}
+
+fn suggest_native<'a>(name: &str, natives: &'a NativeRegistry) -> Option<&'a str> {
+    //natives are also called without their module, so those names are compared without it
+    let short = |native: &'a str| match name.contains("::") {
+        true => native,
+        false => native.rsplit("::").next().unwrap_or(native),
+    };
+    let found = suggest(name, natives.names().map(short))?;
+    natives.names().find(|native| short(native) == found)
+}
+
+pub fn compile(stmts: Vec<Stmt>) -> (Vec<OpCode>, Vec<Span>) {
     let mut code: Vec<OpCode> = Vec::new();
-    let mut lines: Vec<usize> = Vec::new();
+    let mut spans: Vec<Span> = Vec::new();
     for stmt in stmts {
         match stmt {
-            Stmt::Print(x, line) => {
+            Stmt::Print(x, span) => {
                 match x {
                     Value::String { string, printables } => {
                         for i in printables {
-                            dump(&mut code, &mut lines, compile_expr(i))
+                            dump(&mut code, &mut spans, compile_expr(i))
                         }
                         code.push(OpCode::Constant(Value::String {
                             string,
                             printables: Vec::new(),
                         }));
-                        lines.push(line);
+                        spans.push(span);
                     }
                     _ => {
                         code.push(OpCode::Constant(x));
-                        lines.push(line);
+                        spans.push(span);
                     }
                 };
                 code.push(OpCode::Print);
-                lines.push(line);
+                spans.push(span);
             }
             Stmt::Block(stmts, (start, end)) => {
                 code.push(OpCode::Scope);
-                lines.push(start);
-                dump(&mut code, &mut lines, compile(stmts));
+                spans.push(start);
+                dump(&mut code, &mut spans, compile(stmts));
                 code.pop();
-                lines.pop();
+                spans.pop();
                 code.push(OpCode::EndScope);
-                lines.push(end);
+                spans.push(end);
             }
-            Stmt::Expression(expr) => dump(&mut code, &mut lines, compile_expr(expr)),
+            Stmt::Expression(expr) => dump(&mut code, &mut spans, compile_expr(expr)),
             Stmt::If {
                 condition,
                 block,
                 elseblock,
-                lines: line,
+                spans: span,
             } => {
-                dump(&mut code, &mut lines, compile_expr(condition));
+                dump(&mut code, &mut spans, compile_expr(condition));
                 code.push(OpCode::LogicalNot); //jump if false
-                lines.push(line.0);
+                spans.push(span.0);
                 let b_vec: Vec<Stmt> = vec![*block];
                 let blok = compile(b_vec);
                 code.push(OpCode::JumpIf(blok.0.len() as i32 - 1, elseblock.is_none()));
-                lines.push(line.0);
-                dump(&mut code, &mut lines, blok);
+                spans.push(span.0);
+                dump(&mut code, &mut spans, blok);
                 code.pop();
-                lines.pop();
-                if elseblock.is_some() {
+                spans.pop();
+                if let Some(elseblock) = elseblock {
                     code.push(OpCode::LogicalNot); //jump if true
-                    lines.push(line.1.unwrap());
-                    let b_vec: Vec<Stmt> = vec![*elseblock.unwrap()];
+                    spans.push(span.1.unwrap());
+                    let b_vec: Vec<Stmt> = vec![*elseblock];
                     let blok = compile(b_vec);
                     code.push(OpCode::JumpIf(blok.0.len() as i32 - 1, true));
-                    lines.push(line.1.unwrap());
-                    dump(&mut code, &mut lines, blok);
+                    spans.push(span.1.unwrap());
+                    dump(&mut code, &mut spans, blok);
                     code.pop();
-                    lines.pop();
+                    spans.pop();
                 }
             }
-            Stmt::Var { name, value } => {
+            Stmt::Var { pattern, value, .. } => {
                 match value {
-                    Some(value) => dump(&mut code, &mut lines, compile_expr(value)),
+                    Some(value) => dump(&mut code, &mut spans, compile_expr(value)),
                     None => {
                         code.push(OpCode::Constant(Value::None));
-                        lines.push(name.line)
+                        spans.push(pattern.span())
                     }
                 }
-                code.push(OpCode::Store(name.lexeme));
-                lines.push(name.line)
+                dump(&mut code, &mut spans, compile_pattern(pattern));
+            }
+            Stmt::Const {
+                name, ty, value, ..
+            } => {
+                dump(&mut code, &mut spans, compile_expr(value));
+                code.push(OpCode::Store(name.lexeme.clone()));
+                spans.push(name.span);
+                if let Some(ty) = ty {
+                    code.push(OpCode::Type(name.lexeme, ty));
+                    spans.push(name.span);
+                }
             }
             Stmt::While {
                 condition,
                 block,
-                line,
+                span,
             } => {
                 let condition = compile_expr(condition);
                 let con_len = condition.0.len() as i32;
-                dump(&mut code, &mut lines, condition);
+                dump(&mut code, &mut spans, condition);
                 code.push(OpCode::LogicalNot); //jump if false
-                lines.push(line);
+                spans.push(span);
                 let b_vec: Vec<Stmt> = vec![*block];
                 let blok = compile(b_vec);
                 let block_len = blok.0.len() as i32;
                 code.push(OpCode::JumpIf(block_len, true));
-                lines.push(line);
-                dump(&mut code, &mut lines, blok);
+                spans.push(span);
+                dump(&mut code, &mut spans, blok);
                 code.pop();
-                lines.pop();
+                spans.pop();
                 code.push(OpCode::Jump(-(block_len + con_len + 2)));
-                lines.push(line);
+                spans.push(span);
             }
             Stmt::For {
-                identifier,
+                pattern,
                 iterable,
                 block,
-                line,
+                span,
             } => {
-                dump(&mut code, &mut lines, compile_expr(iterable));
-                code.push(OpCode::For);
-                lines.push(line);
-                code.push(OpCode::Scope);
-                lines.push(line);
-                code.push(OpCode::Store(identifier.lexeme));
-                lines.push(line);
+                dump(&mut code, &mut spans, compile_expr(iterable));
+                code.push(OpCode::Iter);
+                spans.push(span);
+                let store = compile_pattern(pattern);
+                let store_len = store.0.len() as i32;
                 let b_vec: Vec<Stmt> = vec![*block];
                 let mut blok = compile(b_vec);
                 blok.0.remove(0);
                 blok.1.remove(0);
+                blok.0.pop();
+                blok.1.pop();
                 let block_len = blok.0.len() as i32;
-                code.push(OpCode::JumpIf(block_len, true));
-                lines.push(line);
-                dump(&mut code, &mut lines, blok);
-                code.pop();
-                lines.pop();
-                code.push(OpCode::Jump(-(block_len + 4)));
-                lines.push(line);
+                code.push(OpCode::Next(store_len + block_len + 2));
+                spans.push(span);
+                code.push(OpCode::Scope); //skipped by next, which opens the scope itself
+                spans.push(span);
+                dump(&mut code, &mut spans, store);
+                dump(&mut code, &mut spans, blok);
+                code.push(OpCode::Jump(-(store_len + block_len + 3)));
+                spans.push(span);
             }
-            Stmt::Fn { name, params, body } => {
-                code.push(OpCode::Fn);
-                lines.push(name.line);
+            Stmt::Fn {
+                name,
+                params,
+                ret,
+                body,
+                generator,
+                ..
+            } => {
+                code.push(OpCode::Fn(
+                    params
+                        .iter()
+                        .map(|p| Param {
+                            name: p.name.lexeme.clone(),
+                            ty: p.ty,
+                            default: p.default.is_some(),
+                            rest: p.rest,
+                        })
+                        .collect(),
+                    ret,
+                    generator,
+                ));
+                spans.push(name.span);
+                let b_vec: Vec<Stmt> = vec![*body];
+                let mut blok = compile(b_vec);
+                code.push(blok.0.remove(0)); //the function's scope
+                spans.push(blok.1.remove(0));
                 for i in params {
-                    code.push(OpCode::Store(i.lexeme));
-                    lines.push(name.line);
+                    if let Some(default) = i.default {
+                        let default = compile_expr(default);
+                        code.push(OpCode::Default(
+                            i.name.lexeme.clone(),
+                            default.0.len() as i32 + 1,
+                        ));
+                        spans.push(i.name.span);
+                        dump(&mut code, &mut spans, default);
+                        code.push(OpCode::Arg(i.name.lexeme));
+                        spans.push(i.name.span);
+                    }
                 }
-                let b_vec: Vec<Stmt> = vec![*body];
-                dump(&mut code, &mut lines, compile(b_vec));
+                dump(&mut code, &mut spans, blok);
                 code.pop();
-                lines.pop();
+                spans.pop();
                 code.push(OpCode::Store(name.lexeme));
-                lines.push(name.line);
+                spans.push(name.span);
+            }
+            Stmt::Try { block, name, catch } => {
+                let mut blok = compile(vec![*block]);
+                blok.0.pop();
+                blok.1.pop();
+                let mut catch = compile(vec![*catch]);
+                catch.0.pop();
+                catch.1.pop();
+                catch.0.insert(1, OpCode::Store(name.lexeme));
+                catch.1.insert(1, name.span);
+                let block_len = blok.0.len() as i32;
+                code.push(OpCode::Try(block_len + 3)); //the scope of the catch block
+                spans.push(name.span);
+                dump(&mut code, &mut spans, blok);
+                code.push(OpCode::EndTry);
+                spans.push(name.span);
+                code.push(OpCode::Jump(catch.0.len() as i32));
+                spans.push(name.span);
+                dump(&mut code, &mut spans, catch);
+            }
+            Stmt::Throw(expr, span) => {
+                dump(&mut code, &mut spans, compile_expr(expr));
+                code.push(OpCode::Throw);
+                spans.push(span);
+            }
+            Stmt::Import(_) | Stmt::Use { .. } => {} //resolved by the linker
+            Stmt::Yield(expr, span) => {
+                dump(&mut code, &mut spans, compile_expr(expr));
+                code.push(OpCode::Yield);
+                spans.push(span);
             }
-            Stmt::Return(expr, line) => match expr {
+            Stmt::Return(expr, span) => match expr {
                 Some(expr) => {
-                    dump(&mut code, &mut lines, compile_expr(expr));
+                    dump(&mut code, &mut spans, compile_expr(expr));
                     code.push(OpCode::Return(true));
-                    lines.push(line)
+                    spans.push(span)
                 }
                 None => {
                     code.push(OpCode::Return(false));
-                    lines.push(line)
+                    spans.push(span)
                 }
             },
         }
     }
     code.push(OpCode::Eof);
-    lines.push(0);
-    for (i, op) in code.clone().into_iter().enumerate() {
-        if matches!(op, OpCode::Call(_)) {
-            if let OpCode::Call(x) = &code[i] {
-                code[i] = OpCode::Call(x.clone());
-            }
-        }
-    }
-    (code, lines)
+    spans.push(Span::default());
+    (code, spans)
 }
 
-pub fn compile_expr(expr: Expr) -> (Vec<OpCode>, Vec<usize>) {
+pub fn compile_expr(expr: Expr) -> (Vec<OpCode>, Vec<Span>) {
     let mut code: Vec<OpCode> = Vec::new();
-    let mut lines: Vec<usize> = Vec::new();
-
+    let mut spans: Vec<Span> = Vec::new();
+    let whole = expr.span();
     match expr {
         Expr::Assign { name, value } => {
-            dump(&mut code, &mut lines, compile_expr(*value));
+            dump(&mut code, &mut spans, compile_expr(*value));
             code.push(OpCode::Store(name.lexeme));
-            lines.push(name.line)
+            spans.push(name.span)
         }
         Expr::Binary {
             left,
             operator,
             right,
         } => {
-            dump(&mut code, &mut lines, compile_expr(*left));
-            dump(&mut code, &mut lines, compile_expr(*right));
+            dump(&mut code, &mut spans, compile_expr(*left));
+            dump(&mut code, &mut spans, compile_expr(*right));
             code.push(bin(operator.tt));
-            lines.push(operator.line)
+            spans.push(whole)
         }
         Expr::Call {
             callee,
             arguments,
+            named,
             native,
+            ..
         } => {
-            let line;
             let len = arguments.len() as i32;
             for arg_expr in arguments {
-                dump(&mut code, &mut lines, compile_expr(arg_expr));
+                dump(&mut code, &mut spans, compile_expr(arg_expr));
             }
+            let callee = match *callee {
+                Expr::Variable(t) => t,
+                _ => unreachable!(),
+            };
             if native {
-                code.push(OpCode::NativeCall(
-                    match *callee {
-                        Expr::Variable(t) => {
-                            lines.push(t.line);
-                            line = t.line;
-                            t.lexeme
-                        }
-                        _ => unreachable!(),
-                    },
-                    len,
-                ));
+                code.push(OpCode::NativeCall(callee.lexeme, len));
             } else {
-                code.push(OpCode::Call(match *callee {
-                    Expr::Variable(t) => {
-                        lines.push(t.line);
-                        line = t.line;
-                        t.lexeme
-                    }
-                    _ => unreachable!(),
-                }));
+                let mut names: Vec<String> = Vec::new();
+                for (name, arg_expr) in named {
+                    dump(&mut code, &mut spans, compile_expr(arg_expr));
+                    names.push(name.lexeme);
+                }
+                code.push(OpCode::Call(callee.lexeme, len, names));
             }
-            lines.push(line);
+            spans.push(whole);
         }
-        Expr::Grouping(expression) => dump(&mut code, &mut lines, compile_expr(*expression)),
-        Expr::Literal(x, line) => match x {
+        Expr::Grouping(expression, _) => dump(&mut code, &mut spans, compile_expr(*expression)),
+        Expr::Literal(x, span) => match x {
             Value::String { string, printables } => {
                 for i in printables {
-                    dump(&mut code, &mut lines, compile_expr(i))
+                    dump(&mut code, &mut spans, compile_expr(i))
                 }
                 code.push(OpCode::Constant(Value::String {
                     string,
                     printables: Vec::new(),
                 }));
-                lines.push(line);
+                spans.push(span);
             }
             _ => {
                 code.push(OpCode::Constant(x));
-                lines.push(line);
+                spans.push(span);
             }
         },
         Expr::Range {
             min,
             max,
             step,
-            line,
+            inclusive,
+            ..
         } => match step {
             Some(x) => {
-                dump(&mut code, &mut lines, compile_expr(*min));
-                dump(&mut code, &mut lines, compile_expr(*max));
-                dump(&mut code, &mut lines, compile_expr(*x));
-                code.push(OpCode::Range(true));
-                lines.push(line);
+                dump(&mut code, &mut spans, compile_expr(*min));
+                dump(&mut code, &mut spans, compile_expr(*max));
+                dump(&mut code, &mut spans, compile_expr(*x));
+                code.push(OpCode::Range(true, inclusive));
+                spans.push(whole);
             }
             None => {
-                dump(&mut code, &mut lines, compile_expr(*min));
-                dump(&mut code, &mut lines, compile_expr(*max));
-                code.push(OpCode::Range(false));
-                lines.push(line);
+                dump(&mut code, &mut spans, compile_expr(*min));
+                dump(&mut code, &mut spans, compile_expr(*max));
+                code.push(OpCode::Range(false, inclusive));
+                spans.push(whole);
             }
         },
         Expr::Unary {
             operator,
             expression,
         } => {
-            dump(&mut code, &mut lines, compile_expr(*expression));
+            dump(&mut code, &mut spans, compile_expr(*expression));
             code.push(un(operator.tt));
-            lines.push(operator.line);
+            spans.push(whole);
         }
         Expr::Variable(name) => {
             code.push(OpCode::Load(name.lexeme));
-            lines.push(name.line)
+            spans.push(name.span)
         }
-        Expr::Vec(vec) => {
+        Expr::Vec(vec, span) => {
             let len = vec.len();
             for i in vec {
-                dump(&mut code, &mut lines, compile_expr(i));
+                dump(&mut code, &mut spans, compile_expr(i));
             }
             code.push(OpCode::Iterable(len as i32));
-            lines.push(0);
+            spans.push(span);
+        }
+        Expr::Wrap { variant, value } => {
+            dump(&mut code, &mut spans, compile_expr(*value));
+            code.push(OpCode::Wrap(variant.tt));
+            spans.push(whole);
+        }
+        Expr::Unwrap(expr, span) => {
+            dump(&mut code, &mut spans, compile_expr(*expr));
+            code.push(OpCode::Unwrap);
+            spans.push(span);
+        }
+    }
+    (code, spans)
+}
+
+pub fn compile_pattern(pattern: Pattern) -> (Vec<OpCode>, Vec<Span>) {
+    //stores the value on top of the stack into the variables of the pattern
+    let mut code: Vec<OpCode> = Vec::new();
+    let mut spans: Vec<Span> = Vec::new();
+    match pattern {
+        Pattern::Identifier { name, ty, .. } => {
+            code.push(OpCode::Store(name.lexeme.clone()));
+            spans.push(name.span);
+            if let Some(ty) = ty {
+                code.push(OpCode::Type(name.lexeme, ty));
+                spans.push(name.span);
+            }
+        }
+        Pattern::Vec {
+            elements,
+            rest,
+            span,
+        } => {
+            code.push(OpCode::Destructure(elements.len() as i32, rest.is_some()));
+            spans.push(span);
+            for i in elements {
+                dump(&mut code, &mut spans, compile_pattern(i));
+            }
+            if let Some(rest) = rest {
+                code.push(OpCode::Store(rest.lexeme));
+                spans.push(rest.span);
+            }
         }
     }
-    (code, lines)
+    (code, spans)
 }
 
 pub fn bin(operator: TokenType) -> OpCode {
@@ -320,11 +496,11 @@ pub fn un(operator: TokenType) -> OpCode {
     }
 }
 
-pub fn dump(main: &mut Vec<OpCode>, lines: &mut Vec<usize>, se: (Vec<OpCode>, Vec<usize>)) {
+pub fn dump(main: &mut Vec<OpCode>, spans: &mut Vec<Span>, se: (Vec<OpCode>, Vec<Span>)) {
     for i in se.0.into_iter() {
         main.push(i);
     }
     for i in se.1.into_iter() {
-        lines.push(i);
+        spans.push(i);
     }
 }
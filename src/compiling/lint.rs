@@ -0,0 +1,651 @@
+use crate::error::{Diagnostic, KlangError, Note, Severity, Span};
+use crate::interpreter::expr::Expr;
+use crate::interpreter::modules::Module;
+use crate::interpreter::stmt::{Pattern, Stmt};
+use crate::messages::{self, Message};
+use crate::scanner::{Token, TokenType, Value};
+use std::collections::{HashMap, HashSet};
+
+// walks the checked ast for code that runs but is probably not what was meant, and warns about it.
+// each file turns lints off with // klang:allow(name) or into errors with // klang:deny(name)
+pub fn lint(modules: &[Module]) -> Vec<Diagnostic> {
+    let mut linter = Linter {
+        scopes: Vec::new(),
+        library: false,
+        levels: HashMap::new(),
+        globals: HashSet::new(),
+        functions: Vec::new(),
+        called: HashSet::new(),
+        strings: HashSet::new(),
+        reads: HashSet::new(),
+        unused: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    for (i, module) in modules.iter().enumerate() {
+        //the top level names of imported modules are what they export, they are used from outside
+        linter.library = i + 1 < modules.len();
+        linter.module(module);
+    }
+    linter.finish()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Lint {
+    UnusedVariables,
+    UnusedFunctions,
+    Unreachable,
+    Shadowing,
+    Undeclared,
+    ConstantCondition,
+    EmptyBlock,
+}
+
+impl Lint {
+    const ALL: [Lint; 7] = [
+        Lint::UnusedVariables,
+        Lint::UnusedFunctions,
+        Lint::Unreachable,
+        Lint::Shadowing,
+        Lint::Undeclared,
+        Lint::ConstantCondition,
+        Lint::EmptyBlock,
+    ];
+    fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariables => "unused_variables",
+            Lint::UnusedFunctions => "unused_functions",
+            Lint::Unreachable => "unreachable",
+            Lint::Shadowing => "shadowing",
+            Lint::Undeclared => "undeclared",
+            Lint::ConstantCondition => "constant_condition",
+            Lint::EmptyBlock => "empty_block",
+        }
+    }
+    fn message(self) -> &'static Message {
+        match self {
+            Lint::UnusedVariables => &messages::UNUSED_VARIABLE,
+            Lint::UnusedFunctions => &messages::UNUSED_FUNCTION,
+            Lint::Unreachable => &messages::UNREACHABLE,
+            Lint::Shadowing => &messages::SHADOWED,
+            Lint::Undeclared => &messages::UNDECLARED_ASSIGNMENT,
+            Lint::ConstantCondition => &messages::CONSTANT_CONDITION,
+            Lint::EmptyBlock => &messages::EMPTY_BLOCK,
+        }
+    }
+    fn named(name: &str) -> Vec<Lint> {
+        //unused is both unused lints, all is every lint
+        Lint::ALL
+            .into_iter()
+            .filter(|lint| {
+                name == "all"
+                    || lint.name() == name
+                    || (name == "unused" && lint.name().starts_with("unused_"))
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+struct Binding {
+    name: String,
+    span: Span,
+    used: bool,
+    level: Level, // of unused_variables in the file it is declared in
+}
+
+struct Scope {
+    bindings: Vec<Binding>,
+    function: bool, // the outermost scope of a function body, names outside it are not shadowed
+}
+
+struct Linter {
+    scopes: Vec<Scope>,
+    library: bool,                // an imported module, not the main file
+    levels: HashMap<Lint, Level>, // of the file being linted
+    globals: HashSet<String>,     // declared at the top level of the file being linted
+    functions: Vec<(String, Span, Level)>,
+    called: HashSet<String>,
+    strings: HashSet<String>, // natives call functions back by their name
+    reads: HashSet<String>,   // of names not declared where they are read, found at runtime
+    unused: Vec<(String, Span, Level)>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter {
+    fn module(&mut self, module: &Module) {
+        self.levels.clear();
+        for directive in module.directives.iter() {
+            let (level, names) = match (
+                directive.strip_prefix("allow("),
+                directive.strip_prefix("deny("),
+            ) {
+                (Some(names), _) => (Level::Allow, names),
+                (_, Some(names)) => (Level::Deny, names),
+                _ => continue,
+            };
+            for name in names.trim_end_matches(')').split(',') {
+                for lint in Lint::named(name.trim()) {
+                    self.levels.insert(lint, level);
+                }
+            }
+        }
+        self.globals = module
+            .stmts
+            .iter()
+            .flat_map(|stmt| match stmt {
+                Stmt::Var { pattern, .. } => pattern.names(),
+                Stmt::Const { name, .. } => vec![name.lexeme.clone()],
+                _ => vec![],
+            })
+            .collect();
+        self.scopes.push(Scope {
+            bindings: Vec::new(),
+            function: true,
+        });
+        self.stmts(&module.stmts);
+        let scope = self.scopes.pop().unwrap();
+        if !self.library {
+            self.close(scope);
+        }
+    }
+    fn finish(mut self) -> Vec<Diagnostic> {
+        for (name, span, level) in std::mem::take(&mut self.unused) {
+            if !self.reads.contains(&name) {
+                self.report(Lint::UnusedVariables, level, &[&name], span, None);
+            }
+        }
+        for (name, span, level) in std::mem::take(&mut self.functions) {
+            let short = name.rsplit("::").next().unwrap_or(&name);
+            let named = self.strings.contains(&name) || self.strings.contains(short);
+            if !self.called.contains(&name) && !named && !short.starts_with('_') {
+                self.report(Lint::UnusedFunctions, level, &[&name], span, None);
+            }
+        }
+        let mut diagnostics = self.diagnostics;
+        diagnostics.sort_by_key(|d| (d.span.line, d.span.start));
+        //the first warning of each lint tells how to turn it off
+        let mut told = HashSet::new();
+        for diagnostic in diagnostics.iter_mut() {
+            if diagnostic.severity == Severity::Warning && told.insert(diagnostic.code) {
+                if let Some(lint) = Lint::ALL
+                    .into_iter()
+                    .find(|lint| Some(lint.message().code) == diagnostic.code)
+                {
+                    diagnostic.notes.push(Note::Note(format!(
+                        "// klang:allow({}) turns this warning off",
+                        lint.name()
+                    )));
+                }
+            }
+        }
+        diagnostics
+    }
+    fn stmts(&mut self, stmts: &[Stmt]) {
+        let mut ended: Option<(&str, Span)> = None;
+        for stmt in stmts {
+            if let Some((after, span)) = ended.take() {
+                let label = Some((span, format!("the block ends at this {after}")));
+                self.warn(Lint::Unreachable, &[after], stmt.span(), label);
+            }
+            self.stmt(stmt);
+            match stmt {
+                Stmt::Return(_, span) => ended = Some(("return", *span)),
+                Stmt::Throw(_, span) => ended = Some(("throw", *span)),
+                _ => (),
+            }
+        }
+    }
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Print(value, _) => self.value(value),
+            Stmt::Block(stmts, _) => {
+                self.push(false);
+                self.stmts(stmts);
+                self.pop();
+            }
+            Stmt::Expression(expr) => self.expr(expr),
+            Stmt::If {
+                condition,
+                block,
+                elseblock,
+                spans,
+            } => {
+                self.expr(condition);
+                if let Some(always) = constant(condition) {
+                    self.warn(
+                        Lint::ConstantCondition,
+                        &[&always.to_string()],
+                        condition.span(),
+                        None,
+                    );
+                }
+                self.empty(block, "if");
+                self.stmt(block);
+                if let Some(elseblock) = elseblock {
+                    if spans.1.is_some() {
+                        self.empty(elseblock, "else");
+                    }
+                    self.stmt(elseblock);
+                }
+            }
+            Stmt::Var { pattern, value, .. } => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+                self.pattern(pattern);
+            }
+            Stmt::Const { name, value, .. } => {
+                self.expr(value);
+                self.declare(name);
+            }
+            Stmt::While {
+                condition, block, ..
+            } => {
+                self.expr(condition);
+                //while true is how loops that end with return or throw are written
+                if constant(condition) == Some(false) {
+                    self.warn(Lint::ConstantCondition, &["false"], condition.span(), None);
+                }
+                self.empty(block, "while");
+                self.stmt(block);
+            }
+            Stmt::For {
+                pattern,
+                iterable,
+                block,
+                ..
+            } => {
+                self.expr(iterable);
+                self.push(false);
+                self.pattern(pattern);
+                self.empty(block, "for");
+                self.stmt(block);
+                self.pop();
+            }
+            Stmt::Fn {
+                name, params, body, ..
+            } => {
+                if !(self.library && self.scopes.len() == 1) {
+                    let level = self.level(Lint::UnusedFunctions);
+                    self.functions.push((name.lexeme.clone(), name.span, level));
+                }
+                self.push(true);
+                for i in params {
+                    if let Some(default) = &i.default {
+                        self.expr(default);
+                    }
+                    self.declare(&i.name);
+                }
+                self.stmt(body);
+                self.pop();
+            }
+            Stmt::Try { block, name, catch } => {
+                self.empty(block, "try");
+                self.stmt(block);
+                self.push(false);
+                self.declare(name);
+                self.empty(catch, "catch");
+                self.stmt(catch);
+                self.pop();
+            }
+            Stmt::Return(expr, _) => {
+                if let Some(expr) = expr {
+                    self.expr(expr);
+                }
+            }
+            Stmt::Yield(expr, _) | Stmt::Throw(expr, _) => self.expr(expr),
+            Stmt::Import(_) | Stmt::Use { .. } => (),
+        }
+    }
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Identifier { name, .. } => self.declare(name),
+            Pattern::Vec { elements, rest, .. } => {
+                for i in elements {
+                    self.pattern(i);
+                }
+                if let Some(rest) = rest {
+                    self.declare(rest);
+                }
+            }
+        }
+    }
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Assign { name, value } => {
+                self.expr(value);
+                let declared = self.binding(&name.lexeme).is_some()
+                    || self.globals.contains(&name.lexeme)
+                    || name.lexeme.contains("::");
+                if !declared {
+                    self.warn(Lint::Undeclared, &[&name.lexeme], name.span, None);
+                    //only the first assignment is reported, the next ones see it declared
+                    self.globals.insert(name.lexeme.clone());
+                }
+            }
+            Expr::Binary { left, right, .. } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Call {
+                callee,
+                arguments,
+                named,
+                native,
+                ..
+            } => {
+                if let (Expr::Variable(name), false) = (&**callee, native) {
+                    self.called.insert(name.lexeme.clone());
+                }
+                for i in arguments {
+                    self.expr(i);
+                }
+                for (_, i) in named {
+                    self.expr(i);
+                }
+            }
+            Expr::Grouping(expr, _) | Expr::Unwrap(expr, _) => self.expr(expr),
+            Expr::Literal(value, _) => self.value(value),
+            Expr::Unary { expression, .. } => self.expr(expression),
+            Expr::Variable(name) => match self.binding(&name.lexeme) {
+                Some(binding) => binding.used = true,
+                None => {
+                    //scoping is dynamic, it might be declared by whatever calls this
+                    self.reads.insert(name.lexeme.clone());
+                }
+            },
+            Expr::Range { min, max, step, .. } => {
+                self.expr(min);
+                self.expr(max);
+                if let Some(step) = step {
+                    self.expr(step);
+                }
+            }
+            Expr::Vec(vec, _) => {
+                for i in vec {
+                    self.expr(i);
+                }
+            }
+            Expr::Wrap { value, .. } => self.expr(value),
+        }
+    }
+    fn value(&mut self, value: &Value) {
+        if let Value::String { string, printables } = value {
+            self.strings.insert(string.clone());
+            for i in printables {
+                self.expr(i);
+            }
+        }
+    }
+    fn empty(&mut self, block: &Stmt, what: &str) {
+        if let Stmt::Block(stmts, (open, close)) = block {
+            if stmts.is_empty() {
+                self.warn(Lint::EmptyBlock, &[what], open.to(*close), None);
+            }
+        }
+    }
+    fn declare(&mut self, name: &Token) {
+        //a block can shadow the blocks around it up to the function it is in
+        let outer = self
+            .scopes
+            .iter()
+            .rev()
+            .skip(1)
+            .scan(
+                self.scopes.last().is_some_and(|s| s.function),
+                |done, scope| {
+                    if *done {
+                        return None;
+                    }
+                    *done = scope.function;
+                    Some(scope)
+                },
+            )
+            .find_map(|scope| scope.bindings.iter().rev().find(|b| b.name == name.lexeme))
+            .map(|binding| binding.span);
+        if let Some(span) = outer {
+            let label = Some((span, "declared here".to_string()));
+            self.warn(Lint::Shadowing, &[&name.lexeme], name.span, label);
+        }
+        let level = self.level(Lint::UnusedVariables);
+        self.scopes.last_mut().unwrap().bindings.push(Binding {
+            name: name.lexeme.clone(),
+            span: name.span,
+            used: false,
+            level,
+        });
+    }
+    fn binding(&mut self, name: &str) -> Option<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.bindings.iter_mut().rev().find(|b| b.name == name))
+    }
+    fn push(&mut self, function: bool) {
+        self.scopes.push(Scope {
+            bindings: Vec::new(),
+            function,
+        });
+    }
+    fn pop(&mut self) {
+        let scope = self.scopes.pop().unwrap();
+        self.close(scope);
+    }
+    fn close(&mut self, scope: Scope) {
+        //whether a name read nowhere around it is read at all is only known at the end
+        for binding in scope.bindings {
+            if !binding.used && !binding.name.starts_with('_') {
+                self.unused
+                    .push((binding.name, binding.span, binding.level));
+            }
+        }
+    }
+    fn level(&self, lint: Lint) -> Level {
+        self.levels.get(&lint).copied().unwrap_or(Level::Warn)
+    }
+    fn warn(&mut self, lint: Lint, args: &[&str], span: Span, label: Option<(Span, String)>) {
+        let level = self.level(lint);
+        self.report(lint, level, args, span, label);
+    }
+    fn report(
+        &mut self,
+        lint: Lint,
+        level: Level,
+        args: &[&str],
+        span: Span,
+        label: Option<(Span, String)>,
+    ) {
+        let mut diagnostic = KlangError::at(KlangError::CompileError, lint.message(), args, span);
+        match level {
+            Level::Allow => return,
+            Level::Warn => diagnostic.severity = Severity::Warning,
+            Level::Deny => {
+                diagnostic = diagnostic.note(&format!("denied by // klang:deny({})", lint.name()))
+            }
+        }
+        if let Some((span, message)) = label {
+            diagnostic = diagnostic.label(span, &message);
+        }
+        self.diagnostics.push(diagnostic);
+    }
+}
+
+// the value of a condition that is the same every time it runs
+fn constant(expr: &Expr) -> Option<bool> {
+    match expr {
+        Expr::Literal(Value::Bool(b), _) => Some(*b),
+        Expr::Grouping(expr, _) => constant(expr),
+        Expr::Unary {
+            operator,
+            expression,
+        } if operator.tt == TokenType::Bang => constant(expression).map(|b| !b),
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => match (operator.tt, constant(left), constant(right)) {
+            (TokenType::And, Some(false), _) | (TokenType::And, _, Some(false)) => Some(false),
+            (TokenType::Or, Some(true), _) | (TokenType::Or, _, Some(true)) => Some(true),
+            //both known and neither decided it early: true && true or false || false
+            (TokenType::And | TokenType::Or, Some(a), Some(_)) => Some(a),
+            (tt, ..) => match (&**left, &**right) {
+                (Expr::Literal(Value::Number(a), _), Expr::Literal(Value::Number(b), _)) => {
+                    compare(tt, a, b)
+                }
+                (Expr::Literal(Value::Bool(a), _), Expr::Literal(Value::Bool(b), _)) => {
+                    compare(tt, a, b)
+                }
+                _ => None,
+            },
+        },
+        _ => None,
+    }
+}
+
+fn compare<T: PartialOrd>(tt: TokenType, a: T, b: T) -> Option<bool> {
+    match tt {
+        TokenType::EqualEqual => Some(a == b),
+        TokenType::BangEqual => Some(a != b),
+        TokenType::Greater => Some(a > b),
+        TokenType::GreaterEqual => Some(a >= b),
+        TokenType::Less => Some(a < b),
+        TokenType::LessEqual => Some(a <= b),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::modules::{link, MemoryLoader};
+
+    // the diagnostics of a playground post, as the code, whether it is an error, and the text it points at
+    fn lints(source: &str) -> Vec<(u16, bool, String)> {
+        let loader = MemoryLoader::split(source);
+        let (modules, sources) = link(MemoryLoader::MAIN, &loader, 20).unwrap();
+        lint(&modules)
+            .into_iter()
+            .map(|d| {
+                let file = sources.file(d.span.line).unwrap();
+                let text = file.source[d.span.start..d.span.end].to_string();
+                (d.code.unwrap(), d.severity == Severity::Error, text)
+            })
+            .collect()
+    }
+
+    fn warning(message: &Message, text: &str) -> (u16, bool, String) {
+        (message.code, false, text.to_string())
+    }
+
+    #[test]
+    fn unused_variables() {
+        let source = "let used = 1;\nlet unused = 2;\nlet _ignored = 3;\nprint(\"{used}\");";
+        assert_eq!(
+            lints(source),
+            vec![warning(&messages::UNUSED_VARIABLE, "unused")]
+        );
+    }
+
+    #[test]
+    fn unused_functions() {
+        let source = "fn called() {}\nfn uncalled() {}\nfn _kept() {}\ncalled();";
+        assert_eq!(
+            lints(source),
+            vec![warning(&messages::UNUSED_FUNCTION, "uncalled")]
+        );
+    }
+
+    #[test]
+    fn unreachable_code() {
+        let source = "fn f() {\n    return 1;\n    print(\"never\");\n}\nf();";
+        assert_eq!(
+            lints(source),
+            vec![warning(&messages::UNREACHABLE, "print(\"never\")")]
+        );
+    }
+
+    #[test]
+    fn shadowing_stops_at_functions() {
+        let source = "let x = 1;\nif x == 1 {\n    let x = 2;\n    print(\"{x}\");\n}\nfn f(x) { return x; }\nf(x);";
+        assert_eq!(lints(source), vec![warning(&messages::SHADOWED, "x")]);
+    }
+
+    #[test]
+    fn undeclared_assignments_are_reported_once() {
+        let source = "total = 1;\ntotal = 2;\nprint(\"{total}\");";
+        assert_eq!(
+            lints(source),
+            vec![warning(&messages::UNDECLARED_ASSIGNMENT, "total")]
+        );
+    }
+
+    #[test]
+    fn constant_conditions() {
+        let source = "if 1 < 2 { print(\"a\"); }\nwhile false { print(\"b\"); }\nwhile true { break_out(); }\nfn break_out() { throw \"out\"; }";
+        assert_eq!(
+            lints(source),
+            vec![
+                warning(&messages::CONSTANT_CONDITION, "1 < 2"),
+                warning(&messages::CONSTANT_CONDITION, "false"),
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_blocks() {
+        let source = "let x = 1;\nif x == 1 {} else { print(\"a\"); }";
+        assert_eq!(lints(source), vec![warning(&messages::EMPTY_BLOCK, "{}")]);
+    }
+
+    #[test]
+    fn first_warning_of_a_lint_tells_how_to_allow_it() {
+        let loader = MemoryLoader::split("let a = 1;\nlet b = 2;");
+        let (modules, _) = link(MemoryLoader::MAIN, &loader, 20).unwrap();
+        let notes: Vec<usize> = lint(&modules).iter().map(|d| d.notes.len()).collect();
+        assert_eq!(notes, vec![1, 0]);
+    }
+
+    #[test]
+    fn allow_and_deny_directives() {
+        let unused = "let a = 1;\nfn f() {}\nlet x = 1;\nif x == 1 { let x = 2; print(\"{x}\"); }";
+        assert_eq!(lints(unused).len(), 3);
+        let allowed = format!("// klang:allow(unused)\n{unused}");
+        assert_eq!(lints(&allowed), vec![warning(&messages::SHADOWED, "x")]);
+        let allowed = format!("// klang:allow(all)\n{unused}");
+        assert!(lints(&allowed).is_empty());
+        let denied = format!("// klang:allow(unused_functions, unused_variables)\n// klang:deny(shadowing)\n{unused}");
+        assert_eq!(
+            lints(&denied),
+            vec![(messages::SHADOWED.code, true, "x".to_string())]
+        );
+    }
+
+    #[test]
+    fn imported_modules_export_their_top_level() {
+        let source = "import \"utils.kl\";\nutils::double(1);\n// klang:file utils.kl\nlet FACTOR = 2;\nfn double(n) { return n * FACTOR; }\nfn half(n) { let unused = 1; return n / 2; }";
+        assert_eq!(
+            lints(source),
+            vec![warning(&messages::UNUSED_VARIABLE, "unused")]
+        );
+    }
+
+    #[test]
+    fn dynamic_scope_reads_use_variables() {
+        //show reads the count of whichever function calls it
+        let source =
+            "fn show() { print(\"{count}\"); }\nfn run() { let count = 1; show(); }\nrun();";
+        assert!(lints(source).is_empty());
+    }
+
+    #[test]
+    fn functions_called_back_by_name_are_used() {
+        let source = "fn double(n) { return n * 2; }\nfn keep(n) { return n > 1; }\nlet v = std::vec::map([1, 2], \"double\");\nlet kept = std::vec::filter(v, \"keep\");\nprint(\"{kept}\");";
+        assert!(lints(source).is_empty());
+    }
+}
@@ -1,4 +1,6 @@
+pub mod checker;
 pub mod compiler;
+pub mod lint;
 pub mod native;
 pub mod opcode;
 pub mod vm;
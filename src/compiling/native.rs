@@ -1,145 +1,287 @@
-use crate::interpreter::scanner::Value;
-use crate::KlangError;
+use super::vm::{equal, range_len, Iter, VM};
+use crate::error::Diagnostic;
+use crate::interpreter::scanner::{Type, Value};
+use crate::messages;
 use rand::Rng;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub struct NativeFn {
     pub name: String,
-    pub args: i32,
-    pub function: Box<dyn Fn(Vec<Value>) -> Result<Value, String>>,
+    pub min: usize,
+    pub max: Option<usize>,  // None when it takes any number of arguments
+    pub types: Vec<ArgType>, // the type of every argument, the last one repeats for extra arguments
+    pub function: Box<Native>,
 }
+// how many arguments something takes: "1 argument", "at least 1 argument" or "0 to 2 arguments"
+pub fn arguments(min: usize, max: Option<usize>) -> String {
+    let (count, last) = match max {
+        Some(max) if max == min => (max.to_string(), max),
+        Some(max) => (format!("{min} to {max}"), max),
+        None => (format!("at least {min}"), min),
+    };
+    let noun = if last == 1 { "argument" } else { "arguments" };
+    format!("{count} {noun}")
+}
+
+pub type Native = dyn Fn(Vec<Value>, &mut VM) -> Result<Value, Diagnostic>;
 impl NativeFn {
-    pub fn call(&self, args: Vec<Value>) -> Result<Value, String> {
-        (self.function)(args)
+    pub fn call(&self, args: Vec<Value>, vm: &mut VM) -> Result<Value, Diagnostic> {
+        (self.function)(args, vm)
+    }
+    pub fn check(&self, args: &[Value], vm: &VM) -> Result<(), Diagnostic> {
+        //the same errors for every native, before it gets its arguments
+        if args.len() < self.min || self.max.is_some_and(|max| args.len() > max) {
+            let given = match args.len() {
+                1 => "1 was".to_string(),
+                n => format!("{n} were"),
+            };
+            let takes = arguments(self.min, self.max);
+            let message = &messages::NATIVE_ARGUMENT_COUNT;
+            return Err(vm.error(message, &[&self.name, &takes, &given]));
+        }
+        for (i, arg) in args.iter().enumerate() {
+            let ty = match self.types.get(i).or(self.types.last()) {
+                Some(x) => x,
+                None => continue,
+            };
+            if !ty.matches(arg) {
+                let (ty, i) = (ty.to_string(), (i + 1).to_string());
+                let message = &messages::NATIVE_ARGUMENT_TYPE;
+                return Err(vm.error(message, &[&self.name, &ty, &i, Type::name(arg)]));
+            }
+        }
+        Ok(())
     }
 }
 
-pub fn create_natives() -> Vec<NativeFn> {
-    let mut natives: Vec<NativeFn> = Vec::new();
-    natives.extend(math_natives());
-    natives.extend(random_natives());
-    natives.extend(time_natives());
-    natives.extend(vector_natives());
-    natives
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArgType {
+    Any,
+    Number,
+    String,
+    Vec,      // ranges are built into vectors before the native gets them
+    Sequence, // a vector or a range, as it is
+    Error,
+}
+
+impl ArgType {
+    pub fn matches(&self, value: &Value) -> bool {
+        matches!(
+            (self, value),
+            (ArgType::Any, _)
+                | (ArgType::Number, Value::Number(_))
+                | (ArgType::String, Value::String { .. })
+                | (
+                    ArgType::Vec | ArgType::Sequence,
+                    Value::Vec(_) | Value::Range { .. }
+                )
+                | (ArgType::Error, Value::Error { .. })
+        )
+    }
+}
+
+impl fmt::Display for ArgType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgType::Any => write!(f, "any value"),
+            ArgType::Number => write!(f, "a number"),
+            ArgType::String => write!(f, "a string"),
+            ArgType::Vec => write!(f, "a vector"),
+            ArgType::Sequence => write!(f, "a vector or range"),
+            ArgType::Error => write!(f, "an error"),
+        }
+    }
+}
+
+// the natives a program can call, hosts pick which ones they offer
+#[derive(Clone, Default)]
+pub struct NativeRegistry {
+    natives: Vec<Rc<NativeFn>>,
+}
+
+impl NativeRegistry {
+    pub fn new() -> NativeRegistry {
+        NativeRegistry::default()
+    }
+    pub fn standard() -> NativeRegistry {
+        let mut registry = NativeRegistry::new();
+        registry.group("math", math_natives());
+        registry.group("rand", random_natives());
+        registry.group("time", time_natives());
+        registry.group("vec", vector_natives());
+        registry.group("error", error_natives());
+        registry.group("num", number_natives());
+        registry.group("result", option_natives());
+        registry.group("iter", iterator_natives());
+        registry.group("io", io_natives());
+        registry.register("same", 2, Some(2), vec![ArgType::Any], |args, _| {
+            //vectors are the same when they are one vector, other values when they are equal
+            Ok(Value::Bool(match (&args[0], &args[1]) {
+                (Value::Vec(x), Value::Vec(y)) => Rc::ptr_eq(x, y),
+                (x, y) => equal(x, y),
+            }))
+        });
+        registry
+    }
+    pub fn register(
+        &mut self,
+        name: &str,
+        min: usize,
+        max: Option<usize>,
+        types: Vec<ArgType>,
+        function: impl Fn(Vec<Value>, &mut VM) -> Result<Value, Diagnostic> + 'static,
+    ) {
+        self.natives.retain(|n| n.name != name);
+        self.natives.push(Rc::new(NativeFn {
+            name: name.to_string(),
+            min,
+            max,
+            types,
+            function: Box::new(function),
+        }));
+    }
+    pub fn group(&mut self, group: &str, natives: Vec<NativeFn>) {
+        //std::math::sin is named math::sin
+        for mut native in natives {
+            native.name = format!("{group}::{}", native.name);
+            self.natives.retain(|n| n.name != native.name);
+            self.natives.push(Rc::new(native));
+        }
+    }
+    pub fn disable(&mut self, group: &str) {
+        let prefix = format!("{group}::");
+        self.natives.retain(|n| !n.name.starts_with(&prefix));
+    }
+    pub fn get(&self, i: usize) -> Rc<NativeFn> {
+        self.natives[i].clone()
+    }
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.natives.iter().map(|n| n.name.as_str())
+    }
+    pub fn resolve(&self, name: &str) -> Option<usize> {
+        //the index of a native, the old names without a module still work when they are not ambiguous
+        if let Some(i) = self.natives.iter().position(|n| n.name == name) {
+            return Some(i);
+        }
+        let mut found = self
+            .natives
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| !name.contains("::") && n.name.ends_with(&format!("::{name}")));
+        match (found.next(), found.next()) {
+            (Some((i, _)), None) => Some(i),
+            _ => None,
+        }
+    }
 }
+
 fn math_natives() -> Vec<NativeFn> {
     let mut math_functions: Vec<NativeFn> = Vec::new();
     math_functions.push(NativeFn {
         name: "sin".to_string(),
-        args: 1,
-        function: Box::new(|args| match args[0] {
-            Value::Number(num) => {
-                let result = num.sin();
-                Ok(Value::Number(result))
-            }
-            _ => Err(error("can only use sin on a number!")),
-        }),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Number],
+        function: Box::new(|args, _| Ok(Value::Number(number(&args[0]).sin()))),
     });
     math_functions.push(NativeFn {
         name: "cos".to_string(),
-        args: 1,
-        function: Box::new(|args| match args[0] {
-            Value::Number(num) => {
-                let result = num.cos();
-                Ok(Value::Number(result))
-            }
-            _ => Err(error("can only use cos on a number!")),
-        }),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Number],
+        function: Box::new(|args, _| Ok(Value::Number(number(&args[0]).cos()))),
     });
     math_functions.push(NativeFn {
         name: "tan".to_string(),
-        args: 1,
-        function: Box::new(|args| match args[0] {
-            Value::Number(num) => {
-                let result = num.tan();
-                Ok(Value::Number(result))
-            }
-            _ => Err(error("can only use tan on a number!")),
-        }),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Number],
+        function: Box::new(|args, _| Ok(Value::Number(number(&args[0]).tan()))),
     });
     math_functions.push(NativeFn {
         name: "sqrt".to_string(),
-        args: 1,
-        function: Box::new(|args| match args[0] {
-            Value::Number(num) => {
-                let result = num.sqrt();
-                Ok(Value::Number(result))
-            }
-            _ => Err(error("can only use sqrt on a number!")),
-        }),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Number],
+        function: Box::new(|args, _| Ok(Value::Number(number(&args[0]).sqrt()))),
     });
     math_functions.push(NativeFn {
         name: "pow".to_string(),
-        args: 2,
-        function: Box::new(|args| match (args[0].clone(), args[1].clone()) {
-            (Value::Number(base), Value::Number(exponent)) => {
-                let result = base.powf(exponent);
-                Ok(Value::Number(result))
-            }
-            _ => Err(error("can only use pow on 2 numbers!")),
+        min: 2,
+        max: Some(2),
+        types: vec![ArgType::Number, ArgType::Number],
+        function: Box::new(|args, _| {
+            let result = number(&args[0]).powf(number(&args[1]));
+            Ok(Value::Number(result))
         }),
     });
     math_functions.push(NativeFn {
         name: "ln".to_string(),
-        args: 1,
-        function: Box::new(|args| match args[0] {
-            Value::Number(num) => {
-                let result = num.ln();
-                Ok(Value::Number(result))
-            }
-            _ => Err(error("can only use ln on a number!")),
-        }),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Number],
+        function: Box::new(|args, _| Ok(Value::Number(number(&args[0]).ln()))),
     });
     math_functions.push(NativeFn {
         name: "log".to_string(),
-        args: 1,
-        function: Box::new(|args| match args[0] {
-            Value::Number(num) => {
-                let result = num.log10();
-                Ok(Value::Number(result))
-            }
-            _ => Err(error("can only use log10 on a number!")),
-        }),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Number],
+        function: Box::new(|args, _| Ok(Value::Number(number(&args[0]).log10()))),
     });
     math_functions.push(NativeFn {
         name: "round".to_string(),
-        args: 1,
-        function: Box::new(|args| match args[0] {
-            Value::Number(num) => {
-                let result = num.round();
-                Ok(Value::Number(result))
-            }
-            _ => Err(error("can only use round on a number!")),
-        }),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Number],
+        function: Box::new(|args, _| Ok(Value::Number(number(&args[0]).round()))),
     });
     math_functions.push(NativeFn {
         name: "abs".to_string(),
-        args: 1,
-        function: Box::new(|args| match args[0] {
-            Value::Number(x) => Ok(Value::Number(x.abs())),
-            _ => Err(error("can only use abs on a number!")),
-        }),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Number],
+        function: Box::new(|args, _| Ok(Value::Number(number(&args[0]).abs()))),
     });
     math_functions.push(NativeFn {
         name: "min".to_string(),
-        args: 2,
-        function: Box::new(|args| match (args[0].clone(), args[1].clone()) {
-            (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a.min(b))),
-            _ => Err(error("can only use min on 2 numbers!")),
+        min: 1,
+        max: None,
+        types: vec![ArgType::Number],
+        function: Box::new(|args, _| {
+            Ok(Value::Number(args.iter().fold(
+                f64::INFINITY,
+                |acc, x| match x {
+                    Value::Number(x) => acc.min(*x),
+                    _ => acc,
+                },
+            )))
         }),
     });
     math_functions.push(NativeFn {
         name: "max".to_string(),
-        args: 2,
-        function: Box::new(|args| match (args[0].clone(), args[1].clone()) {
-            (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a.max(b))),
-            _ => Err(error("can only use max on 2 numbers!")),
+        min: 1,
+        max: None,
+        types: vec![ArgType::Number],
+        function: Box::new(|args, _| {
+            Ok(Value::Number(args.iter().fold(
+                f64::NEG_INFINITY,
+                |acc, x| match x {
+                    Value::Number(x) => acc.max(*x),
+                    _ => acc,
+                },
+            )))
         }),
     });
     math_functions.push(NativeFn {
         name: "pi".to_string(),
-        args: 0,
-        function: Box::new(|_| Ok(Value::Number(std::f64::consts::PI))),
+        min: 0,
+        max: Some(0),
+        types: vec![],
+        function: Box::new(|_, _| Ok(Value::Number(std::f64::consts::PI))),
     });
     math_functions
 }
@@ -147,28 +289,37 @@ pub fn random_natives() -> Vec<NativeFn> {
     let mut natives: Vec<NativeFn> = Vec::new();
     natives.push(NativeFn {
         name: "random".to_string(),
-        args: 0,
-        function: Box::new(|_| {
+        min: 0,
+        max: Some(0),
+        types: vec![],
+        function: Box::new(|_, _| {
             let mut rng = rand::thread_rng();
             Ok(Value::Number(rng.gen::<f64>()))
         }),
     });
     natives.push(NativeFn {
         name: "range".to_string(),
-        args: 2,
-        function: Box::new(|args| match (args[0].clone(), args[1].clone()) {
-            (Value::Number(min), Value::Number(max)) if min < max => {
+        min: 2,
+        max: Some(2),
+        types: vec![ArgType::Number, ArgType::Number],
+        function: Box::new(|args, vm| match (number(&args[0]), number(&args[1])) {
+            (min, max) if min < max => {
                 let mut rng = rand::thread_rng();
                 let random_value = rng.gen_range(min..max);
                 Ok(Value::Number(random_value))
             }
-            _ => Err(error("can only use random_range on 2 numbers!")),
+            (min, max) => {
+                let (min, max) = (min.to_string(), max.to_string());
+                Err(vm.error(&messages::EMPTY_RANDOM_RANGE, &[&min, &max]))
+            }
         }),
     });
     natives.push(NativeFn {
         name: "randbool".to_string(),
-        args: 0,
-        function: Box::new(|_| {
+        min: 0,
+        max: Some(0),
+        types: vec![],
+        function: Box::new(|_, _| {
             let mut rng = rand::thread_rng();
             Ok(Value::Bool(rng.gen::<bool>()))
         }),
@@ -180,8 +331,10 @@ pub fn time_natives() -> Vec<NativeFn> {
     let mut natives: Vec<NativeFn> = Vec::new();
     natives.push(NativeFn {
         name: "time".to_string(),
-        args: 0,
-        function: Box::new(|_| {
+        min: 0,
+        max: Some(0),
+        types: vec![],
+        function: Box::new(|_, _| {
             Ok(Value::Number(
                 SystemTime::now()
                     .duration_since(UNIX_EPOCH)
@@ -192,13 +345,15 @@ pub fn time_natives() -> Vec<NativeFn> {
     });
     natives.push(NativeFn {
         name: "sleep".to_string(),
-        args: 1,
-        function: Box::new(|args| match args[0] {
-            Value::Number(duration) if duration >= 0.0 => {
-                std::thread::sleep(std::time::Duration::from_secs_f64(duration));
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Number],
+        function: Box::new(|args, vm| match number(&args[0]) {
+            duration if duration >= 0.0 => {
+                vm.sleep(duration)?;
                 Ok(Value::None)
             }
-            _ => Err(error("can only use sleep on a number!")),
+            duration => Err(vm.error(&messages::NEGATIVE_SLEEP, &[&duration.to_string()])),
         }),
     });
     natives
@@ -207,77 +362,379 @@ pub fn vector_natives() -> Vec<NativeFn> {
     let mut natives: Vec<NativeFn> = Vec::new();
     natives.push(NativeFn {
         name: "get".to_string(),
-        args: 2,
-        function: Box::new(
-            |mut args| match (args.pop().unwrap(), args.pop().unwrap()) {
-                (Value::Number(index), Value::Vec(mut vec)) => Ok(vec.remove(index as usize)),
-                _ => Err(error("expected a (vector, number)")),
-            },
-        ),
+        min: 2,
+        max: Some(2),
+        types: vec![ArgType::Sequence, ArgType::Number],
+        function: Box::new(|args, vm| {
+            let index = number(&args[1]);
+            element(&args[0], index).ok_or_else(|| out_of_bounds(vm, index))
+        }),
+    });
+    natives.push(NativeFn {
+        name: "try_get".to_string(),
+        min: 2,
+        max: Some(2),
+        types: vec![ArgType::Sequence, ArgType::Number],
+        function: Box::new(|args, _| match element(&args[0], number(&args[1])) {
+            Some(x) => Ok(Value::Some(Box::new(x))),
+            None => Ok(Value::None),
+        }),
     });
     natives.push(NativeFn {
         name: "set".to_string(),
-        args: 3,
-        function: Box::new(|mut args| {
+        min: 3,
+        max: Some(3),
+        types: vec![ArgType::Vec, ArgType::Number, ArgType::Any],
+        function: Box::new(|mut args, vm| {
             let value = args.pop().unwrap();
-            if let Value::Number(index) = args.pop().unwrap() {
-                if let Value::Vec(mut vec) = args.pop().unwrap() {
-                    vec[index as usize] = value;
-                    Ok(Value::Vec(vec))
-                } else {
-                    Err(error("expected a (vector, index, value)"))
-                }
-            } else {
-                Err(error("expected a (vector, index, value)"))
-            }
+            let index = number(&args.pop().unwrap());
+            let vec = vector(args.pop().unwrap());
+            let i = bounds(index, vec.borrow().len()).ok_or_else(|| out_of_bounds(vm, index))?;
+            vec.borrow_mut()[i] = value;
+            Ok(Value::Vec(vec))
         }),
     });
     natives.push(NativeFn {
         name: "remove".to_string(),
-        args: 2,
-        function: Box::new(
-            |mut args| match (args.pop().unwrap(), args.pop().unwrap()) {
-                (Value::Number(index), Value::Vec(mut vec)) => {
-                    vec.remove(index as usize);
-                    Ok(Value::Vec(vec))
-                }
-                _ => Err(error("expected a (vector, number)")),
-            },
-        ),
+        min: 2,
+        max: Some(2),
+        types: vec![ArgType::Vec, ArgType::Number],
+        function: Box::new(|mut args, vm| {
+            let index = number(&args.pop().unwrap());
+            let vec = vector(args.pop().unwrap());
+            let i = bounds(index, vec.borrow().len()).ok_or_else(|| out_of_bounds(vm, index))?;
+            vec.borrow_mut().remove(i);
+            Ok(Value::Vec(vec))
+        }),
     });
     natives.push(NativeFn {
         name: "insert".to_string(),
-        args: 3,
-        function: Box::new(|mut args| {
+        min: 3,
+        max: Some(3),
+        types: vec![ArgType::Vec, ArgType::Number, ArgType::Any],
+        function: Box::new(|mut args, vm| {
+            let value = args.pop().unwrap();
+            let index = number(&args.pop().unwrap());
+            let vec = vector(args.pop().unwrap());
+            let len = vec.borrow().len() + 1; //one past the end inserts at the end
+            let i = bounds(index, len).ok_or_else(|| out_of_bounds(vm, index))?;
+            vec.borrow_mut().insert(i, value);
+            Ok(Value::Vec(vec))
+        }),
+    });
+    natives.push(NativeFn {
+        name: "push".to_string(),
+        min: 2,
+        max: Some(2),
+        types: vec![ArgType::Vec, ArgType::Any],
+        function: Box::new(|mut args, _| {
             let value = args.pop().unwrap();
-            if let Value::Number(index) = args.pop().unwrap() {
-                if let Value::Vec(mut vec) = args.pop().unwrap() {
-                    vec.insert(index as usize, value);
-                    Ok(Value::Vec(vec))
-                } else {
-                    Err(error("expected a (vector, index, value)"))
+            let vec = vector(args.pop().unwrap());
+            vec.borrow_mut().push(value);
+            Ok(Value::Vec(vec))
+        }),
+    });
+    natives.push(NativeFn {
+        name: "map".to_string(),
+        min: 2,
+        max: Some(2),
+        types: vec![ArgType::Vec, ArgType::String],
+        function: Box::new(|mut args, vm| {
+            let name = string(args.pop().unwrap());
+            let values = vector(args.pop().unwrap()).borrow().clone(); //the function might change the vector
+            let mut mapped: Vec<Value> = Vec::new();
+            for i in values {
+                mapped.push(vm.call_function(&name, vec![i])?);
+            }
+            Ok(Value::vec(mapped))
+        }),
+    });
+    natives.push(NativeFn {
+        name: "filter".to_string(),
+        min: 2,
+        max: Some(2),
+        types: vec![ArgType::Vec, ArgType::String],
+        function: Box::new(|mut args, vm| {
+            let name = string(args.pop().unwrap());
+            let values = vector(args.pop().unwrap()).borrow().clone();
+            let mut kept: Vec<Value> = Vec::new();
+            for i in values {
+                match vm.call_function(&name, vec![i.clone()])? {
+                    Value::Bool(true) => kept.push(i),
+                    Value::Bool(false) => (),
+                    x => {
+                        let message = &messages::FILTER_NOT_BOOL;
+                        return Err(vm.error(message, &[&name, Type::name(&x)]));
+                    }
                 }
-            } else {
-                Err(error("expected a (vector, index, value)"))
             }
+            Ok(Value::vec(kept))
+        }),
+    });
+    natives.push(NativeFn {
+        name: "enumerate".to_string(),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Vec],
+        function: Box::new(|mut args, _| {
+            let vec = vector(args.pop().unwrap());
+            let pairs = vec
+                .borrow()
+                .iter()
+                .enumerate()
+                .map(|(i, x)| Value::vec(vec![Value::Number(i as f64), x.clone()]))
+                .collect();
+            Ok(Value::vec(pairs))
         }),
     });
     natives.push(NativeFn {
         name: "len".to_string(),
-        args: 1,
-        function: Box::new(|mut args| {
-            if let Value::Vec(vec) = args.pop().unwrap() {
-                Ok(Value::Number(vec.len() as f64))
-            } else {
-                error("expected a (vector)");
-                panic!()
-            }
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Sequence],
+        function: Box::new(|mut args, _| match args.pop().unwrap() {
+            Value::Range {
+                start,
+                end,
+                step,
+                inclusive,
+            } => Ok(Value::Number(range_len(start, end, step, inclusive) as f64)),
+            vec => Ok(Value::Number(vector(vec).borrow().len() as f64)),
         }),
     });
+    natives.push(NativeFn {
+        name: "copy".to_string(),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Vec],
+        function: Box::new(|mut args, _| {
+            Ok(Value::vec(vector(args.pop().unwrap()).borrow().clone()))
+        }),
+    });
+    natives.push(NativeFn {
+        name: "collect".to_string(),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Vec],
+        function: Box::new(|mut args, _| Ok(args.pop().unwrap())), //the vm already built the range
+    });
 
     natives
 }
 
-fn error(msg: &str) -> String {
-    KlangError::error(KlangError::RuntimeError, msg, 0)
+pub fn error_natives() -> Vec<NativeFn> {
+    vec![
+        NativeFn {
+            name: "message".to_string(),
+            min: 1,
+            max: Some(1),
+            types: vec![ArgType::Error],
+            function: Box::new(|mut args, _| {
+                let (message, _) = error(args.pop().unwrap());
+                Ok(Value::String {
+                    string: message,
+                    printables: vec![],
+                })
+            }),
+        },
+        NativeFn {
+            name: "line".to_string(),
+            min: 1,
+            max: Some(1),
+            types: vec![ArgType::Error],
+            function: Box::new(|mut args, _| {
+                let (_, line) = error(args.pop().unwrap());
+                Ok(Value::Number(line as f64))
+            }),
+        },
+    ]
+}
+
+pub fn number_natives() -> Vec<NativeFn> {
+    vec![NativeFn {
+        name: "parse".to_string(),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::String],
+        function: Box::new(|mut args, _| {
+            let text = string(args.pop().unwrap());
+            match text.trim().parse::<f64>() {
+                Ok(x) => Ok(Value::Ok(Box::new(Value::Number(x)))),
+                Err(_) => Ok(Value::Err(Box::new(Value::String {
+                    string: format!("cannot parse \"{text}\" as a number"),
+                    printables: vec![],
+                }))),
+            }
+        }),
+    }]
+}
+
+pub fn option_natives() -> Vec<NativeFn> {
+    let mut natives: Vec<NativeFn> = Vec::new();
+    natives.push(NativeFn {
+        name: "unwrap".to_string(),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Any],
+        function: Box::new(|mut args, vm| match args.pop().unwrap() {
+            Value::Ok(x) | Value::Some(x) => Ok(*x),
+            Value::Err(x) => Err(vm.error(&messages::UNWRAP_FAILED, &[&format!("Err({x})")])),
+            Value::None => Err(vm.error(&messages::UNWRAP_FAILED, &["None"])),
+            _ => Err(expected(vm, "result::unwrap", "an Ok, Err, Some or None")),
+        }),
+    });
+    natives.push(NativeFn {
+        name: "unwrap_or".to_string(),
+        min: 2,
+        max: Some(2),
+        types: vec![ArgType::Any, ArgType::Any],
+        function: Box::new(
+            |mut args, vm| match (args.pop().unwrap(), args.pop().unwrap()) {
+                (_, Value::Ok(x) | Value::Some(x)) => Ok(*x),
+                (default, Value::Err(_) | Value::None) => Ok(default),
+                _ => Err(expected(
+                    vm,
+                    "result::unwrap_or",
+                    "an Ok, Err, Some or None and a value",
+                )),
+            },
+        ),
+    });
+    natives.push(NativeFn {
+        name: "is_ok".to_string(),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Any],
+        function: Box::new(|mut args, _| {
+            Ok(Value::Bool(matches!(args.pop().unwrap(), Value::Ok(_))))
+        }),
+    });
+    natives.push(NativeFn {
+        name: "is_err".to_string(),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Any],
+        function: Box::new(|mut args, _| {
+            Ok(Value::Bool(matches!(args.pop().unwrap(), Value::Err(_))))
+        }),
+    });
+    natives.push(NativeFn {
+        name: "is_some".to_string(),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Any],
+        function: Box::new(|mut args, _| {
+            Ok(Value::Bool(matches!(args.pop().unwrap(), Value::Some(_))))
+        }),
+    });
+    natives.push(NativeFn {
+        name: "is_none".to_string(),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::Any],
+        function: Box::new(|mut args, _| {
+            Ok(Value::Bool(matches!(args.pop().unwrap(), Value::None)))
+        }),
+    });
+    natives
+}
+
+pub fn iterator_natives() -> Vec<NativeFn> {
+    vec![NativeFn {
+        name: "from_fn".to_string(),
+        min: 1,
+        max: Some(1),
+        types: vec![ArgType::String],
+        function: Box::new(|mut args, vm| match string(args.pop().unwrap()) {
+            name if vm.functions.contains_key(&name) => {
+                Ok(Value::Iterator(Box::new(Iter::Function(name))))
+            }
+            name => Err(vm.error(&messages::NO_ITERATOR_FUNCTION, &[&name])),
+        }),
+    }]
+}
+
+pub fn io_natives() -> Vec<NativeFn> {
+    vec![NativeFn {
+        name: "input".to_string(),
+        min: 0,
+        max: Some(0),
+        types: vec![],
+        function: Box::new(|_, vm| {
+            let mut line = String::new();
+            match std::io::stdin().read_line(&mut line) {
+                Ok(_) => Ok(Value::String {
+                    string: line.trim_end_matches(['\n', '\r']).to_string(),
+                    printables: vec![],
+                }),
+                Err(err) => Err(vm.error(&messages::INPUT_FAILED, &[&err.to_string()])),
+            }
+        }),
+    }]
+}
+
+fn element(sequence: &Value, index: f64) -> Option<Value> {
+    //the value at index of a vector or range, none when the index is out of bounds
+    match sequence {
+        Value::Vec(vec) => {
+            let vec = vec.borrow();
+            Some(vec[bounds(index, vec.len())?].clone())
+        }
+        Value::Range {
+            start,
+            end,
+            step,
+            inclusive,
+        } => {
+            let i = bounds(index, range_len(*start, *end, *step, *inclusive))?;
+            Some(Value::Number(start + i as f64 * step))
+        }
+        _ => None,
+    }
+}
+
+fn bounds(index: f64, len: usize) -> Option<usize> {
+    //the index as a usize, if its a whole number smaller than len
+    if index < 0.0 || index.fract() != 0.0 || index as usize >= len {
+        return None;
+    }
+    Some(index as usize)
+}
+
+fn out_of_bounds(vm: &VM, index: f64) -> Diagnostic {
+    vm.error(&messages::INDEX_OUT_OF_BOUNDS, &[&index.to_string()])
+}
+
+// the arguments of the types the signature of a native already checked
+fn number(value: &Value) -> f64 {
+    match value {
+        Value::Number(x) => *x,
+        _ => unreachable!("the signature only lets numbers through"),
+    }
+}
+
+fn string(value: Value) -> String {
+    match value {
+        Value::String { string, .. } => string,
+        _ => unreachable!("the signature only lets strings through"),
+    }
+}
+
+fn vector(value: Value) -> Rc<RefCell<Vec<Value>>> {
+    match value {
+        Value::Vec(vec) => vec,
+        _ => unreachable!("the signature builds ranges into vectors"),
+    }
+}
+
+fn error(value: Value) -> (String, usize) {
+    match value {
+        Value::Error { message, line } => (message, line),
+        _ => unreachable!("the signature only lets errors through"),
+    }
+}
+
+fn expected(vm: &VM, native: &str, what: &str) -> Diagnostic {
+    //arguments of the right types a native still can't take, like a number given to unwrap
+    vm.error(&messages::NATIVE_EXPECTED, &[native, what])
 }
@@ -1,16 +1,16 @@
-use crate::scanner::Value;
+use crate::scanner::{TokenType, Type, Value};
 use std::fmt;
 
 #[derive(Debug, Clone)]
 pub enum OpCode {
-    Constant(Value),         //Load a constant value onto the stack
+    Constant(Value),   //Load a constant value onto the stack
     Store(String), // Store the value from the top of the stack into the variable in the hashtable.
     Load(String),  //Load the value of the variable from the hashtable onto the stack
     Add,           // Performs addition on the last two values on the stack.
     Subtract,      // Performs subtraction on the last two values on the stack.
     Multiply,      // Performs multiplication on the last two values on the stack.
     Divide,        // Performs division on the last two values on the stack.
-    Modulo,        // sex
+    Modulo,        // Performs modulo on the last two values on the stack.
     EqualEqual,    // Compares equality between the last two values on the stack.
     NotEqual,      // Compares inequality between the last two values on the stack.
     Less,          // Checks if the second-to-last value on the stack is less than the last value.
@@ -23,17 +23,30 @@ pub enum OpCode {
     Negate,       // Negates the last numeric value on the stack.
     Jump(i32),    // Unconditionally jumps to a specified instruction address.
     JumpIf(i32, bool), // Jumps to a specified instruction address if the last value on the stack is true.
-    Call(String),      // Calls a function at a specified instruction address.
+    // Calls a function with the positional and named arguments on the stack.
+    Call(String, i32, Vec<String>),
     NativeCall(String, i32), // Calls a native function or external function.
-    Print,             // Prints the last value on the stack to the console or output stream.
-    Range(bool),
+    Native(usize, i32),      // A native call after its name was resolved to the native's index.
+    Print,                   // Prints the last value on the stack to the console or output stream.
+    Range(bool, bool), // Builds a range from the numbers on the stack, with a step and inclusive or not.
     Scope,
     EndScope,
     EndFn,
     Return(bool),
-    For,
-    Fn,
+    Yield, // Hands the value on top of the stack to the loop running the generator, and suspends it.
+    Iter,  // Turns the value on top of the stack into an iterator for a for loop.
+    Next(i32), // Opens the loop's scope with the next value of the iterator, or jumps out when its done.
+    Fn(Vec<Param>, Option<Type>, bool), // Declares a function, the bool is true for generators.
+    Default(String, i32), // Skips the default value of a parameter if an argument was given for it.
+    Arg(String), // Binds the top of the stack to a parameter in the function's scope.
+    Destructure(i32, bool), // Unpacks the vector on top of the stack, first element on top.
+    Type(String, Type), // Declares the type of a variable and checks its current value.
     Iterable(i32),
+    Try(i32), // Registers a handler that jumps to the catch block when an error is raised.
+    EndTry,   // Removes the handler of the try block that just finished.
+    Throw,    // Raises the value on top of the stack as an error.
+    Wrap(TokenType), // Wraps the value on top of the stack in Ok, Err or Some.
+    Unwrap,   // Unwraps an Ok or Some, or returns the Err or None from the function.
     Eof,
 }
 
@@ -60,18 +73,38 @@ impl fmt::Display for OpCode {
             OpCode::Negate => write!(f, "Negate"),
             OpCode::Jump(x) => write!(f, "Jump {}", x),
             OpCode::JumpIf(x, y) => write!(f, "JumpIf {} {}", x, y),
-            OpCode::Call(x) => write!(f, "Call {}", x),
+            OpCode::Call(x, y, z) => write!(f, "Call {} {} {:?}", x, y, z),
             OpCode::NativeCall(x, y) => write!(f, "NativeCall {} {}", x, y),
+            OpCode::Native(x, y) => write!(f, "Native {} {}", x, y),
             OpCode::Print => write!(f, "Print"),
-            OpCode::Range(x) => write!(f, "Range {}", x),
+            OpCode::Range(x, y) => write!(f, "Range {} {}", x, y),
             OpCode::Scope => write!(f, "Scope"),
             OpCode::EndScope => write!(f, "EndScope"),
             OpCode::EndFn => write!(f, "EndFn"),
             OpCode::Return(x) => write!(f, "Return {}", x),
-            OpCode::For => write!(f, "For"),
-            OpCode::Fn => write!(f, "Fn"),
+            OpCode::Yield => write!(f, "Yield"),
+            OpCode::Iter => write!(f, "Iter"),
+            OpCode::Next(x) => write!(f, "Next {}", x),
+            OpCode::Fn(x, _, y) => write!(f, "Fn {} {}", x.len(), y),
+            OpCode::Default(x, y) => write!(f, "Default {} {}", x, y),
+            OpCode::Arg(x) => write!(f, "Arg {}", x),
+            OpCode::Destructure(x, y) => write!(f, "Destructure {} {}", x, y),
+            OpCode::Type(x, y) => write!(f, "Type {} {}", x, y),
+            OpCode::Try(x) => write!(f, "Try {}", x),
+            OpCode::EndTry => write!(f, "EndTry"),
+            OpCode::Throw => write!(f, "Throw"),
+            OpCode::Wrap(x) => write!(f, "Wrap {}", x),
+            OpCode::Unwrap => write!(f, "Unwrap"),
             OpCode::Iterable(x) => write!(f, "Iterable {}", x),
             OpCode::Eof => write!(f, "Eof"),
         }
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct Param {
+    pub name: String,
+    pub ty: Option<Type>,
+    pub default: bool,
+    pub rest: bool,
+}
@@ -1,39 +1,164 @@
 use super::{
     compiler::Chunk,
-    native::{create_natives, NativeFn},
-    opcode::OpCode,
+    native::{arguments, ArgType, NativeRegistry},
+    opcode::{OpCode, Param},
 };
-use crate::interpreter::scanner::{TokenType, Value};
-use crate::KlangError;
-use std::collections::HashMap;
+use crate::error::{suggest, Diagnostic, KlangError, Span};
+use crate::interpreter::modules::SourceMap;
+use crate::interpreter::scanner::{TokenType, Type, Value};
+use crate::messages::{self, Message};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 pub struct VM {
-    pub chunk: Chunk,
+    pub chunk: Rc<Chunk>,
     pub global: Scope,
     pub index: i32,
-    pub functions: HashMap<String, (Vec<OpCode>, Vec<String>)>,
-    pub native: Vec<NativeFn>,
+    pub functions: HashMap<String, Function>,
+    pub natives: NativeRegistry,
+    pub frames: Vec<CallFrame>,
+    pub handlers: Vec<Handler>,
+    pub thrown: Option<Value>, // the value of the last throw, until its caught
+    yielded: Option<Value>,    // the value of the last yield, until the generator is suspended
+    pub sources: SourceMap,
+    pub limits: Limits,
+    fuel: Option<u64>,         // the instructions left to run
+    deadline: Option<Instant>, // when the program has to stop
+    ticks: u64,
+    halted: bool,
+    memory: usize,   // the bytes last measured, plus the values pushed since
+    pub peak: usize, // the most bytes the program was measured using
+    nested: usize,   // how many runs natives and generators started are inside each other
 }
 
 impl VM {
-    pub fn new(chunk: Chunk) -> VM {
+    pub fn new(chunk: Chunk, natives: NativeRegistry) -> VM {
         VM {
-            chunk,
+            chunk: Rc::new(chunk),
             global: Scope::new(),
             index: 0,
             functions: HashMap::new(),
-            native: create_natives(),
+            natives,
+            frames: Vec::new(),
+            handlers: Vec::new(),
+            thrown: None,
+            yielded: None,
+            sources: SourceMap::default(),
+            limits: Limits::default(),
+            fuel: None,
+            deadline: None,
+            ticks: 0,
+            halted: false,
+            memory: 0,
+            peak: 0,
+            nested: 0,
         }
     }
-    pub fn once(&mut self, jumps: &mut i32) -> Result<String, String> {
+    pub fn limit(&mut self, limits: Limits) {
+        self.fuel = limits.fuel;
+        self.deadline = limits
+            .time
+            .and_then(|time| Instant::now().checked_add(time));
+        self.limits = limits;
+    }
+    pub fn once(&mut self) -> Result<String, Diagnostic> {
+        if let Some(s) = self.tick() {
+            return Err(s);
+        }
+        match self.execute() {
+            Ok(s) => Ok(s),
+            Err(s) => self.catch(s),
+        }
+    }
+    fn tick(&mut self) -> Option<Diagnostic> {
+        //spends one instruction of fuel, and looks at the clock every so often
+        if let Some(fuel) = self.fuel.as_mut() {
+            if *fuel == 0 {
+                let budget = self.limits.fuel.unwrap_or(0).to_string();
+                return Some(self.halt(&messages::OUT_OF_FUEL, &[&budget]));
+            }
+            *fuel -= 1;
+        }
+        if let Some(s) = self.check_memory(0) {
+            return Some(s);
+        }
+        self.ticks += 1;
+        if self.ticks.is_multiple_of(1024) {
+            self.measure();
+            return self.check_time();
+        }
+        None
+    }
+    fn check_memory(&mut self, bytes: usize) -> Option<Diagnostic> {
+        //only measures again when the estimate goes over the cap, measuring walks every value
+        let cap = self.limits.memory?;
+        if self.memory.saturating_add(bytes) <= cap {
+            return None;
+        }
+        self.measure();
+        if self.memory.saturating_add(bytes) <= cap {
+            return None;
+        }
+        Some(self.halt(&messages::OUT_OF_MEMORY, &[&bytes_to_string(cap)]))
+    }
+    pub fn measure(&mut self) -> usize {
+        let mut seen = Seen::new();
+        let memory = self.thrown.as_ref().map_or(0, |x| size(x, &mut seen))
+            + scope_size(&self.global, &mut seen);
+        self.memory = memory;
+        self.peak = self.peak.max(memory);
+        memory
+    }
+    fn check_time(&mut self) -> Option<Diagnostic> {
+        if self.limits.cancel.load(Ordering::Relaxed) {
+            return Some(self.halt(&messages::CANCELLED, &[]));
+        }
+        match (self.deadline, self.limits.time) {
+            (Some(deadline), Some(time)) if Instant::now() >= deadline => {
+                let time = time.as_secs_f64().to_string();
+                Some(self.halt(&messages::TIMED_OUT, &[&time]))
+            }
+            _ => None,
+        }
+    }
+    fn halt(&mut self, message: &Message, args: &[&str]) -> Diagnostic {
+        //errors that stop the program even inside a try
+        self.halted = true;
+        self.error(message, args)
+    }
+    pub fn sleep(&mut self, secs: f64) -> Result<(), Diagnostic> {
+        //sleeps in small steps, so the deadline and cancelling still stop the program
+        let end = Duration::try_from_secs_f64(secs)
+            .ok()
+            .and_then(|d| Instant::now().checked_add(d));
+        loop {
+            if let Some(s) = self.check_time() {
+                return Err(s);
+            }
+            let now = Instant::now();
+            let step = match end {
+                Some(end) if now >= end => return Ok(()),
+                Some(end) => (end - now).min(Duration::from_millis(10)),
+                None => Duration::from_millis(10),
+            };
+            std::thread::sleep(step);
+        }
+    }
+    fn execute(&mut self) -> Result<String, Diagnostic> {
         match self.chunk.code[self.index as usize].clone() {
             OpCode::Constant(x) => self.push(x),
-            OpCode::Store(x) => self.set_var(x),
+            OpCode::Store(x) => {
+                if let Some(s) = self.set_var(x) {
+                    return Err(s);
+                }
+            }
             OpCode::Load(x) => {
-                let var = match VM::get_var(&x, &mut self.global).0 {
+                let var = match VM::get_var(&x, &self.global).0 {
                     Some(x) => x,
-                    None => {
-                        return Err(self.error(format!("variable \"{x}\" do not exist").as_str()))
-                    }
+                    None => return Err(self.unknown(&x)),
                 };
                 self.push(var);
             }
@@ -114,7 +239,7 @@ impl VM {
             }
             OpCode::Jump(x) => {
                 if self.index + x > self.chunk.code.len() as i32 {
-                    return Err(self.error("cannot jump out of bounds like ur dad jumped out of the 50th story window bozo"));
+                    return Err(self.error(&messages::INTERNAL, &["a jump out of the code"]));
                 }
                 self.index += x;
             }
@@ -122,37 +247,41 @@ impl VM {
                 if t {
                     if let Value::Bool(true) = match self.pop() {
                         Some(x) => x,
-                        None => return Err(self.error("stack overflow (cant pop an empty stack)")),
+                        None => {
+                            return Err(self.error(&messages::INTERNAL, &["the stack is empty"]))
+                        }
                     } {
                         if self.index + x > self.chunk.code.len() as i32 {
-                            return Err(self.error("cannot jump out of bounds like ur dad jumped out of the 50th story window bozo"));
+                            return Err(
+                                self.error(&messages::INTERNAL, &["a jump out of the code"])
+                            );
                         }
                         self.index += x;
-                    } else {
-                        *jumps += 1;
                     }
                 } else if let Ok(Value::Bool(true)) = self.top() {
                     if self.index + x > self.chunk.code.len() as i32 {
-                        return Err(self.error("cannot jump out of bounds like ur dad jumped out of the 50th story window bozo"));
+                        return Err(self.error(&messages::INTERNAL, &["a jump out of the code"]));
                     }
                     self.index += x;
-                } else {
-                    *jumps += 1;
                 }
             }
-            OpCode::Call(x) => {
-                if let Some(s) = self.call(x, self.index) {
+            OpCode::Call(x, y, z) => {
+                if let Some(s) = self.call(x, y, z) {
                     return Err(s);
                 }
             }
-            OpCode::NativeCall(x, y) => {
+            OpCode::NativeCall(x, _) => {
+                let msg = format!("native function std::{x} was not resolved");
+                return Err(self.error(&messages::INTERNAL, &[&msg]));
+            }
+            OpCode::Native(x, y) => {
                 if let Some(s) = self.native_call(x, y) {
                     return Err(s);
                 }
             }
             OpCode::Print => return self.print(),
-            OpCode::Range(x) => {
-                if let Some(s) = self.range(x) {
+            OpCode::Range(x, y) => {
+                if let Some(s) = self.range(x, y) {
                     return Err(s);
                 }
             }
@@ -160,52 +289,66 @@ impl VM {
             OpCode::Scope => self.create_inner(),
             OpCode::EndScope => self.close_inner(),
             OpCode::EndFn => {
-                self.push(Value::None);
+                if let Some(s) = self.ret(Value::None) {
+                    return Err(s);
+                }
             }
             OpCode::Return(x) => {
-                if x {
-                    let val = match self.pop() {
+                let val = if x {
+                    match self.pop() {
                         Some(x) => x,
                         None => Value::None,
-                    };
-                    let mut counter = 1;
-                    while !matches!(self.chunk.code[self.index as usize], OpCode::EndFn) {
-                        self.index += 1;
-                        if matches!(self.chunk.code[self.index as usize], OpCode::Scope) {
-                            counter -= 1;
-                        }
-                        if matches!(self.chunk.code[self.index as usize], OpCode::EndScope) {
-                            counter += 1;
-                        }
                     }
-                    for _ in 0..counter {
-                        self.close_inner()
-                    }
-                    self.push(val);
                 } else {
-                    let mut counter = 1;
-                    while !matches!(self.chunk.code[self.index as usize], OpCode::EndFn) {
-                        self.index += 1;
-                        if matches!(self.chunk.code[self.index as usize], OpCode::Scope) {
-                            counter -= 1;
-                        }
-                        if matches!(self.chunk.code[self.index as usize], OpCode::EndScope) {
-                            counter += 1;
+                    Value::None
+                };
+                if let Some(s) = self.ret(val) {
+                    return Err(s);
+                }
+            }
+            OpCode::Iter => {
+                if let Some(s) = self.iter() {
+                    return Err(s);
+                }
+            }
+            OpCode::Next(x) => return self.next(x),
+            OpCode::Yield => {
+                let value = match self.pop() {
+                    Some(x) => x,
+                    None => return Err(self.error(&messages::INTERNAL, &["the stack is empty"])),
+                };
+                if let Some(frame) = self.frames.last().filter(|f| f.generator) {
+                    if let Some(ty) = frame.ret {
+                        if !ty.matches(&value) {
+                            let what = format!("the values of \"{}\"", frame.name);
+                            return Err(self.type_error(&what, ty, &value));
                         }
                     }
-                    for _ in 0..counter {
-                        self.close_inner()
-                    }
-                    self.push(Value::None);
+                }
+                self.yielded = Some(value);
+            }
+            OpCode::Fn(x, y, z) => {
+                if let Some(s) = self.function(x, y, z) {
+                    return Err(s);
+                }
+            }
+            OpCode::Default(x, y) => {
+                if self.innermost().callframe.contains_key(&x) {
+                    self.index += y;
                 }
             }
-            OpCode::For => {
-                if let Some(s) = self.for_loop() {
+            OpCode::Arg(x) => {
+                if let Some(s) = self.set_var_inner(x) {
                     return Err(s);
                 }
             }
-            OpCode::Fn => {
-                if let Some(s) = self.function() {
+            OpCode::Type(x, y) => {
+                if let Some(s) = self.declare_type(x, y) {
+                    return Err(s);
+                }
+            }
+            OpCode::Destructure(x, y) => {
+                if let Some(s) = self.destructure(x, y) {
                     return Err(s);
                 }
             }
@@ -214,17 +357,113 @@ impl VM {
                     return Err(s);
                 }
             }
+            OpCode::Try(x) => self.handlers.push(Handler {
+                catch: self.index + x,
+                frames: self.frames.len(),
+                depth: self.depth(),
+            }),
+            OpCode::EndTry => {
+                self.handlers.pop();
+            }
+            OpCode::Throw => {
+                let value = match self.pop() {
+                    Some(x) => x,
+                    None => return Err(self.error(&messages::INTERNAL, &["the stack is empty"])),
+                };
+                let value = match value {
+                    Value::Error { .. } => value,
+                    Value::String { .. } => {
+                        self.push(value);
+                        Value::Error {
+                            message: self.interpolate()?,
+                            line: self.line(),
+                        }
+                    }
+                    _ => Value::Error {
+                        message: value.to_string(),
+                        line: self.line(),
+                    },
+                };
+                let msg = self.error(&messages::THROWN, &[&value.to_string()]);
+                self.thrown = Some(value);
+                return Err(msg);
+            }
+            OpCode::Wrap(x) => {
+                let value = match self.pop() {
+                    Some(x) => Box::new(x),
+                    None => return Err(self.error(&messages::INTERNAL, &["the stack is empty"])),
+                };
+                self.push(match x {
+                    TokenType::Ok => Value::Ok(value),
+                    TokenType::Err => Value::Err(value),
+                    _ => Value::Some(value),
+                });
+            }
+            OpCode::Unwrap => {
+                if let Some(s) = self.unwrap() {
+                    return Err(s);
+                }
+            }
             OpCode::Eof => {}
         }
         Ok(String::new())
     }
-    fn iterable(&mut self, x: i32) -> Option<String> {
+    fn unwrap(&mut self) -> Option<Diagnostic> {
+        //the ? operator, an Err or None is returned from the function
+        let value = match self.pop() {
+            Some(x) => x,
+            None => return Some(self.error(&messages::INTERNAL, &["the stack is empty"])),
+        };
+        match value {
+            Value::Ok(x) | Value::Some(x) => {
+                self.push(*x);
+                None
+            }
+            Value::Err(_) | Value::None => {
+                if self.frames.is_empty() {
+                    let value = value.to_string();
+                    return Some(self.error(&messages::UNWRAP_OUTSIDE_FUNCTION, &[&value]));
+                }
+                self.ret(value)
+            }
+            _ => Some(self.error(&messages::INVALID_UNWRAP, &[Type::name(&value)])),
+        }
+    }
+    fn catch(&mut self, err: Diagnostic) -> Result<String, Diagnostic> {
+        //jumps to the innermost catch block with the error, unwinding the calls and scopes inside its try
+        if self.halted {
+            return Err(err);
+        }
+        let handler = match self.handlers.pop() {
+            Some(x) => x,
+            None => return Err(err),
+        };
+        let value = match self.thrown.take() {
+            Some(x) => x,
+            None => Value::Error {
+                message: err.message.clone(),
+                line: self.line(),
+            },
+        };
+        while self.frames.len() > handler.frames {
+            let frame = self.frames.pop().unwrap();
+            self.chunk = frame.chunk;
+        }
+        while self.depth() > handler.depth {
+            self.close_inner();
+        }
+        self.index = handler.catch; //the scope of the catch block
+        self.create_inner();
+        self.push(value);
+        Ok(String::new())
+    }
+    fn iterable(&mut self, x: i32) -> Option<Diagnostic> {
         let mut vec: Vec<Value> = Vec::with_capacity(x as usize);
         for _ in 0..x {
             vec.push(match self.pop() {
                 Some(x) => x,
                 None => {
-                    return Some(self.error("stack overflow (cant pop an empty stack)"));
+                    return Some(self.error(&messages::INTERNAL, &["the stack is empty"]));
                 }
             });
         }
@@ -232,25 +471,23 @@ impl VM {
         for i in vec.into_iter().rev() {
             vec1.push(i);
         }
-        self.push(Value::Vec(vec1));
+        self.push(Value::vec(vec1));
         None
     }
-    fn function(&mut self) -> Option<String> {
+    fn function(
+        &mut self,
+        params: Vec<Param>,
+        ret: Option<Type>,
+        generator: bool,
+    ) -> Option<Diagnostic> {
+        let span = self.chunk.spans[self.index as usize];
         self.index += 1; //consume fn
-        let mut args: Vec<String> = Vec::new();
-        while match self.chunk.code[self.index as usize].clone() {
-            OpCode::Store(x) => {
-                args.push(x);
-                true
-            }
-            _ => false,
-        } {
-            self.index += 1; //consume arg
-        }
         let mut bytes: Vec<OpCode> = Vec::new();
+        let mut spans: Vec<Span> = Vec::new();
         let mut counter = 1;
         while counter != 0 {
             bytes.push(self.chunk.code[self.index as usize].clone());
+            spans.push(self.chunk.spans[self.index as usize]);
             self.index += 1;
             if matches!(self.chunk.code[self.index as usize], OpCode::EndScope) {
                 counter -= 1;
@@ -259,89 +496,292 @@ impl VM {
                 counter += 1;
             }
         }
+        bytes.push(OpCode::EndFn);
+        spans.push(self.chunk.spans[self.index as usize]);
         self.index += 1;
         match self.chunk.code[self.index as usize].clone() {
-            OpCode::Store(x) => self.functions.insert(x, (bytes, args)),
-            _ => return Some(self.error("ksang made a little oopsy")),
+            OpCode::Store(x) => self.functions.insert(
+                x,
+                Function {
+                    params,
+                    ret,
+                    chunk: Rc::new(Chunk::new((bytes, spans))),
+                    generator,
+                    span,
+                },
+            ),
+            _ => return Some(self.error(&messages::INTERNAL, &["a function without a name"])),
         };
         None
     }
-    fn range(&mut self, cstep: bool) -> Option<String> {
-        if cstep {
-            let step = match self.pop() {
-                Some(Value::Number(x)) => {
-                    if x < 1.0 {
-                        return Some(self.error("cannot use anything smaller than 1 as a step!"));
-                    };
-                    x as usize
+    fn range(&mut self, cstep: bool, inclusive: bool) -> Option<Diagnostic> {
+        let step = match cstep {
+            true => match self.pop() {
+                Some(Value::Number(0.0)) => {
+                    return Some(self.error(&messages::RANGE_ZERO_STEP, &[]))
                 }
-                _ => return Some(self.error("step is not a number")),
-            };
-            let end = match self.pop() {
-                Some(Value::Number(x)) => x,
-                _ => return Some(self.error("end is not a number")),
-            };
-            let start = match self.pop() {
-                Some(Value::Number(x)) => x,
-                _ => return Some(self.error("start is not a number")),
-            };
-            let mut vec: Vec<Value> = Vec::new();
-            for i in (start as i32..end as i32).step_by(step) {
-                vec.push(Value::Number(i as f64));
+                Some(Value::Number(x)) => Some(x),
+                _ => return Some(self.error(&messages::RANGE_NOT_NUMBER, &["step"])),
+            },
+            false => None,
+        };
+        let end = match self.pop() {
+            Some(Value::Number(x)) => x,
+            _ => return Some(self.error(&messages::RANGE_NOT_NUMBER, &["end"])),
+        };
+        let start = match self.pop() {
+            Some(Value::Number(x)) => x,
+            _ => return Some(self.error(&messages::RANGE_NOT_NUMBER, &["start"])),
+        };
+        //without a step, ranges count down when the end is smaller than the start
+        let step = step.unwrap_or(if end < start { -1.0 } else { 1.0 });
+        if !(start.is_finite() && end.is_finite() && step.is_finite()) {
+            return Some(self.error(&messages::RANGE_NOT_FINITE, &[]));
+        }
+        self.push(Value::Range {
+            start,
+            end,
+            step,
+            inclusive,
+        });
+        None
+    }
+    fn collect(&mut self, value: Value) -> Result<Value, Diagnostic> {
+        //builds the vector of a range, when something needs it as a vector
+        match value {
+            Value::Range {
+                start,
+                end,
+                step,
+                inclusive,
+            } => {
+                let len = range_len(start, end, step, inclusive);
+                if let Some(s) = self.check_memory(len.saturating_mul(std::mem::size_of::<Value>()))
+                {
+                    return Err(s);
+                }
+                Ok(Value::vec(
+                    (0..len)
+                        .map(|i| Value::Number(start + i as f64 * step))
+                        .collect(),
+                ))
             }
-            self.push(Value::Vec(vec));
-            None
-        } else {
-            let end = match self.pop() {
-                Some(Value::Number(x)) => x,
-                _ => return Some(self.error("end is not a number")),
-            };
-            let start = match self.pop() {
-                Some(Value::Number(x)) => x,
-                _ => return Some(self.error("start is not a number")),
-            };
-            let mut vec: Vec<Value> = Vec::new();
-            for i in start as i32..end as i32 {
-                vec.push(Value::Number(i as f64));
+            _ => Ok(value),
+        }
+    }
+    fn destructure(&mut self, len: i32, rest: bool) -> Option<Diagnostic> {
+        let value = match self.pop().map(|x| self.collect(x)) {
+            Some(Ok(x)) => Some(x),
+            Some(Err(s)) => return Some(s),
+            None => None,
+        };
+        let mut vector = match value {
+            Some(Value::Vec(x)) => x.borrow().clone(),
+            Some(x) => return Some(self.error(&messages::NOT_A_VECTOR, &[&x.to_string()])),
+            None => return Some(self.error(&messages::INTERNAL, &["the stack is empty"])),
+        };
+        if vector.len() < len as usize || (!rest && vector.len() > len as usize) {
+            let expected = format!("{}{len}", if rest { "at least " } else { "" });
+            let found = vector.len().to_string();
+            return Some(self.error(&messages::DESTRUCTURE_LENGTH, &[&expected, &found]));
+        }
+        if rest {
+            self.push(Value::vec(vector.split_off(len as usize)));
+        }
+        for i in vector.into_iter().rev() {
+            self.push(i);
+        }
+        None
+    }
+    fn iter(&mut self) -> Option<Diagnostic> {
+        let iter = match self.pop() {
+            Some(Value::Vec(x)) => Iter::Vec(x, 0),
+            Some(Value::Range {
+                start,
+                end,
+                step,
+                inclusive,
+            }) => Iter::Range {
+                start,
+                step,
+                len: range_len(start, end, step, inclusive),
+                index: 0,
+            },
+            Some(Value::String { string, .. }) => Iter::String(string.chars().collect(), 0),
+            Some(Value::Iterator(x)) => *x,
+            Some(x) => return Some(self.error(&messages::NOT_ITERABLE, &[Type::name(&x)])),
+            None => return Some(self.error(&messages::INTERNAL, &["the stack is empty"])),
+        };
+        self.push(Value::Iterator(Box::new(iter)));
+        None
+    }
+    fn next(&mut self, exit: i32) -> Result<String, Diagnostic> {
+        //the iterator stays on the stack under the loop's scope until its done
+        let mut iter = match self.pop() {
+            Some(Value::Iterator(x)) => x,
+            _ => return Err(self.error(&messages::INTERNAL, &["the for loop lost its iterator"])),
+        };
+        let mut output = String::new();
+        let next = match &mut *iter {
+            Iter::Vec(vec, index) => {
+                let next = vec.borrow().get(*index).cloned();
+                *index += 1;
+                next
+            }
+            Iter::Range {
+                start,
+                step,
+                len,
+                index,
+            } => (*index < *len).then(|| {
+                *index += 1;
+                Value::Number(*start + (*index - 1) as f64 * *step)
+            }),
+            Iter::String(chars, index) => chars.get(*index).map(|ch| {
+                *index += 1;
+                Value::String {
+                    string: ch.to_string(),
+                    printables: vec![],
+                }
+            }),
+            Iter::Function(name) => match self.call_function(name, vec![])? {
+                Value::Some(x) => Some(*x),
+                Value::None => None,
+                x => {
+                    let message = &messages::INVALID_ITERATOR;
+                    return Err(self.error(message, &[name, Type::name(&x)]));
+                }
+            },
+            Iter::Generator(generator) => {
+                let (next, printed) = self.resume(generator)?;
+                output = printed;
+                next
             }
-            self.push(Value::Vec(vec));
-            None
+        };
+        match next {
+            Some(value) => {
+                self.push(Value::Iterator(iter));
+                self.index += 1; //skip the scope
+                self.create_inner();
+                self.push(value);
+            }
+            None => self.index += exit,
         }
+        Ok(output)
     }
-    fn for_loop(&mut self) -> Option<String> {
-        let range = match self.pop() {
+    fn resume(
+        &mut self,
+        generator: &RefCell<Generator>,
+    ) -> Result<(Option<Value>, String), Diagnostic> {
+        //runs the generator's frame until it yields a value or returns
+        //its scope is taken out while it runs, so resuming it from inside itself finds it finished
+        let mut state = generator.borrow_mut();
+        let scope = match state.scope.take() {
             Some(x) => x,
-            None => return Some(self.error("Stack overflow (cant pop an empty stack)")),
+            None => return Ok((None, String::new())),
         };
-        let mut vector = match range {
-            Value::Vec(x) => x,
-            _ => return Some(self.error("invalid witewabwe!")),
+        if self.nested >= NESTED {
+            state.scope = Some(scope);
+            return Err(self.nested_error());
+        }
+        let depth = self.depth();
+        let frames = self.frames.len();
+        self.innermost().inner = Some(scope);
+        let handlers = std::mem::take(&mut self.handlers); //errors go back to the loop
+        self.handlers = state
+            .handlers
+            .drain(..)
+            .map(|h| Handler {
+                catch: h.catch,
+                frames: h.frames + frames,
+                depth: h.depth + depth,
+            })
+            .collect();
+        self.frames.push(CallFrame {
+            name: state.name.clone(),
+            ret: state.ret,
+            generator: true,
+            chunk: std::mem::replace(&mut self.chunk, state.chunk.clone()),
+            index: self.index,
+            depth,
+        });
+        self.index = state.index;
+        drop(state);
+        let mut output = String::new();
+        self.nested += 1;
+        let result = loop {
+            self.index += 1;
+            match self.once() {
+                Ok(s) => output.push_str(&s),
+                Err(s) => break Err(s),
+            }
+            if self.frames.len() == frames {
+                self.pop(); //it returned, and ret left nada for the caller
+                break Ok(None);
+            }
+            if let Some(value) = self.yielded.take() {
+                break Ok(Some(value));
+            }
         };
-        self.index += 1;
-        self.create_inner();
-        if vector.is_empty() {
-            self.push(Value::Bool(true));
-            self.push(Value::None);
-            return None;
-        } else {
-            self.push(Value::Bool(false));
-            self.push(vector.remove(0));
+        self.nested -= 1;
+        match result {
+            Ok(Some(value)) => {
+                let frame = self.frames.pop().unwrap();
+                let mut state = generator.borrow_mut();
+                state.index = self.index;
+                state.scope = self.detach(depth);
+                state.handlers = std::mem::take(&mut self.handlers)
+                    .into_iter()
+                    .map(|h| Handler {
+                        catch: h.catch,
+                        frames: h.frames - frames,
+                        depth: h.depth - depth,
+                    })
+                    .collect();
+                self.chunk = frame.chunk;
+                self.index = frame.index;
+                self.handlers = handlers;
+                Ok((Some(value), output))
+            }
+            Ok(None) => {
+                self.handlers = handlers;
+                Ok((None, output))
+            }
+            Err(s) => {
+                let s = self.trace(s, frames);
+                while self.frames.len() > frames {
+                    let frame = self.frames.pop().unwrap();
+                    self.chunk = frame.chunk;
+                    self.index = frame.index;
+                }
+                while self.depth() > depth {
+                    self.close_inner();
+                }
+                self.handlers = handlers;
+                Err(s)
+            }
         }
+    }
+    fn detach(&mut self, depth: usize) -> Option<Box<Scope>> {
+        //takes the scopes deeper than depth out of the chain
         let mut scope: &mut Scope = &mut self.global;
-        while scope.inner.as_mut().unwrap().inner.is_some() {
+        for _ in 0..depth {
             scope = scope.inner.as_mut().unwrap();
         }
-        scope.stack.push(Value::Vec(vector));
-        None
+        scope.inner.take()
     }
-    fn print(&mut self) -> Result<String, String> {
+    fn print(&mut self) -> Result<String, Diagnostic> {
+        Ok(self.interpolate()? + "\n")
+    }
+    fn interpolate(&mut self) -> Result<String, Diagnostic> {
+        //fills the braces of the string on top of the stack with the printables below it
         let mut print = match self.pop() {
             Some(Value::String {
                 string,
                 printables: _,
             }) => string,
             _ => {
-                return Err(self.error("is it even possible to get here?"));
+                return Err(self.error(&messages::INTERNAL, &["a string without its printables"]));
             }
         };
         for _ in 0..self.count_braces(print.as_str()) {
@@ -353,14 +793,22 @@ impl VM {
                 Some(Value::Number(x)) => x.to_string(),
                 Some(Value::Bool(x)) => x.to_string(),
                 Some(Value::Vec(x)) => format!("{}", Value::Vec(x)),
+                Some(Value::Error { message, .. }) => message,
+                Some(
+                    x @ (Value::Ok(_)
+                    | Value::Err(_)
+                    | Value::Some(_)
+                    | Value::Range { .. }
+                    | Value::Iterator(_)),
+                ) => x.to_string(),
                 Some(Value::None) => "None".to_string(),
                 None => {
-                    return Err(self.error("Stack overflow (cant pop an empty stack)"));
+                    return Err(self.error(&messages::INTERNAL, &["the stack is empty"]));
                 }
             };
             print = self.replace_last_braces(print.as_str(), repl.as_str());
         }
-        Ok(print + "\n")
+        Ok(print)
     }
     fn count_braces(&self, string: &str) -> usize {
         let mut count = 0;
@@ -368,12 +816,10 @@ impl VM {
         for c in string.chars() {
             match c {
                 '{' => braces += 1,
-                '}' => {
-                    if braces > 0 {
-                        braces -= 1;
-                        if braces == 0 {
-                            count += 1;
-                        }
+                '}' if braces > 0 => {
+                    braces -= 1;
+                    if braces == 0 {
+                        count += 1;
                     }
                 }
                 _ => {}
@@ -392,52 +838,93 @@ impl VM {
             String::from(string)
         }
     }
-    fn get_var(name: &str, scope: &mut Scope) -> (Option<Value>, bool) {
+    fn get_var(name: &str, scope: &Scope) -> (Option<Value>, bool) {
         //gets a variable from the most inner scope, if its not there searches on the outer scopes, return true when found the variable
-        if scope.inner.is_some() {
-            let i = VM::get_var(name, scope.inner.as_mut().unwrap());
-            if !i.1 {
-                return match scope.callframe.get(name) {
-                    Some(val) => (Some(val.clone()), true),
-                    None => (None, false),
-                };
+        //walks the chain in a loop, deep recursion opens a scope per call and would run out of stack
+        let mut found = None;
+        let mut scope = Some(scope);
+        while let Some(x) = scope {
+            if let Some(val) = x.callframe.get(name) {
+                found = Some(val);
             }
-            return i;
+            scope = x.inner.as_deref();
         }
-        match scope.callframe.get(name) {
+        match found {
             Some(val) => (Some(val.clone()), true),
             None => (None, false),
         }
     }
-    fn set_var(&mut self, name: String) {
-        //sets a variable in the most inner scope, to the top value of the stack
+    fn unknown(&self, name: &str) -> Diagnostic {
+        //a variable that is not in any open scope, with the one it was probably meant to be
+        let error = self.error(&messages::UNKNOWN_VARIABLE, &[name]);
+        let mut names: Vec<&str> = Vec::new();
+        let mut scope = Some(&self.global);
+        while let Some(x) = scope {
+            names.extend(x.callframe.keys().map(|k| k.as_str()));
+            scope = x.inner.as_deref();
+        }
+        error.did_you_mean(suggest(name, names))
+    }
+    fn set_var(&mut self, name: String) -> Option<Diagnostic> {
+        //sets a variable in the most outer scope it exists in (or the most inner one), to the top value of the stack
         let pop = match self.pop() {
             Some(x) => x,
             None => Value::None,
         };
         let mut scope: &mut Scope = &mut self.global;
-        while scope.inner.is_some() {
-            if let std::collections::hash_map::Entry::Occupied(mut e) =
-                scope.callframe.entry(name.clone())
-            {
-                e.insert(pop);
-                return;
-            }
+        while scope.inner.is_some() && !scope.callframe.contains_key(&name) {
             scope = scope.inner.as_mut().unwrap();
         }
+        if let Some(ty) = scope.types.get(&name) {
+            if !ty.matches(&pop) {
+                let ty = *ty;
+                return Some(self.type_error(&format!("\"{name}\""), ty, &pop));
+            }
+        }
         scope.callframe.insert(name, pop);
+        None
     }
-    fn set_var_inner(&mut self, name: String) {
+    fn set_var_inner(&mut self, name: String) -> Option<Diagnostic> {
         //sets a variable in the most inner scope, to the top value of the stack
         let pop = match self.pop() {
             Some(x) => x,
             None => Value::None,
         };
+        let scope = self.innermost();
+        if let Some(ty) = scope.types.get(&name) {
+            if !ty.matches(&pop) {
+                let ty = *ty;
+                return Some(self.type_error(&format!("\"{name}\""), ty, &pop));
+            }
+        }
+        scope.callframe.insert(name, pop);
+        None
+    }
+    fn declare_type(&mut self, name: String, ty: Type) -> Option<Diagnostic> {
+        //the same scope set_var would store the variable in
+        let mut scope: &mut Scope = &mut self.global;
+        while scope.inner.is_some() && !scope.callframe.contains_key(&name) {
+            scope = scope.inner.as_mut().unwrap();
+        }
+        if let Some(value) = scope.callframe.get(&name) {
+            if !matches!(value, Value::None) && !ty.matches(value) {
+                let value = value.clone();
+                return Some(self.type_error(&format!("\"{name}\""), ty, &value));
+            }
+        }
+        scope.types.insert(name, ty);
+        None
+    }
+    fn type_error(&self, what: &str, ty: Type, value: &Value) -> Diagnostic {
+        let ty = ty.to_string();
+        self.error(&messages::MISMATCHED_TYPES, &[&ty, what, Type::name(value)])
+    }
+    fn innermost(&mut self) -> &mut Scope {
         let mut scope: &mut Scope = &mut self.global;
         while scope.inner.is_some() {
             scope = scope.inner.as_mut().unwrap();
         }
-        scope.callframe.insert(name, pop);
+        scope
     }
     fn create_inner(&mut self) {
         let mut scope: &mut Scope = &mut self.global;
@@ -446,6 +933,15 @@ impl VM {
         }
         scope.inner = Some(Box::new(Scope::new()));
     }
+    fn depth(&self) -> usize {
+        let mut scope: &Scope = &self.global;
+        let mut depth = 0;
+        while let Some(inner) = scope.inner.as_ref() {
+            scope = inner;
+            depth += 1;
+        }
+        depth
+    }
     fn close_inner(&mut self) {
         let mut scope: &mut Scope = &mut self.global;
         while scope.inner.as_mut().unwrap().inner.is_some() {
@@ -453,15 +949,52 @@ impl VM {
         }
         scope.inner = None;
     }
-    fn error(&self, msg: &str) -> String {
-        KlangError::error(
+    fn line(&self) -> usize {
+        //the line of the current instruction inside its own file
+        self.sources
+            .locate(self.chunk.spans[self.index as usize].line)
+            .1
+    }
+    fn nested_error(&self) -> Diagnostic {
+        //calls made through natives and generators nest on the rust stack, so they have their own cap
+        let nested = NESTED.to_string();
+        self.error(&messages::RECURSION_LIMIT, &[&nested])
+            .help(&format!(
+                "functions called back by natives or generators can only be {nested} deep"
+            ))
+    }
+    fn trace(&self, mut diagnostic: Diagnostic, from: usize) -> Diagnostic {
+        //the calls above the first `from` the error happened inside, from the innermost out
+        let mut span = self.chunk.spans[self.index as usize];
+        for frame in self.frames[from..].iter().rev() {
+            diagnostic = diagnostic.frame(&frame.name, span);
+            span = frame.chunk.spans[frame.index as usize];
+        }
+        diagnostic
+    }
+    pub fn uncaught(&self, diagnostic: Diagnostic) -> Diagnostic {
+        //an error that stopped the program, with every call it happened inside
+        let diagnostic = self.trace(diagnostic, 0);
+        if !diagnostic.traced() {
+            return diagnostic;
+        }
+        let span = match self.frames.first() {
+            Some(frame) => frame.chunk.spans[frame.index as usize],
+            None => self.chunk.spans[self.index as usize],
+        };
+        diagnostic.frame("top level", span)
+    }
+    pub fn error(&self, message: &Message, args: &[&str]) -> Diagnostic {
+        //at the instruction running, natives use it for their errors at the line of the call
+        KlangError::at(
             KlangError::RuntimeError,
-            msg,
-            self.chunk.lines[self.index as usize],
+            message,
+            args,
+            self.chunk.spans[self.index as usize],
         )
     }
 
-    fn bin_op(&mut self, operation: TokenType) -> Option<String> {
+    fn bin_op(&mut self, operation: TokenType) -> Option<Diagnostic> {
         let pop2 = match self.pop2() {
             Ok(a) => a,
             Err(s) => return Some(s),
@@ -469,191 +1002,360 @@ impl VM {
         self.push(match operation {
             TokenType::Plus => match pop2 {
                 (Value::Number(x), Value::Number(y)) => Value::Number(x + y),
-                _ => return Some(self.error("can only add numbers")),
+                _ => return Some(self.error(&messages::INVALID_OPERANDS, &["add numbers"])),
             },
             TokenType::Minus => match pop2 {
                 (Value::Number(x), Value::Number(y)) => Value::Number(y - x),
-                _ => return Some(self.error("can only subtract numbers")),
+                _ => return Some(self.error(&messages::INVALID_OPERANDS, &["subtract numbers"])),
             },
             TokenType::Star => match pop2 {
                 (Value::Number(x), Value::Number(y)) => Value::Number(x * y),
-                _ => return Some(self.error("can only multiply numbers")),
+                _ => return Some(self.error(&messages::INVALID_OPERANDS, &["multiply numbers"])),
             },
             TokenType::Slash => match pop2 {
                 (Value::Number(x), Value::Number(y)) => {
                     if x == 0.0 {
-                        return Some(self.error("division by zero"));
+                        return Some(self.error(&messages::DIVISION_BY_ZERO, &[]));
                     }
                     Value::Number(y / x)
                 }
-                _ => return Some(self.error("can only divide numbers")),
+                _ => return Some(self.error(&messages::INVALID_OPERANDS, &["divide numbers"])),
             },
             TokenType::Modulo => match pop2 {
                 (Value::Number(x), Value::Number(y)) => {
                     if x == 0.0 {
-                        return Some(self.error("no modulo by zero"));
+                        return Some(self.error(&messages::MODULO_BY_ZERO, &[]));
                     }
                     Value::Number(y % x)
                 }
                 _ => {
-                    return Some(
-                        self.error("can only use the modulo operator on numbers, dickfuck"),
-                    )
-                }
-            },
-            TokenType::EqualEqual => match pop2 {
-                (Value::Number(x), Value::Number(y)) => Value::Bool(x == y),
-                (Value::Bool(x), Value::Bool(y)) => Value::Bool(x == y),
-                (Value::String { string: x, .. }, Value::String { string: y, .. }) => {
-                    Value::Bool(x == y)
-                }
-                _ => Value::Bool(false),
-            },
-            TokenType::BangEqual => match pop2 {
-                (Value::Number(x), Value::Number(y)) => Value::Bool(x != y),
-                (Value::Bool(x), Value::Bool(y)) => Value::Bool(x != y),
-                (Value::String { string: x, .. }, Value::String { string: y, .. }) => {
-                    Value::Bool(x != y)
+                    return Some(self.error(
+                        &messages::INVALID_OPERANDS,
+                        &["use the modulo operator on numbers"],
+                    ))
                 }
-                _ => Value::Bool(true),
             },
+            TokenType::EqualEqual => Value::Bool(equal(&pop2.0, &pop2.1)),
+            TokenType::BangEqual => Value::Bool(!equal(&pop2.0, &pop2.1)),
             TokenType::Less => match pop2 {
                 (Value::Number(x), Value::Number(y)) => Value::Bool(x > y),
-                _ => return Some(self.error("can only compare numbers")),
+                _ => return Some(self.error(&messages::INVALID_OPERANDS, &["compare numbers"])),
             },
             TokenType::LessEqual => match pop2 {
                 (Value::Number(x), Value::Number(y)) => Value::Bool(x >= y),
-                _ => return Some(self.error("can only compare numbers")),
+                _ => return Some(self.error(&messages::INVALID_OPERANDS, &["compare numbers"])),
             },
             TokenType::Greater => match pop2 {
                 (Value::Number(x), Value::Number(y)) => Value::Bool(x < y),
-                _ => return Some(self.error("can only compare numbers")),
+                _ => return Some(self.error(&messages::INVALID_OPERANDS, &["compare numbers"])),
             },
             TokenType::GreaterEqual => match pop2 {
                 (Value::Number(x), Value::Number(y)) => Value::Bool(x <= y),
-                _ => return Some(self.error("can only compare numbers")),
+                _ => return Some(self.error(&messages::INVALID_OPERANDS, &["compare numbers"])),
             },
             TokenType::And => match pop2 {
                 (Value::Bool(x), Value::Bool(y)) => Value::Bool(x && y),
-                _ => return Some(self.error("can only perform logical AND on bool values")),
+                _ => {
+                    return Some(self.error(
+                        &messages::INVALID_OPERANDS,
+                        &["perform logical AND on bool values"],
+                    ))
+                }
             },
             TokenType::Or => match pop2 {
                 (Value::Bool(x), Value::Bool(y)) => Value::Bool(x || y),
-                _ => return Some(self.error("can only perform logical OR on bool values")),
+                _ => {
+                    return Some(self.error(
+                        &messages::INVALID_OPERANDS,
+                        &["perform logical OR on bool values"],
+                    ))
+                }
             },
-            _ => return Some(self.error("unsupported binary operation")),
+            _ => {
+                return Some(self.error(&messages::INTERNAL, &["an unsupported binary operation"]))
+            }
         });
         None
     }
-    fn un_op(&mut self, operation: TokenType) -> Option<String> {
+    fn un_op(&mut self, operation: TokenType) -> Option<Diagnostic> {
         let pop = match self.pop() {
             Some(x) => x,
-            None => return Some(self.error("stack overflow (cant pop an empty stack)")),
+            None => return Some(self.error(&messages::INTERNAL, &["the stack is empty"])),
         };
         self.push(match operation {
             TokenType::Bang => match pop {
                 Value::Bool(x) => Value::Bool(!x),
-                _ => return Some(self.error("can only use ! on bools")),
+                _ => return Some(self.error(&messages::INVALID_OPERANDS, &["use ! on bools"])),
             },
             TokenType::Minus => match pop {
                 Value::Number(x) => Value::Number(-x),
-                _ => return Some(self.error("can only use minus on ints and floats")),
+                _ => {
+                    return Some(self.error(
+                        &messages::INVALID_OPERANDS,
+                        &["use minus on ints and floats"],
+                    ))
+                }
             },
-            _ => return Some(self.error("unsupported unary operation")),
+            _ => return Some(self.error(&messages::INTERNAL, &["an unsupported unary operation"])),
         });
         None
     }
-    fn call(&mut self, callee: String, index: i32) -> Option<String> {
-        let mut scope: &mut Scope = &mut self.global;
-        let mut counter = 0;
-        while scope.inner.is_some() {
-            scope = scope.inner.as_mut().unwrap();
-            counter += 1;
+    fn call(&mut self, callee: String, arg_num: i32, named: Vec<String>) -> Option<Diagnostic> {
+        if self.frames.len() >= self.limits.depth {
+            let depth = self.limits.depth.to_string();
+            return Some(self.error(&messages::RECURSION_LIMIT, &[&depth]));
         }
-        if counter > 100 {
-            return Some(self.error(
-                "your recursion is 100 layers deep- bro thinks he can crash klang :skull:",
-            ));
-        }
-        let fun = match self.functions.remove(&callee) {
-            Some(x) => x,
-            None => return Some(self.error("please call a real function next time stupid ass mf")),
+        let fun = match self.functions.get(&callee) {
+            Some(x) => x.clone(),
+            None => {
+                let found = suggest(&callee, self.functions.keys().map(|k| k.as_str()));
+                let error = self.error(&messages::UNKNOWN_FUNCTION, &[&callee]);
+                return Some(error.did_you_mean(found));
+            }
         };
-        self.functions.insert(callee.clone(), fun.clone());
+        let mut named_args: Vec<(String, Value)> = Vec::new();
+        for name in named.into_iter().rev() {
+            match self.pop() {
+                Some(x) => named_args.insert(0, (name, x)),
+                None => {
+                    return Some(
+                        self.error(&messages::INTERNAL, &["not enough arguments on the stack"]),
+                    )
+                }
+            }
+        }
+        let mut args: Vec<Value> = Vec::new();
+        for _ in 0..arg_num {
+            match self.pop() {
+                Some(x) => args.insert(0, x),
+                None => {
+                    return Some(
+                        self.error(&messages::INTERNAL, &["not enough arguments on the stack"]),
+                    )
+                }
+            }
+        }
+        let given = args.len() + named_args.len();
+        let rest = fun.params.iter().position(|p| p.rest);
+        let positional = rest.unwrap_or(fun.params.len());
+        if rest.is_none() && args.len() > positional {
+            return Some(self.arity_error(&callee, &fun, given, None));
+        }
+        let mut bound: Vec<Option<Value>> = vec![None; fun.params.len()];
+        let mut extra: Vec<Value> = Vec::new();
+        for (i, arg) in args.into_iter().enumerate() {
+            if i < positional {
+                bound[i] = Some(arg);
+            } else {
+                extra.push(arg);
+            }
+        }
+        for (name, arg) in named_args {
+            let i = match fun.params.iter().position(|p| p.name == name && !p.rest) {
+                Some(i) => i,
+                None => return Some(self.error(&messages::UNKNOWN_PARAMETER, &[&callee, &name])),
+            };
+            if bound[i].is_some() {
+                return Some(self.error(&messages::PARAMETER_TWICE, &[&name, &callee]));
+            }
+            bound[i] = Some(arg);
+        }
+        if let Some(i) = rest {
+            bound[i] = Some(Value::vec(extra));
+        }
+        for (i, param) in fun.params.iter().enumerate() {
+            if bound[i].is_none() && !param.default {
+                return Some(self.arity_error(&callee, &fun, given, Some(&param.name)));
+            }
+        }
+        for (param, value) in fun.params.iter().zip(&bound) {
+            if let (Some(ty), Some(value)) = (param.ty, value) {
+                if !ty.matches(value) {
+                    return Some(self.type_error(
+                        &format!("parameter \"{}\" of \"{callee}\"", param.name),
+                        ty,
+                        value,
+                    ));
+                }
+            }
+        }
+        let depth = self.depth();
         self.create_inner();
-        self.index += 1;
-        for i in fun.1.into_iter().rev() {
-            let mut scope: &mut Scope = &mut self.global;
-            while scope.inner.as_mut().unwrap().inner.is_some() {
-                scope = scope.inner.as_mut().unwrap();
+        for param in fun.params.iter() {
+            if let Some(ty) = param.ty {
+                self.innermost().types.insert(param.name.clone(), ty);
             }
-            let pop = match scope.stack.pop() {
-                Some(x) => x,
-                None => return Some(self.error("not enough arguments!")),
+        }
+        for (param, value) in fun.params.into_iter().zip(bound) {
+            if let Some(value) = value {
+                self.push(value);
+                self.set_var_inner(param.name);
+            }
+        }
+        if fun.generator {
+            //the generator does not run yet, the loop resumes it for every value
+            let generator = Generator {
+                name: callee,
+                ret: fun.ret,
+                chunk: fun.chunk,
+                index: 0,
+                scope: self.detach(depth),
+                handlers: Vec::new(),
             };
-            self.push(pop);
-            self.set_var_inner(i);
+            self.push(Value::Iterator(Box::new(Iter::Generator(Rc::new(
+                RefCell::new(generator),
+            )))));
+            return None;
         }
-        let mut b = 0;
-        for (i, op) in fun.0.into_iter().enumerate() {
-            self.chunk.code.insert(index as usize + i + 1, op);
-            self.chunk.lines.push(0);
-            b = index as usize + i + 2;
+        self.frames.push(CallFrame {
+            name: callee,
+            ret: fun.ret,
+            generator: false,
+            chunk: std::mem::replace(&mut self.chunk, fun.chunk),
+            index: self.index,
+            depth,
+        });
+        self.index = 0; //the arguments scope replaces the function's own scope
+        None
+    }
+    fn ret(&mut self, val: Value) -> Option<Diagnostic> {
+        let frame = match self.frames.last() {
+            Some(x) => x,
+            None => return Some(self.error(&messages::RETURN_OUTSIDE_FUNCTION, &[])),
+        };
+        if let (Some(ty), false) = (frame.ret, frame.generator) {
+            if !ty.matches(&val) {
+                return Some(self.type_error(
+                    &format!("the return value of \"{}\"", frame.name),
+                    ty,
+                    &val,
+                ));
+            }
         }
-        self.chunk.code.insert(b, OpCode::EndFn);
+        let frame = self.frames.pop().unwrap();
+        let frames = self.frames.len();
+        self.handlers.retain(|h| h.frames <= frames);
+        while self.depth() > frame.depth {
+            self.close_inner();
+        }
+        self.chunk = frame.chunk;
+        self.index = frame.index;
+        self.push(val);
         None
     }
-    fn native_call(&mut self, callee: String, arg_num: i32) -> Option<String> {
-        let mut found = false;
-        for i in 0..self.native.len() {
-            if self.native[i].name == callee {
-                if arg_num != self.native[i].args {
-                    return Some(
-                        self.error(
-                            format!(
-                                "the function takes {} arguments but you only gave it {arg_num}",
-                                self.native[i].args
-                            )
-                            .as_str(),
-                        ),
-                    );
-                }
-                let mut args: Vec<Value> = Vec::new();
-                for _ in 0..arg_num {
-                    args.insert(
-                        0,
-                        match self.pop() {
-                            Some(x) => x,
-                            None => return Some(self.error("not enough arguments!")),
-                        },
-                    )
-                }
-                match self.native[i].call(args) {
-                    Ok(x) => self.push(x),
+    fn arity_error(
+        &self,
+        callee: &str,
+        fun: &Function,
+        given: usize,
+        missing: Option<&str>,
+    ) -> Diagnostic {
+        let params = &fun.params;
+        let required = params.iter().filter(|p| !p.default && !p.rest).count();
+        let max = params.iter().filter(|p| !p.rest).count();
+        let rest = params.iter().any(|p| p.rest);
+        let expected = arguments(required, if rest { None } else { Some(max) });
+        let given = format!("{given} {}", if given == 1 { "was" } else { "were" });
+        let missing = match missing {
+            Some(name) => format!(" (missing \"{name}\")"),
+            None => String::new(),
+        };
+        self.error(
+            &messages::WRONG_ARGUMENT_COUNT,
+            &[callee, &expected, &given, &missing],
+        )
+        .label(fun.span, "function declared here")
+    }
+    fn native_call(&mut self, i: usize, arg_num: i32) -> Option<Diagnostic> {
+        let mut args: Vec<Value> = Vec::new();
+        for _ in 0..arg_num {
+            args.insert(
+                0,
+                match self.pop() {
+                    Some(x) => x,
+                    None => {
+                        return Some(
+                            self.error(&messages::INTERNAL, &["not enough arguments on the stack"]),
+                        )
+                    }
+                },
+            )
+        }
+        let native = self.natives.get(i);
+        if let Err(s) = native.check(&args, self) {
+            return Some(s);
+        }
+        for (i, arg) in args.iter_mut().enumerate() {
+            if native.types.get(i).or(native.types.last()) == Some(&ArgType::Vec) {
+                match self.collect(std::mem::replace(arg, Value::None)) {
+                    Ok(x) => *arg = x,
                     Err(s) => return Some(s),
                 }
-                found = true;
-                break;
             }
         }
-        if !found {
-            return Some(self.error("not a real native function dumbass"));
+        match native.call(args, self) {
+            Ok(x) => self.push(x),
+            Err(s) => return Some(s),
         }
         None
     }
+    pub fn call_function(&mut self, name: &str, args: Vec<Value>) -> Result<Value, Diagnostic> {
+        //runs a klang function until it returns, for natives that call back into the program
+        if self.nested >= NESTED {
+            return Err(self.nested_error());
+        }
+        let index = self.index;
+        let frames = self.frames.len();
+        let depth = self.depth();
+        let handlers = std::mem::take(&mut self.handlers); //errors go back to the native
+        let arg_num = args.len() as i32;
+        for arg in args {
+            self.push(arg);
+        }
+        let mut result = match self.call(name.to_string(), arg_num, Vec::new()) {
+            Some(s) => Err(s),
+            None => Ok(()),
+        };
+        self.nested += 1;
+        while result.is_ok() && self.frames.len() > frames {
+            result = self.once().map(|_| ());
+            if result.is_ok() {
+                self.index += 1;
+            }
+        }
+        self.nested -= 1;
+        if let Err(s) = result {
+            result = Err(self.trace(s, frames));
+        }
+        while self.frames.len() > frames {
+            let frame = self.frames.pop().unwrap();
+            self.chunk = frame.chunk;
+        }
+        self.index = index;
+        self.handlers = handlers;
+        match result {
+            Ok(()) => Ok(self.pop().unwrap_or(Value::None)),
+            Err(s) => {
+                while self.depth() > depth {
+                    self.close_inner();
+                }
+                Err(s)
+            }
+        }
+    }
 
-    fn pop2(&mut self) -> Result<(Value, Value), String> {
+    fn pop2(&mut self) -> Result<(Value, Value), Diagnostic> {
         Ok((
             match self.pop() {
                 Some(x) => x,
                 None => {
-                    return Err(self.error("stack overflow (cant pop an empty stack)"));
+                    return Err(self.error(&messages::INTERNAL, &["the stack is empty"]));
                 }
             },
             match self.pop() {
                 Some(x) => x,
                 None => {
-                    return Err(self.error("stack overflow (cant pop an empty stack)"));
+                    return Err(self.error(&messages::INTERNAL, &["the stack is empty"]));
                 }
             },
         ))
@@ -665,19 +1367,24 @@ impl VM {
         }
         scope.stack.pop()
     }
-    fn top(&mut self) -> Result<Value, String> {
+    fn top(&mut self) -> Result<Value, Diagnostic> {
         let mut scope: &mut Scope = &mut self.global;
         while scope.inner.is_some() {
             scope = scope.inner.as_mut().unwrap();
         }
         if scope.stack.is_empty() {
-            return Err(self.error("stack overflow (cant top an empty stack)"));
+            return Err(self.error(&messages::INTERNAL, &["the stack is empty"]));
         }
         let val = scope.stack.pop().unwrap();
         scope.stack.push(val.clone());
         Ok(val)
     }
     fn push(&mut self, v: Value) {
+        self.memory += match &v {
+            //a vector that is shared was already counted where it came from
+            Value::Vec(vec) if Rc::strong_count(vec) > 1 => std::mem::size_of::<Value>(),
+            _ => size(&v, &mut Seen::new()),
+        };
         let mut scope: &mut Scope = &mut self.global;
         while scope.inner.is_some() {
             scope = scope.inner.as_mut().unwrap();
@@ -689,6 +1396,7 @@ impl VM {
 #[derive(Debug, Clone)]
 pub struct Scope {
     pub callframe: HashMap<String, Value>,
+    pub types: HashMap<String, Type>,
     pub inner: Option<Box<Scope>>,
     pub stack: Vec<Value>,
 }
@@ -696,8 +1404,1008 @@ impl Scope {
     pub fn new() -> Self {
         Self {
             callframe: HashMap::new(),
+            types: HashMap::new(),
             inner: None,
             stack: Vec::new(),
         }
     }
 }
+impl Drop for Scope {
+    fn drop(&mut self) {
+        //unlinks the chain one scope at a time, dropping it recursively runs out of stack on deep recursion
+        let mut inner = self.inner.take();
+        while let Some(mut scope) = inner {
+            inner = scope.inner.take();
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub params: Vec<Param>,
+    pub ret: Option<Type>,
+    pub chunk: Rc<Chunk>,
+    pub generator: bool,
+    pub span: Span, // where it is declared
+}
+
+#[derive(Debug, Clone)]
+pub struct CallFrame {
+    pub name: String,
+    pub ret: Option<Type>,
+    pub generator: bool, // ret is then the type of the values it yields, not of what it returns
+    pub chunk: Rc<Chunk>, // the caller's code, restored on return
+    pub index: i32,      // the caller's Call instruction
+    pub depth: usize,    // how many scopes were open in the caller
+}
+
+#[derive(Debug, Clone)]
+pub struct Handler {
+    pub catch: i32,    // the catch block of the try
+    pub frames: usize, // how many calls were active at the try
+    pub depth: usize,  // how many scopes were open at the try
+}
+
+// how many calls may be running at once unless the limits say otherwise
+pub const DEPTH: usize = 1000;
+
+// how many runs natives and generators start may be inside each other, each one takes rust stack
+pub const NESTED: usize = 100;
+
+// how much a program may run, the playground has to stop programs that never end
+#[derive(Debug, Clone)]
+pub struct Limits {
+    pub fuel: Option<u64>,       // how many instructions the program may run
+    pub time: Option<Duration>,  // how long the program may run
+    pub cancel: Arc<AtomicBool>, // set from another thread to stop the program
+    pub memory: Option<usize>,   // how many bytes the values of the program may take
+    pub depth: usize,            // how many calls may be running at once
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            fuel: None,
+            time: None,
+            cancel: Arc::default(),
+            memory: None,
+            depth: DEPTH,
+        }
+    }
+}
+
+// compares what two values hold, vectors element by element
+pub fn equal(a: &Value, b: &Value) -> bool {
+    equal_in(a, b, &mut Vec::new())
+}
+
+fn equal_in(a: &Value, b: &Value, comparing: &mut Vec<(VecPtr, VecPtr)>) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x == y,
+        (Value::Bool(x), Value::Bool(y)) => x == y,
+        (Value::String { string: x, .. }, Value::String { string: y, .. }) => x == y,
+        (Value::Vec(x), Value::Vec(y)) => {
+            //vectors that contain themselves are equal when they are equal up to where they repeat
+            let pair = (Rc::as_ptr(x), Rc::as_ptr(y));
+            if Rc::ptr_eq(x, y) || comparing.contains(&pair) {
+                return true;
+            }
+            comparing.push(pair);
+            let (x, y) = (x.borrow(), y.borrow());
+            let equal = x.len() == y.len()
+                && x.iter()
+                    .zip(y.iter())
+                    .all(|(a, b)| equal_in(a, b, comparing));
+            comparing.pop();
+            equal
+        }
+        (
+            Value::Range {
+                start,
+                end,
+                step,
+                inclusive,
+            },
+            Value::Range {
+                start: start2,
+                end: end2,
+                step: step2,
+                inclusive: inclusive2,
+            },
+        ) => start == start2 && end == end2 && step == step2 && inclusive == inclusive2,
+        (Value::Error { message: x, .. }, Value::Error { message: y, .. }) => x == y,
+        (Value::Ok(x), Value::Ok(y))
+        | (Value::Err(x), Value::Err(y))
+        | (Value::Some(x), Value::Some(y)) => equal_in(x, y, comparing),
+        (Value::None, Value::None) => true,
+        _ => false,
+    }
+}
+
+type VecPtr = *const RefCell<Vec<Value>>;
+type Seen = HashSet<VecPtr>;
+
+// roughly how many bytes a value takes, with everything it owns. a shared vector is counted once
+fn size(value: &Value, seen: &mut Seen) -> usize {
+    std::mem::size_of::<Value>()
+        + match value {
+            Value::String { string, .. } => string.len(),
+            Value::Error { message, .. } => message.len(),
+            Value::Vec(vec) => vec_size(vec, seen),
+            Value::Ok(x) | Value::Err(x) | Value::Some(x) => size(x, seen),
+            Value::Iterator(x) => match &**x {
+                Iter::Vec(vec, _) => vec_size(vec, seen),
+                Iter::String(chars, _) => chars.len() * std::mem::size_of::<char>(),
+                Iter::Generator(generator) => generator
+                    .borrow()
+                    .scope
+                    .as_deref()
+                    .map_or(0, |scope| scope_size(scope, seen)),
+                Iter::Range { .. } | Iter::Function(_) => 0,
+            },
+            Value::Number(_) | Value::Bool(_) | Value::Range { .. } | Value::None => 0,
+        }
+}
+
+fn vec_size(vec: &Rc<RefCell<Vec<Value>>>, seen: &mut Seen) -> usize {
+    if !seen.insert(Rc::as_ptr(vec)) {
+        return 0;
+    }
+    vec.borrow().iter().map(|x| size(x, seen)).sum()
+}
+
+// what a for loop goes through, one value at a time
+#[derive(Debug, Clone)]
+pub enum Iter {
+    Vec(Rc<RefCell<Vec<Value>>>, usize), // it sees the changes made to the vector while looping
+    Range {
+        start: f64,
+        step: f64,
+        len: usize,
+        index: usize,
+    },
+    String(Vec<char>, usize),
+    Function(String), // a klang function called for every value, until it returns None
+    Generator(Rc<RefCell<Generator>>), // shared by every copy, they all see the same values once
+}
+
+// the frame of a generator while it waits for the loop to ask for its next value. it is only data,
+// nothing of it lives on the rust stack between two values, so a suspended generator can be saved
+// and restored with the rest of the program
+#[derive(Debug, Clone)]
+pub struct Generator {
+    pub name: String,
+    pub ret: Option<Type>, // the type of the values it yields
+    pub chunk: Rc<Chunk>,
+    pub index: i32,                // the yield it stopped at
+    pub scope: Option<Box<Scope>>, // its variables, None once it returned
+    pub handlers: Vec<Handler>,    // its try blocks, counted from its own frame
+}
+
+// how many bytes the values of a scope and the scopes inside it take
+fn scope_size(scope: &Scope, seen: &mut Seen) -> usize {
+    let mut memory = 0;
+    let mut scope = Some(scope);
+    while let Some(s) = scope {
+        memory += s.stack.iter().map(|x| size(x, seen)).sum::<usize>();
+        memory += s.callframe.values().map(|x| size(x, seen)).sum::<usize>();
+        scope = s.inner.as_deref();
+    }
+    memory
+}
+
+// how many values a range goes through, a little forgiving so fractional steps still reach the end
+pub fn range_len(start: f64, end: f64, step: f64, inclusive: bool) -> usize {
+    let steps = (end - start) / step;
+    let len = match inclusive {
+        true => (steps + 1e-9).floor() + 1.0,
+        false => (steps - 1e-9).ceil(),
+    };
+    if len > 0.0 {
+        len as usize
+    } else {
+        0
+    }
+}
+
+pub fn bytes_to_string(bytes: usize) -> String {
+    match bytes {
+        0..=1023 => format!("{bytes} bytes"),
+        1024..=1048575 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1048576.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiling::compiler;
+    use crate::compiling::native::{ArgType, NativeFn};
+    use crate::error::Note;
+    use crate::interpreter::modules::{link, MemoryLoader};
+    use crate::interpreter::stmt::Stmt;
+    use crate::interpreter::{parser::Parser, scanner::Scanner};
+
+    // what a program prints, or the error it stops at
+    fn run(source: &str) -> Result<String, Diagnostic> {
+        run_with(source, NativeRegistry::standard())
+    }
+
+    // like run, but with use and import resolved by the linker first
+    fn run_linked(source: &str) -> Result<String, Diagnostic> {
+        let (modules, _) = link(MemoryLoader::MAIN, &MemoryLoader::split(source), 20)
+            .map_err(|(mut errors, _)| errors.remove(0))?;
+        let stmts = modules.into_iter().flat_map(|m| m.stmts).collect();
+        execute(stmts, NativeRegistry::standard(), Limits::default())
+    }
+
+    fn run_with(source: &str, natives: NativeRegistry) -> Result<String, Diagnostic> {
+        execute(parse(source), natives, Limits::default())
+    }
+
+    fn run_limited(source: &str, limits: Limits) -> Result<String, Diagnostic> {
+        execute(parse(source), NativeRegistry::standard(), limits)
+    }
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        Parser::new(tokens).parse().unwrap()
+    }
+
+    fn execute(
+        stmts: Vec<Stmt>,
+        natives: NativeRegistry,
+        limits: Limits,
+    ) -> Result<String, Diagnostic> {
+        let mut chunk = compiler::Chunk::new(compiler::compile(stmts));
+        compiler::resolve(&mut chunk, &natives).map_err(|mut errors| errors.remove(0))?;
+        let mut vm = VM::new(chunk, natives);
+        vm.limit(limits);
+        let mut output = String::new();
+        while vm.index < vm.chunk.code.len() as i32 {
+            output.push_str(&vm.once().map_err(|err| vm.uncaught(err))?);
+            vm.index += 1;
+        }
+        Ok(output)
+    }
+
+    #[test]
+    fn default_rest_and_named_arguments() {
+        let source = "fn f(a, b = 2, ...rest) { print(\"{a} {b} {rest}\"); }
+            f(1); f(1, 3); f(1, 3, 4, 5); f(b = 5, a = 6);";
+        assert_eq!(run(source).unwrap(), "1 2 []\n1 3 []\n1 3 [4, 5]\n6 5 []\n");
+    }
+
+    #[test]
+    fn named_arguments_must_match_a_parameter_once() {
+        let err = run("fn f(a) { return a; } f(b = 1);").unwrap_err();
+        assert!(err
+            .message
+            .contains("function \"f\" has no parameter named \"b\""));
+        let err = run("fn f(a) { return a; } f(1, a = 2);").unwrap_err();
+        assert!(err
+            .message
+            .contains("parameter \"a\" of function \"f\" was given twice"));
+    }
+
+    #[test]
+    fn calls_inside_loops_keep_their_jumps() {
+        let source = "fn inc(n) { return n + 1; }
+            let i = 0; let s = 0;
+            while i < 3 { s = inc(s); i = i + 1; }
+            print(\"{s}\");";
+        assert_eq!(run(source).unwrap(), "3\n");
+    }
+
+    const DOWN: &str = "fn down(n) { if n == 0 { return 0; } return down(n - 1) + 1; }";
+
+    #[test]
+    fn deep_recursion_runs() {
+        let source = format!("{DOWN} print(\"{{down(900)}}\");");
+        assert_eq!(run(&source).unwrap(), "900\n");
+        let source = format!("{DOWN} print(\"{{down(2000)}}\");");
+        assert!(run(&source)
+            .unwrap_err()
+            .message
+            .contains("more than 1000 calls are running"));
+    }
+
+    #[test]
+    fn argument_counts_are_singular() {
+        let err = run("fn f(a, ...rest) { return a; } f();").unwrap_err();
+        assert!(err
+            .message
+            .contains("takes at least 1 argument but 0 were given"));
+        let err = run("fn f(a, b) { return a; } f(1);").unwrap_err();
+        assert!(err.message.contains("takes 2 arguments but 1 was given"));
+    }
+
+    #[test]
+    fn let_destructures_vectors() {
+        let source = "let [a, b] = [1, 2];
+            let [head, ...tail] = [1, 2, 3];
+            let [x, [y, z]] = [4, [5, 6]];
+            print(\"{a} {b} {head} {tail} {x} {y} {z}\");";
+        assert_eq!(run(source).unwrap(), "1 2 1 [2, 3] 4 5 6\n");
+    }
+
+    #[test]
+    fn for_destructures_each_element() {
+        let source = "let v = [\"a\", \"b\"];
+            for [i, x] in std::enumerate(v) { print(\"{i} {x}\"); }";
+        assert_eq!(run(source).unwrap(), "0 a\n1 b\n");
+    }
+
+    #[test]
+    fn destructuring_needs_the_right_length() {
+        let err = run("let a = 1;\nlet [x, y] = [1, 2, 3];").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[RuntimeError] at line 2: expected 2 values to destructure but found 3"
+        );
+        let err = run("let [x, ...rest] = [];").unwrap_err();
+        assert!(err
+            .message
+            .contains("expected at least 1 values to destructure but found 0"));
+        let err = run("let [x] = 1;").unwrap_err();
+        assert!(err
+            .message
+            .contains("cannot destructure 1, it is not a vector"));
+    }
+
+    #[test]
+    fn annotated_variables_check_their_values() {
+        let err = run("let x: int = \"hi\";").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[RuntimeError] at line 1: mismatched types: expected int for \"x\" but found string"
+        );
+        let err = run("let x: int = 1;\nx = 2.5;").unwrap_err();
+        assert!(err.to_string().starts_with(
+            "[RuntimeError] at line 2: mismatched types: expected int for \"x\""
+        ));
+        let err = run("let x: string;\nx = true;").unwrap_err();
+        assert!(err
+            .message
+            .contains("expected string for \"x\" but found bool"));
+        assert_eq!(
+            run("let x: float = 1;\nx = 2.5;\nprint(\"{x}\");").unwrap(),
+            "2.5\n"
+        );
+    }
+
+    #[test]
+    fn annotated_parameters_check_their_arguments() {
+        let source =
+            "fn add(a: int, b: int) -> int { return a + b; }\nlet sum = add(1, 2);\nadd(1, \"2\");";
+        let err = run(source).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[RuntimeError] at line 3: mismatched types: expected int for parameter \"b\" of \"add\" but found string"
+        );
+        let err = run("fn f(a: int) { a = \"no\"; }\nf(1);").unwrap_err();
+        assert!(err
+            .message
+            .contains("expected int for \"a\" but found string"));
+        let err = run("fn f() -> string { return 1; }\nf();").unwrap_err();
+        assert!(err
+            .message
+            .contains("expected string for the return value of \"f\" but found int"));
+    }
+
+    #[test]
+    fn throw_unwinds_through_calls() {
+        let source = "fn inner(n) { throw \"boom {n}\"; }
+            fn outer(n) { let x = 1; return inner(n) + x; }
+            let before = 5;
+            try { outer(3); } catch e { print(\"{std::message(e)}\"); }
+            print(\"{before}\");";
+        assert_eq!(run(source).unwrap(), "boom 3\n5\n");
+    }
+
+    #[test]
+    fn runtime_errors_are_caught_inside_functions() {
+        let source = "fn div(n) { return n / 0; }
+            fn safe(n) { try { return div(n); } catch e { return -1; } }
+            print(\"{safe(1)} {safe(2)}\");
+            try { div(1); } catch e { print(\"{std::message(e)} at {std::line(e)}\"); }";
+        assert_eq!(run(source).unwrap(), "-1 -1\ndivision by zero at 1\n");
+    }
+
+    #[test]
+    fn nested_try_rethrows_to_the_outer_catch() {
+        let source = "try {
+                try { throw \"inner\"; } catch e { throw \"outer {std::message(e)}\"; }
+            } catch e { print(\"{std::message(e)}\"); }";
+        assert_eq!(run(source).unwrap(), "outer inner\n");
+    }
+
+    #[test]
+    fn uncaught_throw_stops_the_program() {
+        let err = run("fn f() { throw \"nope\"; } f(); print(\"after\");").unwrap_err();
+        assert_eq!(err.header(), "[RuntimeError K0424] at line 1: nope");
+    }
+
+    #[test]
+    fn results_and_options_are_values() {
+        let source = "let ok = Ok(1); let err = Err(\"no\"); let some = Some(2.5); let none = None;
+            print(\"{ok} {err} {some} {none}\");
+            print(\"{std::unwrap(ok)} {std::unwrap_or(err, 0)} {std::is_some(some)} {std::is_none(none)}\");";
+        assert_eq!(
+            run(source).unwrap(),
+            "Ok(1) Err(no) Some(2.5) None\n1 0 true true\n"
+        );
+        let err = run("std::unwrap(Err(\"bad\"));").unwrap_err();
+        assert!(err.message.contains("called unwrap on Err(bad)"));
+    }
+
+    #[test]
+    fn question_mark_unwraps_or_returns_early() {
+        let source = "fn double(s) {
+                let n = std::parse(s)?;
+                return Ok(n * 2);
+            }
+            let a = double(\"21\"); let b = double(\"x\");
+            print(\"{a} {b}\");";
+        assert_eq!(
+            run(source).unwrap(),
+            "Ok(42) Err(cannot parse \"x\" as a number)\n"
+        );
+        let source = "fn first(v) {
+                let x = std::vec::try_get(v, 0)?;
+                return Some(x + 1);
+            }
+            print(\"{first([1])} {first([])}\");";
+        assert_eq!(run(source).unwrap(), "Some(2) None\n");
+    }
+
+    #[test]
+    fn question_mark_needs_a_result_or_option() {
+        let err = run("fn f() { return 1?; } f();").unwrap_err();
+        assert!(err.code == Some(messages::INVALID_UNWRAP.code), "{err}");
+    }
+
+    #[test]
+    fn use_std_aliases_the_module() {
+        let source = "use std::math;\nprint(\"{math::abs(-2)}\");";
+        assert_eq!(run_linked(source).unwrap(), "2\n");
+        let source = "use std::math::{abs};\nprint(\"{abs(-2)}\");";
+        assert_eq!(run_linked(source).unwrap(), "2\n");
+        let err = run_linked("print(\"{math::abs(-2)}\");").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[CompileError] at line 1: module \"math\" is not imported"
+        );
+    }
+
+    #[test]
+    fn own_functions_are_not_natives() {
+        let source = "use std::math::{abs};\nfn abs(x) { return x; }\nprint(\"{abs(-2)}\");";
+        assert_eq!(run_linked(source).unwrap(), "-2\n");
+    }
+
+    #[test]
+    fn old_names_resolve_unless_ambiguous() {
+        let twice = || NativeFn {
+            name: "twice".to_string(),
+            min: 1,
+            max: Some(1),
+            types: vec![ArgType::Number],
+            function: Box::new(|args, _| match args[0] {
+                Value::Number(x) => Ok(Value::Number(x * 2.0)),
+                _ => unreachable!(),
+            }),
+        };
+        let mut natives = NativeRegistry::standard();
+        let math = natives.resolve("math::abs");
+        assert!(math.is_some());
+        assert_eq!(natives.resolve("abs"), math);
+        natives.group("a", vec![twice()]);
+        assert!(natives.resolve("twice").is_some());
+        natives.group("b", vec![twice()]);
+        assert_eq!(natives.resolve("twice"), None);
+        assert!(natives.resolve("b::twice").is_some());
+    }
+
+    #[test]
+    fn hosts_can_register_closures() {
+        let mut natives = NativeRegistry::standard();
+        let offset = 10.0;
+        natives.register(
+            "host::shift",
+            1,
+            Some(1),
+            vec![ArgType::Number],
+            move |args, _| match args[0] {
+                Value::Number(x) => Ok(Value::Number(x + offset)),
+                _ => unreachable!(),
+            },
+        );
+        let source = "print(\"{std::host::shift(5)}\");";
+        assert_eq!(run_with(source, natives).unwrap(), "15\n");
+    }
+
+    #[test]
+    fn variadic_natives_check_their_arity() {
+        let mut natives = NativeRegistry::standard();
+        natives.register("host::sum", 1, Some(3), vec![ArgType::Number], |args, _| {
+            let mut sum = 0.0;
+            for arg in args {
+                if let Value::Number(x) = arg {
+                    sum += x;
+                }
+            }
+            Ok(Value::Number(sum))
+        });
+        let source = "print(\"{std::host::sum(1)} {std::host::sum(1, 2, 3)}\");";
+        assert_eq!(run_with(source, natives.clone()).unwrap(), "1 6\n");
+        let err = run_with("std::host::sum();", natives.clone()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[RuntimeError] at line 1: std::host::sum takes 1 to 3 arguments but 0 were given"
+        );
+        let err = run_with("std::host::sum(1, 2, 3, 4);", natives.clone()).unwrap_err();
+        assert!(err
+            .message
+            .contains("takes 1 to 3 arguments but 4 were given"));
+        let err = run_with("std::host::sum(1, \"2\");", natives).unwrap_err();
+        assert!(err
+            .message
+            .contains("std::host::sum expects a number for argument 2 but found string"));
+    }
+
+    #[test]
+    fn disabled_groups_fail_to_resolve() {
+        let mut natives = NativeRegistry::standard();
+        natives.disable("time");
+        let err = run_with("let x = 1;\nstd::time::time();", natives).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[CompileError] at line 2: there is no native function std::time::time"
+        );
+    }
+
+    #[test]
+    fn natives_can_call_back_into_klang() {
+        let mut natives = NativeRegistry::standard();
+        natives.register(
+            "host::twice",
+            2,
+            Some(2),
+            vec![ArgType::String, ArgType::Any],
+            |args, vm| {
+                let name = args[0].to_string();
+                let once = vm.call_function(&name, vec![args[1].clone()])?;
+                vm.call_function(&name, vec![once])
+            },
+        );
+        let source = "fn inc(n) { return n + 1; }
+            let x = std::host::twice(\"inc\", 1);
+            print(\"{x}\");";
+        assert_eq!(run_with(source, natives.clone()).unwrap(), "3\n");
+        let source = "fn fail(n) { return n / 0; }
+            try { std::host::twice(\"fail\", 1); } catch e { print(\"{std::error::message(e)}\"); }";
+        assert_eq!(run_with(source, natives).unwrap(), "division by zero\n");
+    }
+
+    #[test]
+    fn nested_callbacks_are_capped() {
+        let source =
+            "fn cb(x) { let v = std::vec::map([x], \"inner\"); return std::vec::get(v, 0); }
+            fn inner(x) { if x == 0 { return 0; } return cb(x - 1); }
+            print(\"{inner(1000)}\");";
+        assert!(run(source)
+            .unwrap_err()
+            .message
+            .contains(&format!("more than {NESTED} calls are running")));
+    }
+
+    #[test]
+    fn depth_comes_from_the_limits() {
+        let source = format!("{DOWN} print(\"{{down(50)}}\");");
+        let limits = Limits {
+            depth: 20,
+            ..Limits::default()
+        };
+        let err = run_limited(&source, limits).unwrap_err();
+        assert!(err.code == Some(messages::RECURSION_LIMIT.code));
+    }
+
+    const FOREVER: &str = "try { while true {} } catch e { print(\"caught\"); }";
+
+    #[test]
+    fn fuel_stops_the_program_inside_try() {
+        let limits = Limits {
+            fuel: Some(1000),
+            ..Limits::default()
+        };
+        let err = run_limited(FOREVER, limits).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[RuntimeError] at line 1: out of fuel: the program ran more than 1000 instructions"
+        );
+    }
+
+    #[test]
+    fn fuel_is_enough_for_short_programs() {
+        let limits = Limits {
+            fuel: Some(1000),
+            ..Limits::default()
+        };
+        assert_eq!(run_limited("print(\"hi\");", limits).unwrap(), "hi\n");
+    }
+
+    #[test]
+    fn timeout_stops_the_program_inside_try() {
+        let limits = Limits {
+            time: Some(Duration::ZERO),
+            ..Limits::default()
+        };
+        let err = run_limited(FOREVER, limits).unwrap_err();
+        assert!(err
+            .message
+            .contains("timed out: the program ran longer than 0 seconds"));
+    }
+
+    #[test]
+    fn cancel_stops_the_program_inside_try() {
+        let limits = Limits::default();
+        limits.cancel.store(true, Ordering::Relaxed);
+        let err = run_limited(FOREVER, limits).unwrap_err();
+        assert!(err.message.contains("the program was cancelled"));
+    }
+
+    #[test]
+    fn memory_cap_stops_the_program_inside_try() {
+        let source = "let v = [];
+            try { while true { std::vec::push(v, \"some text\"); } } catch e { print(\"caught\"); }";
+        let limits = Limits {
+            memory: Some(10_000),
+            ..Limits::default()
+        };
+        let err = run_limited(source, limits).unwrap_err();
+        assert!(
+            err.message
+                .contains("out of memory: the program used more than 9.8 KB"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn memory_cap_lets_small_programs_run() {
+        let source = "let v = [1, 2, 3]; let w = v; print(\"{std::vec::len(w)}\");";
+        let limits = Limits {
+            memory: Some(10_000),
+            ..Limits::default()
+        };
+        assert_eq!(run_limited(source, limits).unwrap(), "3\n");
+    }
+
+    #[test]
+    fn ranges_iterate_every_way() {
+        let source = "for i in 0..=2 { print(\"{i}\"); }
+            for i in 2..0 { print(\"d{i}\"); }
+            for i in 0..1..0.25 { print(\"f{i}\"); }";
+        assert_eq!(
+            run(source).unwrap(),
+            "0\n1\n2\nd2\nd1\nf0\nf0.25\nf0.5\nf0.75\n"
+        );
+    }
+
+    #[test]
+    fn huge_ranges_are_lazy() {
+        let source = "fn first(r) { for i in r { if i == 2 { return i; } } }
+            let r = 0..=1000000000;
+            print(\"{first(r)} {std::vec::get(r, 5)} {std::vec::len(r)}\");";
+        let limits = Limits {
+            memory: Some(10_000),
+            ..Limits::default()
+        };
+        assert_eq!(run_limited(source, limits).unwrap(), "2 5 1000000001\n");
+    }
+
+    #[test]
+    fn for_loops_over_strings_and_iterator_functions() {
+        let source = "let n = 0;
+            fn next() { n = n + 1; if n > 3 { return None; } return Some(n * 10); }
+            for x in std::iter::from_fn(\"next\") { print(\"{x}\"); }
+            for c in \"ab\" { print(\"{c}\"); }";
+        assert_eq!(run(source).unwrap(), "10\n20\n30\na\nb\n");
+    }
+
+    #[test]
+    fn vector_loops_see_pushes() {
+        let source = "let v = [1, 2];
+            for x in v { if x < 3 { std::vec::push(v, x + 2); } print(\"{x}\"); }";
+        assert_eq!(run(source).unwrap(), "1\n2\n3\n4\n");
+    }
+
+    #[test]
+    fn for_over_a_number_is_an_error() {
+        let err = run("for x in 5 { print(\"{x}\"); }").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[RuntimeError] at line 1: cannot loop over int, it is not iterable"
+        );
+    }
+
+    #[test]
+    fn vectors_are_shared_between_names() {
+        let source = "let a = [1];
+            let b = a;
+            std::vec::push(b, 2);
+            fn add(v) { std::vec::push(v, 3); }
+            add(a);
+            print(\"{a} {std::same(a, b)}\");";
+        assert_eq!(run(source).unwrap(), "[1, 2, 3] true\n");
+    }
+
+    #[test]
+    fn copies_are_not_the_same_vector() {
+        let source = "let a = [1, [2]];
+            let b = std::vec::copy(a);
+            std::vec::push(b, 3);
+            print(\"{a} {b} {std::same(a, b)} {a == [1, [2]]} {std::same(a, [1, [2]])}\");";
+        assert_eq!(
+            run(source).unwrap(),
+            "[1, [2]] [1, [2], 3] false true false\n"
+        );
+    }
+
+    #[test]
+    fn a_vector_can_hold_itself() {
+        let source = "let a = [1]; std::vec::push(a, a); print(\"{std::vec::len(a)} {a == a}\");";
+        assert_eq!(run(source).unwrap(), "2 true\n");
+    }
+
+    #[test]
+    fn argument_count_errors_point_at_the_call() {
+        let source = "fn add(a, b) { return a + b; }\nlet x = add(1);";
+        let err = run(source).unwrap_err();
+        assert_eq!(err.span, Span::new(2, 39, 45));
+        assert_eq!(&source[39..45], "add(1)");
+        let source = "let x = 1;\nlet y = std::math::abs(1, 2);";
+        let err = run(source).unwrap_err();
+        assert_eq!(err.span, Span::new(2, 19, 39));
+        assert_eq!(&source[19..39], "std::math::abs(1, 2)");
+    }
+
+    // the calls an error happened inside, innermost first: the function, its line and repeats
+    fn frames(err: &Diagnostic) -> Vec<(&str, usize, usize)> {
+        err.notes
+            .iter()
+            .filter_map(|note| match note {
+                Note::Frame(frame) => {
+                    Some((frame.function.as_str(), frame.span.line, frame.repeats))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn uncaught_errors_carry_their_calls() {
+        let source = "fn inner(n) {
+                return n / 0;
+            }
+            fn middle(n) {
+                let x = 1;
+                return inner(n) + x;
+            }
+            fn outer() { return middle(2); }
+            print(\"start\");
+            outer();";
+        let err = run(source).unwrap_err();
+        assert_eq!(err.span.line, 2);
+        assert_eq!(
+            frames(&err),
+            vec![
+                ("inner", 2, 0),
+                ("middle", 6, 0),
+                ("outer", 8, 0),
+                ("top level", 10, 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn caught_errors_and_top_level_errors_have_no_calls() {
+        assert!(frames(&run("let x = 1 / 0;").unwrap_err()).is_empty());
+        let source = "fn f() { return 1 / 0; }
+            try { f(); } catch e { print(\"caught\"); }
+            let x = 1 / 0;";
+        let err = run(source).unwrap_err();
+        assert_eq!(err.span.line, 3);
+        assert!(frames(&err).is_empty());
+    }
+
+    #[test]
+    fn recursion_is_traced_once_per_place() {
+        let source = "fn down(n) {
+                if n == 0 { return 1 / 0; }
+                return down(n - 1);
+            }
+            down(50);";
+        let err = run(source).unwrap_err();
+        assert_eq!(
+            frames(&err),
+            vec![("down", 2, 0), ("down", 3, 49), ("top level", 5, 0)]
+        );
+    }
+
+    #[test]
+    fn typed_generator_finishes() {
+        let source = "fn* g() -> int { yield 1; yield 2; } for x in g() { print(\"{x}\"); }";
+        assert_eq!(run(source).unwrap(), "1\n2\n");
+    }
+
+    #[test]
+    fn typed_generator_checks_yields() {
+        let source = "fn* g() -> int { yield \"a\"; } for x in g() { print(\"{x}\"); }";
+        assert_eq!(
+            run(source).unwrap_err().code,
+            Some(messages::MISMATCHED_TYPES.code)
+        );
+    }
+
+    #[test]
+    fn typed_function_checks_returns() {
+        let source = "fn f() -> int { return \"a\"; } print(\"{f()}\");";
+        assert_eq!(
+            run(source).unwrap_err().code,
+            Some(messages::MISMATCHED_TYPES.code)
+        );
+    }
+
+    const COUNT: &str = "fn* count(n) { let i = 0; while i < n { yield i; i = i + 1; } }";
+
+    #[test]
+    fn a_generator_runs_once() {
+        let source = format!(
+            "{COUNT} let g = count(2);
+            for x in g {{ print(\"{{x}}\"); }}
+            for x in g {{ print(\"again {{x}}\"); }}
+            for x in count(1) {{ print(\"new {{x}}\"); }}"
+        );
+        assert_eq!(run(&source).unwrap(), "0\n1\nnew 0\n");
+    }
+
+    #[test]
+    fn a_generator_left_early_goes_on_where_it_stopped() {
+        let source = format!(
+            "{COUNT} fn two(g) {{ for x in g {{ print(\"{{x}}\"); if x == 1 {{ return 0; }} }} }}
+            let g = count(4);
+            two(g);
+            for x in g {{ print(\"then {{x}}\"); }}"
+        );
+        assert_eq!(run(&source).unwrap(), "0\n1\nthen 2\nthen 3\n");
+    }
+
+    #[test]
+    fn try_blocks_reach_across_yields() {
+        let source =
+            "fn* safe() { try { yield 1; throw \"inside\"; } catch e { yield 2; } yield 3; }
+            for x in safe() { print(\"{x}\"); }";
+        assert_eq!(run(source).unwrap(), "1\n2\n3\n");
+        let source = "fn* late() { yield 1; throw \"late\"; }
+            try {
+                for x in late() { print(\"{x}\"); }
+            } catch e { print(\"caught {std::error::message(e)}\"); }
+            print(\"after\");";
+        assert_eq!(run(source).unwrap(), "1\ncaught late\nafter\n");
+    }
+
+    #[test]
+    fn get_returns_the_element() {
+        let source =
+            "let v = [1, 2]; print(\"{std::vec::get(v, 1)} {std::vec::get(0..10..2, 2)}\");";
+        assert_eq!(run(source).unwrap(), "2 4\n");
+        let err = run("print(\"{std::vec::get([1, 2], 2)}\");").unwrap_err();
+        assert!(err.message.contains("index 2 is out of bounds"));
+    }
+
+    #[test]
+    fn try_get_returns_an_option() {
+        let source =
+            "let v = [1, 2]; print(\"{std::vec::try_get(v, 1)} {std::vec::try_get(v, 2)}\");";
+        assert_eq!(run(source).unwrap(), "Some(2) None\n");
+    }
+
+    // the help lines of the errors resolving the calls of a program
+    fn resolve_helps(source: &str) -> Vec<String> {
+        let mut chunk = compiler::Chunk::new(compiler::compile(parse(source)));
+        let errors = compiler::resolve(&mut chunk, &NativeRegistry::standard()).unwrap_err();
+        errors
+            .iter()
+            .flat_map(|error| error.notes.iter())
+            .filter_map(|note| match note {
+                Note::Help(help) => Some(help.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn misspelled_calls_suggest_the_same_way() {
+        let source = "fn double(n) { return n * 2; } std::math::sn(1); doubel(2);";
+        assert_eq!(
+            resolve_helps(source),
+            vec![
+                "did you mean \"std::math::sin\"?",
+                "did you mean \"double\"?"
+            ]
+        );
+        let error = run("let total = 1; print(\"{totl}\");").unwrap_err();
+        assert!(
+            matches!(&error.notes[..], [Note::Help(help)] if help == "did you mean \"total\"?")
+        );
+    }
+
+    #[test]
+    fn vector_natives_take_ranges() {
+        let source =
+            "print(\"{std::vec::copy(0..3)} {std::vec::len(0..=3)} {std::vec::get(0..10..5, 1)}\");
+            fn inc(n) { return n + 1; }
+            let incremented = std::vec::map(0..2, \"inc\");
+            print(\"{std::vec::enumerate(1..3)} {incremented}\");";
+        assert_eq!(
+            run(source).unwrap(),
+            "[0, 1, 2] 4 5\n[[0, 1], [1, 2]] [1, 2]\n"
+        );
+    }
+
+    #[test]
+    fn native_errors_have_their_own_codes() {
+        let cases: [(&str, &Message, &str); 7] = [
+            (
+                "std::math::sin(1, 2);",
+                &messages::NATIVE_ARGUMENT_COUNT,
+                "std::math::sin takes 1 argument but 2 were given",
+            ),
+            (
+                "std::math::sin(\"a\");",
+                &messages::NATIVE_ARGUMENT_TYPE,
+                "std::math::sin expects a number for argument 1 but found string",
+            ),
+            (
+                "std::result::unwrap(1);",
+                &messages::NATIVE_EXPECTED,
+                "std::result::unwrap expects an Ok, Err, Some or None",
+            ),
+            (
+                "std::vec::get([1], 1);",
+                &messages::INDEX_OUT_OF_BOUNDS,
+                "index 1 is out of bounds",
+            ),
+            (
+                "std::rand::range(2, 2);",
+                &messages::EMPTY_RANDOM_RANGE,
+                "std::rand::range needs a min smaller than its max, but got 2 and 2",
+            ),
+            (
+                "std::result::unwrap(std::num::parse(\"x\"));",
+                &messages::UNWRAP_FAILED,
+                "called unwrap on Err(cannot parse \"x\" as a number)",
+            ),
+            (
+                "std::iter::from_fn(\"nothing\");",
+                &messages::NO_ITERATOR_FUNCTION,
+                "there is no function named \"nothing\" to iterate with",
+            ),
+        ];
+        for (source, message, text) in cases {
+            let error = run(source).unwrap_err();
+            assert_eq!(error.code, Some(message.code), "{source}");
+            assert_eq!(error.message, text);
+        }
+    }
+
+    #[test]
+    fn native_errors_point_at_the_call() {
+        let source =
+            "fn not_bool(x) { return 1; }\nprint(\"start\");\nstd::vec::filter([1], \"not_bool\");";
+        let error = run(source).unwrap_err();
+        assert_eq!(error.code, Some(messages::FILTER_NOT_BOOL.code));
+        assert_eq!(error.span.line, 3);
+    }
+}
@@ -1,13 +1,250 @@
 #![allow(clippy::enum_variant_names)]
-#[derive(Debug)]
+use crate::messages::{self, Message};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum KlangError {
     ScannerError,
     ParserError,
+    CompileError,
     RuntimeError,
 }
 
 impl KlangError {
-    pub fn error(et: KlangError, msg: &str, line: usize) -> String {
-        format!("[{et:?}] at line {}: {}", line, msg)
+    pub fn error(et: KlangError, message: &Message, args: &[&str], line: usize) -> Diagnostic {
+        KlangError::at(et, message, args, Span::line(line))
+    }
+    pub fn at(et: KlangError, message: &Message, args: &[&str], span: Span) -> Diagnostic {
+        let mut diagnostic = Diagnostic::new(et, &message.text(args), span);
+        diagnostic.code = Some(message.code);
+        diagnostic
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+// where something is in the source: its line, and its bytes from the start of its file
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, start: usize, end: usize) -> Span {
+        Span { line, start, end }
+    }
+    pub fn line(line: usize) -> Span {
+        //a span that only knows its line, for code that has no place in the source
+        Span {
+            line,
+            start: 0,
+            end: 0,
+        }
+    }
+    pub fn to(self, other: Span) -> Span {
+        //from the start of this span to the end of the other, on the line of the first
+        if other.end <= other.start {
+            return self;
+        }
+        if self.end <= self.start {
+            return other;
+        }
+        Span {
+            line: self.line.min(other.line),
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+// a secondary place a diagnostic points at, with what is there
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+// a call that was running when a runtime error happened: the function, and where it was in it
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub function: String,
+    pub span: Span,
+    pub repeats: usize, // how many more times the same call is right under it, for deep recursion
+}
+
+// a line under a diagnostic, telling more about it or how to fix it
+#[derive(Debug, Clone)]
+pub enum Note {
+    Note(String),
+    Help(String),
+    Frame(Frame),
+}
+
+// an error or warning from any stage, from scanning to running
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub kind: KlangError,
+    pub code: Option<u16>, // shown as K0123
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    pub labels: Vec<Label>,
+    pub notes: Vec<Note>,
+    pub file: Option<Box<str>>, // set when the error is not in the main file
+}
+
+impl Diagnostic {
+    pub fn new(kind: KlangError, message: &str, span: Span) -> Diagnostic {
+        Diagnostic {
+            kind,
+            code: None,
+            severity: Severity::Error,
+            message: message.to_string(),
+            span,
+            labels: Vec::new(),
+            notes: Vec::new(),
+            file: None,
+        }
+    }
+    pub fn label(mut self, span: Span, message: &str) -> Diagnostic {
+        self.labels.push(Label {
+            span,
+            message: message.to_string(),
+        });
+        self
+    }
+    pub fn note(mut self, note: &str) -> Diagnostic {
+        self.notes.push(Note::Note(note.to_string()));
+        self
+    }
+    pub fn help(mut self, help: &str) -> Diagnostic {
+        self.notes.push(Note::Help(help.to_string()));
+        self
+    }
+    pub fn did_you_mean(self, name: Option<&str>) -> Diagnostic {
+        //the name found by suggest, when there was one close enough
+        match name {
+            Some(name) => self.help(&messages::DID_YOU_MEAN.text(&[name])),
+            None => self,
+        }
+    }
+    pub fn frame(mut self, function: &str, span: Span) -> Diagnostic {
+        //the next call out from the error, the same call over and over is kept once
+        if let Some(Note::Frame(last)) = self.notes.last_mut() {
+            if last.function == function && last.span == span {
+                last.repeats += 1;
+                return self;
+            }
+        }
+        self.notes.push(Note::Frame(Frame {
+            function: function.to_string(),
+            span,
+            repeats: 0,
+        }));
+        self
+    }
+    pub fn traced(&self) -> bool {
+        self.notes.iter().any(|note| matches!(note, Note::Frame(_)))
+    }
+    pub fn header(&self) -> String {
+        //the first line render shows: its kind and code, where it is and its message
+        self.first_line(self.code)
+    }
+    fn first_line(&self, code: Option<u16>) -> String {
+        let kind = match self.severity {
+            Severity::Warning => "Warning".to_string(),
+            Severity::Error => format!("{:?}", self.kind),
+        };
+        let mut line = match code {
+            Some(code) => format!("[{kind} K{code:04}]"),
+            None => format!("[{kind}]"),
+        };
+        if let Some(file) = &self.file {
+            line += &format!(" in {file}");
+        }
+        line + &format!(" at line {}: {}", self.span.line, self.message)
+    }
+}
+
+// the plain text errors were before they had codes, [Kind] at line N: message, for hosts that
+// match on it. codes only show up in render
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.first_line(None))?;
+        for label in self.labels.iter() {
+            write!(f, " ({} at line {})", label.message, label.span.line)?;
+        }
+        for note in self.notes.iter() {
+            match note {
+                Note::Note(note) => write!(f, "\n  note: {note}")?,
+                Note::Help(help) => write!(f, "\n  help: {help}")?,
+                Note::Frame(frame) => {
+                    write!(f, "\n  in {} at line {}", frame.function, frame.span.line)?;
+                    if frame.repeats > 0 {
+                        write!(f, " ({} more times)", frame.repeats)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// the candidate closest to a name nothing is called, when it is close enough to be a typo of it
+pub fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| {
+            //on a tie the names it is missing letters of win, like sin for sn
+            let mut letters = candidate.chars();
+            let missing = !name.chars().all(|c| letters.any(|x| x == c));
+            (distance(name, candidate), missing, candidate)
+        })
+        .filter(|(distance, ..)| *distance <= limit)
+        .min()
+        .map(|(.., candidate)| candidate)
+}
+
+// how many characters have to be added, removed or replaced to turn one name into the other
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let replaced = diagonal + usize::from(x != *y);
+            diagonal = row[j + 1];
+            row[j + 1] = replaced.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_leaves_out_the_code() {
+        let error = KlangError::error(KlangError::RuntimeError, &messages::THROWN, &["nope"], 3);
+        assert_eq!(error.to_string(), "[RuntimeError] at line 3: nope");
+        assert_eq!(error.header(), "[RuntimeError K0424] at line 3: nope");
+        let mut error = Diagnostic::new(KlangError::CompileError, "cannot assign", Span::line(2))
+            .label(Span::line(1), "declared")
+            .help("declare it with mut");
+        error.file = Some("utils.kl".into());
+        assert_eq!(
+            error.to_string(),
+            "[CompileError] in utils.kl at line 2: cannot assign (declared at line 1)\n  help: declare it with mut"
+        );
     }
 }
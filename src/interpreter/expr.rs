@@ -1,3 +1,4 @@
+use crate::error::Span;
 use crate::scanner::{Token, Value};
 
 #[derive(Clone, Debug)]
@@ -14,10 +15,12 @@ pub enum Expr {
     Call {
         callee: Box<Expr>,
         arguments: Vec<Expr>,
+        named: Vec<(Token, Expr)>, // f(a, b = 2)
         native: bool,
+        span: Span, // from the name to the closing )
     },
-    Grouping(Box<Expr>), // "(" expression ")"
-    Literal(Value, usize),
+    Grouping(Box<Expr>, Span), // "(" expression ")"
+    Literal(Value, Span),
 
     Unary {
         operator: Token,
@@ -28,7 +31,47 @@ pub enum Expr {
         min: Box<Expr>,
         max: Box<Expr>,
         step: Option<Box<Expr>>,
-        line: usize,
+        inclusive: bool, // ..= includes the end
+        span: Span,      // the .. or ..=
     }, // range
-    Vec(Vec<Expr>),
+    Vec(Vec<Expr>, Span),
+    Wrap {
+        variant: Token,
+        value: Box<Expr>,
+    }, // Ok(x), Err(x) or Some(x)
+    Unwrap(Box<Expr>, Span), // expression?
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        //the whole expression, from its first token to its last
+        match self {
+            Expr::Assign { name, value } => name.span.to(value.span()),
+            Expr::Binary { left, right, .. } => left.span().to(right.span()),
+            Expr::Call { span, .. }
+            | Expr::Grouping(_, span)
+            | Expr::Literal(_, span)
+            | Expr::Vec(_, span) => *span,
+            Expr::Unary {
+                operator,
+                expression,
+            } => operator.span.to(expression.span()),
+            Expr::Variable(name) => name.span,
+            Expr::Range {
+                min,
+                max,
+                step,
+                span,
+                ..
+            } => {
+                let span = min.span().to(*span).to(max.span());
+                match step {
+                    Some(step) => span.to(step.span()),
+                    None => span,
+                }
+            }
+            Expr::Wrap { variant, value } => variant.span.to(value.span()),
+            Expr::Unwrap(expr, span) => expr.span().to(*span),
+        }
+    }
 }
@@ -1,4 +1,5 @@
 pub mod expr;
+pub mod modules;
 pub mod parser;
 pub mod scanner;
 pub mod stmt;
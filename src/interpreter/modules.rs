@@ -0,0 +1,759 @@
+use super::expr::Expr;
+use super::parser::Parser;
+use super::scanner::{Scanner, Token, Value};
+use super::stmt::{Pattern, Stmt};
+use crate::error::{Diagnostic, KlangError, Span};
+use crate::messages::{self, Message};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+// where the source of imported files comes from
+pub trait Loader {
+    fn load(&self, path: &str) -> Result<String, String>;
+}
+
+// reads files relative to the directory of the main file (the cli)
+pub struct FileLoader {
+    pub root: PathBuf,
+}
+
+impl Loader for FileLoader {
+    fn load(&self, path: &str) -> Result<String, String> {
+        std::fs::read_to_string(self.root.join(path))
+            .map_err(|err| format!("cannot read \"{path}\": {err}"))
+    }
+}
+
+// files posted together to the playground, each one starts with // klang:file name.kl
+pub struct MemoryLoader {
+    pub files: HashMap<String, String>,
+}
+
+impl MemoryLoader {
+    pub const MAIN: &'static str = "main.kl";
+    pub fn split(source: &str) -> MemoryLoader {
+        let mut files: HashMap<String, String> = HashMap::new();
+        let mut name = MemoryLoader::MAIN.to_string();
+        let mut file = String::new();
+        for line in source.split_inclusive('\n') {
+            match line.trim().strip_prefix("// klang:file ") {
+                Some(next) => {
+                    files.insert(name, file);
+                    name = normalize(next.trim());
+                    file = String::new();
+                }
+                None => file.push_str(line),
+            }
+        }
+        files.insert(name, file);
+        MemoryLoader { files }
+    }
+}
+
+impl Loader for MemoryLoader {
+    fn load(&self, path: &str) -> Result<String, String> {
+        match self.files.get(path) {
+            Some(x) => Ok(x.clone()),
+            None => Err(format!("cannot find module \"{path}\"")),
+        }
+    }
+}
+
+// every file gets its own range of line numbers, so a line tells which file it came from
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    pub files: Vec<SourceFile>, // main first
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub name: String,
+    pub base: usize, // the line before its first line
+    pub source: String,
+}
+
+impl SourceMap {
+    pub fn file(&self, line: usize) -> Option<&SourceFile> {
+        self.files.iter().rev().find(|file| line > file.base)
+    }
+    pub fn locate(&self, line: usize) -> (&str, usize) {
+        match self.file(line) {
+            Some(file) => (file.name.as_str(), line - file.base),
+            None => ("", line),
+        }
+    }
+    pub fn annotate(&self, mut diagnostic: Diagnostic) -> Diagnostic {
+        //points the lines of a diagnostic that are not in the main file at their own file
+        if let Some(base) = self.files.get(1).map(|file| file.base) {
+            if diagnostic.span.line > base {
+                let (file, line) = self.locate(diagnostic.span.line);
+                diagnostic.file = Some(file.into());
+                diagnostic.span.line = line;
+            }
+            for label in diagnostic.labels.iter_mut() {
+                label.span.line = self.locate(label.span.line).1;
+            }
+        }
+        diagnostic
+    }
+}
+
+pub struct Module {
+    pub stmts: Vec<Stmt>,
+    pub directives: Vec<String>,
+    pub read_only: Vec<ReadOnly>, // the top level names other modules may not assign to
+    pub declared: HashSet<String>, // every name the file declares or assigns, in any scope
+}
+
+// a constant, or a variable declared without mut in a file with // klang:immutable
+#[derive(Debug, Clone)]
+pub struct ReadOnly {
+    pub name: String, // as the other modules see it once linked, like utils::X
+    pub constant: bool,
+    pub span: Span,
+}
+
+// loads the main file and everything it imports, the modules come in the order they should run
+// the errors come with the files read so far, to show the lines they point at
+pub fn link(
+    main: &str,
+    loader: &dyn Loader,
+    max_errors: usize,
+) -> Result<(Vec<Module>, SourceMap), (Vec<Diagnostic>, SourceMap)> {
+    let mut linker = Linker {
+        loader,
+        max_errors,
+        modules: Vec::new(),
+        exports: HashMap::new(),
+        loading: Vec::new(),
+        sources: SourceMap::default(),
+        next_line: 0,
+    };
+    let source = match loader.load(main) {
+        Ok(t) => t,
+        Err(s) => {
+            let error =
+                KlangError::error(KlangError::CompileError, &messages::LOAD_FAILED, &[&s], 0);
+            return Err((vec![error], linker.sources));
+        }
+    };
+    match linker.module(main.to_string(), source, None) {
+        Ok(t) => t,
+        Err(errors) => return Err((errors, linker.sources)),
+    };
+    Ok((linker.modules, linker.sources))
+}
+
+struct Linker<'a> {
+    loader: &'a dyn Loader,
+    max_errors: usize, // parse errors collected per file
+    modules: Vec<Module>,
+    exports: HashMap<String, HashSet<String>>, // the top level names of every loaded module
+    loading: Vec<String>,                      // the imports being loaded, to find cycles
+    sources: SourceMap,
+    next_line: usize,
+}
+
+impl<'a> Linker<'a> {
+    fn module(
+        &mut self,
+        path: String,
+        source: String,
+        prefix: Option<String>,
+    ) -> Result<(), Vec<Diagnostic>> {
+        self.sources.files.push(SourceFile {
+            name: path.clone(),
+            base: self.next_line,
+            source: source.clone(),
+        });
+        let mut scanner = Scanner::new(&source);
+        scanner.line = self.next_line + 1;
+        self.next_line += source.matches('\n').count() + 2;
+        let tokens = scanner.scan_tokens()?;
+        let mut stmts = Parser::new(tokens)
+            .max_errors(self.max_errors)
+            .docs(std::mem::take(&mut scanner.docs))
+            .parse()?;
+        self.loading.push(path.clone());
+        let mut aliases: HashMap<String, String> = HashMap::new();
+        for stmt in stmts.iter() {
+            if let Stmt::Import(file) = stmt {
+                let id = join(&path, &file.lexeme);
+                let alias = alias(&id);
+                if aliases.insert(alias.clone(), id.clone()).is_some() {
+                    return Err(error(&messages::DUPLICATE_IMPORT, &[&alias], file.span));
+                }
+                if let Some(i) = self.loading.iter().position(|x| *x == id) {
+                    let mut cycle = self.loading[i..].to_vec();
+                    cycle.push(id);
+                    return Err(error(
+                        &messages::IMPORT_CYCLE,
+                        &[&cycle.join(" -> ")],
+                        file.span,
+                    ));
+                }
+                if self.exports.contains_key(&id) {
+                    continue;
+                }
+                let source = match self.loader.load(&id) {
+                    Ok(t) => t,
+                    Err(s) => return Err(error(&messages::LOAD_FAILED, &[&s], file.span)),
+                };
+                self.module(id.clone(), source, Some(prefix_of(&id)))?;
+            }
+        }
+        self.loading.pop();
+        let globals = top_level(&stmts);
+        let mut resolver = Resolver {
+            prefix,
+            globals: &globals,
+            aliases,
+            used: HashMap::new(),
+            natives: HashMap::new(),
+            exports: &self.exports,
+            locals: Vec::new(),
+            declared: HashSet::new(),
+            error: None,
+        };
+        resolver.stmts(&mut stmts);
+        if let Some(err) = resolver.error {
+            return Err(vec![err]);
+        }
+        let declared = resolver.declared;
+        self.exports.insert(path.clone(), globals);
+        stmts.retain(|stmt| !matches!(stmt, Stmt::Import(_) | Stmt::Use { .. }));
+        let immutable = scanner.directives.iter().any(|d| d == "immutable");
+        self.modules.push(Module {
+            read_only: read_only(&stmts, immutable),
+            declared,
+            stmts,
+            directives: scanner.directives,
+        });
+        Ok(())
+    }
+}
+
+// renames the top level names of a module to module::name, and the names taken from other modules to theirs
+struct Resolver<'a> {
+    prefix: Option<String>,
+    globals: &'a HashSet<String>,
+    aliases: HashMap<String, String>, // utils -> utils.kl
+    used: HashMap<String, String>,    // names brought in with use
+    natives: HashMap<String, String>, // math -> math, sin -> math::sin from use std::...
+    exports: &'a HashMap<String, HashSet<String>>,
+    locals: Vec<HashSet<String>>, // the scopes inside the top level
+    declared: HashSet<String>,
+    error: Option<Diagnostic>,
+}
+
+impl<'a> Resolver<'a> {
+    fn stmts(&mut self, stmts: &mut [Stmt]) {
+        for stmt in stmts {
+            self.stmt(stmt);
+        }
+    }
+    fn stmt(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::Print(Value::String { printables, .. }, _) => {
+                for i in printables {
+                    self.expr(i);
+                }
+            }
+            Stmt::Print(..) => (),
+            Stmt::Block(stmts, _) => {
+                self.locals.push(HashSet::new());
+                self.stmts(stmts);
+                self.locals.pop();
+            }
+            Stmt::Expression(expr) => self.expr(expr),
+            Stmt::If {
+                condition,
+                block,
+                elseblock,
+                ..
+            } => {
+                self.expr(condition);
+                self.stmt(block);
+                if let Some(elseblock) = elseblock {
+                    self.stmt(elseblock);
+                }
+            }
+            Stmt::Var { pattern, value, .. } => {
+                if let Some(value) = value {
+                    self.expr(value);
+                }
+                self.pattern(pattern);
+            }
+            Stmt::Const { name, value, .. } => {
+                self.expr(value);
+                self.declare(name);
+            }
+            Stmt::While {
+                condition, block, ..
+            } => {
+                self.expr(condition);
+                self.stmt(block);
+            }
+            Stmt::For {
+                pattern,
+                iterable,
+                block,
+                ..
+            } => {
+                self.expr(iterable);
+                self.locals.push(HashSet::new());
+                self.pattern(pattern);
+                self.stmt(block);
+                self.locals.pop();
+            }
+            Stmt::Fn {
+                name, params, body, ..
+            } => {
+                self.declare(name);
+                self.locals.push(HashSet::new());
+                for i in params {
+                    if let Some(default) = &mut i.default {
+                        self.expr(default);
+                    }
+                    self.declare(&mut i.name);
+                }
+                self.stmt(body);
+                self.locals.pop();
+            }
+            Stmt::Try { block, name, catch } => {
+                self.stmt(block);
+                self.locals.push(HashSet::new());
+                self.declare(name);
+                self.stmt(catch);
+                self.locals.pop();
+            }
+            Stmt::Throw(expr, _) | Stmt::Yield(expr, _) => self.expr(expr),
+            Stmt::Return(expr, _) => {
+                if let Some(expr) = expr {
+                    self.expr(expr);
+                }
+            }
+            Stmt::Import(path) => {
+                if !self.locals.is_empty() {
+                    self.error(&messages::NESTED_IMPORT, &[], path.span);
+                }
+            }
+            Stmt::Use { module, names } => {
+                if !self.locals.is_empty() {
+                    self.error(&messages::NESTED_USE, &[], module.span);
+                }
+                if let Some(path) = module.lexeme.strip_prefix("std") {
+                    for name in names.iter() {
+                        let path = format!("{path}::{}", name.lexeme);
+                        let path = path.trim_start_matches("::").to_string();
+                        self.natives.insert(name.lexeme.clone(), path);
+                    }
+                    return;
+                }
+                for name in names.iter() {
+                    let path = format!("{}::{}", module.lexeme, name.lexeme);
+                    if let Some(x) = self.qualified(&path, name.span) {
+                        self.used.insert(name.lexeme.clone(), x);
+                    }
+                }
+            }
+        }
+    }
+    fn pattern(&mut self, pattern: &mut Pattern) {
+        match pattern {
+            Pattern::Identifier { name, .. } => self.declare(name),
+            Pattern::Vec { elements, rest, .. } => {
+                for i in elements {
+                    self.pattern(i);
+                }
+                if let Some(rest) = rest {
+                    self.declare(rest);
+                }
+            }
+        }
+    }
+    fn expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Assign { name, value } => {
+                self.expr(value);
+                self.resolve(name);
+                //assigning a name no one declared declares it where the assignment runs
+                self.declared.insert(name.lexeme.clone());
+            }
+            Expr::Binary { left, right, .. } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::Call {
+                callee,
+                arguments,
+                named,
+                native,
+                ..
+            } => {
+                if !*native {
+                    self.native(callee, native);
+                }
+                if !*native {
+                    self.expr(callee);
+                }
+                for i in arguments {
+                    self.expr(i);
+                }
+                for (_, i) in named {
+                    self.expr(i);
+                }
+            }
+            Expr::Grouping(expr, _) => self.expr(expr),
+            Expr::Literal(Value::String { printables, .. }, _) => {
+                for i in printables {
+                    self.expr(i);
+                }
+            }
+            Expr::Literal(..) => (),
+            Expr::Unary { expression, .. } => self.expr(expression),
+            Expr::Variable(name) => self.resolve(name),
+            Expr::Range { min, max, step, .. } => {
+                self.expr(min);
+                self.expr(max);
+                if let Some(step) = step {
+                    self.expr(step);
+                }
+            }
+            Expr::Vec(vec, _) => {
+                for i in vec {
+                    self.expr(i);
+                }
+            }
+            Expr::Wrap { value, .. } => self.expr(value),
+            Expr::Unwrap(expr, _) => self.expr(expr),
+        }
+    }
+    fn native(&mut self, callee: &mut Expr, native: &mut bool) {
+        //turns calls like math::sin(x) into std::math::sin(x) after use std::math;
+        let name = match callee {
+            Expr::Variable(name) => name,
+            _ => return,
+        };
+        if self.locals.iter().any(|scope| scope.contains(&name.lexeme))
+            || self.globals.contains(&name.lexeme)
+        {
+            return;
+        }
+        let (first, rest) = match name.lexeme.split_once("::") {
+            Some((first, rest)) => (first, format!("::{rest}")),
+            None => (name.lexeme.as_str(), String::new()),
+        };
+        if self.aliases.contains_key(first) || (rest.is_empty() && self.used.contains_key(first)) {
+            return;
+        }
+        if let Some(path) = self.natives.get(first) {
+            name.lexeme = format!("{path}{rest}");
+            *native = true;
+        }
+    }
+    fn declare(&mut self, name: &mut Token) {
+        match self.locals.last_mut() {
+            Some(scope) => {
+                scope.insert(name.lexeme.clone());
+            }
+            None => {
+                if let Some(prefix) = &self.prefix {
+                    name.lexeme = format!("{prefix}::{}", name.lexeme);
+                }
+            }
+        }
+        self.declared.insert(name.lexeme.clone());
+    }
+    fn resolve(&mut self, name: &mut Token) {
+        if self.locals.iter().any(|scope| scope.contains(&name.lexeme)) {
+            return;
+        }
+        if name.lexeme.contains("::") {
+            if let Some(x) = self.qualified(&name.lexeme, name.span) {
+                name.lexeme = x;
+            }
+        } else if self.globals.contains(&name.lexeme) {
+            if let Some(prefix) = &self.prefix {
+                name.lexeme = format!("{prefix}::{}", name.lexeme);
+            }
+        } else if let Some(x) = self.used.get(&name.lexeme) {
+            name.lexeme = x.clone();
+        }
+    }
+    fn qualified(&mut self, path: &str, span: Span) -> Option<String> {
+        //utils::f to the name f got inside utils.kl
+        let (module, item) = path.split_once("::").unwrap();
+        let id = match self.aliases.get(module) {
+            Some(x) => x.clone(),
+            None => {
+                self.error(&messages::NOT_IMPORTED, &[module], span);
+                return None;
+            }
+        };
+        if !self.exports[&id].contains(item) {
+            self.error(&messages::NO_SUCH_ITEM, &[module, item], span);
+            return None;
+        }
+        Some(format!("{}::{item}", prefix_of(&id)))
+    }
+    fn error(&mut self, message: &Message, args: &[&str], span: Span) {
+        if self.error.is_none() {
+            self.error = Some(KlangError::at(
+                KlangError::CompileError,
+                message,
+                args,
+                span,
+            ));
+        }
+    }
+}
+
+fn top_level(stmts: &[Stmt]) -> HashSet<String> {
+    //the names declared at the top level of a file
+    let mut names: HashSet<String> = HashSet::new();
+    for stmt in stmts {
+        match stmt {
+            Stmt::Var { pattern, .. } => pattern_names(pattern, &mut names),
+            Stmt::Const { name, .. } | Stmt::Fn { name, .. } => {
+                names.insert(name.lexeme.clone());
+            }
+            _ => (),
+        }
+    }
+    names
+}
+
+fn read_only(stmts: &[Stmt], immutable: bool) -> Vec<ReadOnly> {
+    //the names use brought in were renamed to module::name, so these are the only names to guard
+    let mut names: Vec<ReadOnly> = Vec::new();
+    for stmt in stmts {
+        match stmt {
+            Stmt::Const { name, .. } => names.push(ReadOnly {
+                name: name.lexeme.clone(),
+                constant: true,
+                span: name.span,
+            }),
+            Stmt::Var { pattern, .. } if immutable => immutable_names(pattern, &mut names),
+            _ => (),
+        }
+    }
+    names
+}
+
+fn immutable_names(pattern: &Pattern, names: &mut Vec<ReadOnly>) {
+    let (name, mutable) = match pattern {
+        Pattern::Identifier { name, mutable, .. } => (name, *mutable),
+        Pattern::Vec { elements, rest, .. } => {
+            for i in elements {
+                immutable_names(i, names);
+            }
+            match rest {
+                Some(rest) => (rest, false),
+                None => return,
+            }
+        }
+    };
+    if !mutable {
+        names.push(ReadOnly {
+            name: name.lexeme.clone(),
+            constant: false,
+            span: name.span,
+        });
+    }
+}
+
+fn pattern_names(pattern: &Pattern, names: &mut HashSet<String>) {
+    match pattern {
+        Pattern::Identifier { name, .. } => {
+            names.insert(name.lexeme.clone());
+        }
+        Pattern::Vec { elements, rest, .. } => {
+            for i in elements {
+                pattern_names(i, names);
+            }
+            if let Some(rest) = rest {
+                names.insert(rest.lexeme.clone());
+            }
+        }
+    }
+}
+
+fn error(message: &Message, args: &[&str], span: Span) -> Vec<Diagnostic> {
+    vec![KlangError::at(
+        KlangError::CompileError,
+        message,
+        args,
+        span,
+    )]
+}
+
+fn join(importer: &str, path: &str) -> String {
+    //the path of an import, relative to the file that imports it
+    match importer.rsplit_once('/') {
+        Some((dir, _)) if !path.starts_with('/') => normalize(&format!("{dir}/{path}")),
+        _ => normalize(path),
+    }
+}
+
+fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => (),
+            ".." if !parts.is_empty() && parts[parts.len() - 1] != ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+fn prefix_of(id: &str) -> String {
+    //utils.kl -> utils, lib/math.kl -> lib/math
+    id.strip_suffix(".kl").unwrap_or(id).to_string()
+}
+
+fn alias(id: &str) -> String {
+    //the name the module is used with, lib/math.kl -> math
+    let prefix = prefix_of(id);
+    match prefix.rsplit_once('/') {
+        Some((_, name)) => name.to_string(),
+        None => prefix,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiling::checker;
+
+    // links the files of a playground post, main first and the others after // klang:file lines
+    fn link_source(source: &str) -> Result<Vec<Module>, (Vec<Diagnostic>, SourceMap)> {
+        link(MemoryLoader::MAIN, &MemoryLoader::split(source), 20).map(|(modules, _)| modules)
+    }
+
+    fn link_error(source: &str) -> Diagnostic {
+        let (mut errors, sources) = match link_source(source) {
+            Ok(_) => panic!("{source} should not link"),
+            Err(x) => x,
+        };
+        assert_eq!(errors.len(), 1);
+        sources.annotate(errors.remove(0))
+    }
+
+    // the names the statements of a module declare and call, as they are once linked
+    fn names(module: &Module) -> Vec<String> {
+        module
+            .stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Fn { name, .. } | Stmt::Const { name, .. } => Some(name.lexeme.clone()),
+                Stmt::Expression(Expr::Call { callee, .. }) => match &**callee {
+                    Expr::Variable(name) => Some(name.lexeme.clone()),
+                    _ => None,
+                },
+                Stmt::Expression(Expr::Assign { name, .. }) => Some(name.lexeme.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    const UTILS: &str = "import \"utils.kl\";
+use utils::{double};
+double(1);
+utils::half(2);
+// klang:file utils.kl
+fn double(n) { return n * 2; }
+fn half(n) { return n / 2; }
+";
+
+    #[test]
+    fn import_and_use_resolve_to_the_module() {
+        let modules = link_source(UTILS).unwrap();
+        assert_eq!(modules.len(), 2);
+        assert_eq!(names(&modules[0]), vec!["utils::double", "utils::half"]);
+        assert_eq!(names(&modules[1]), vec!["utils::double", "utils::half"]);
+    }
+
+    #[test]
+    fn import_cycles_are_errors() {
+        let source = "import \"a.kl\";
+// klang:file a.kl
+import \"lib/b.kl\";
+// klang:file lib/b.kl
+import \"../a.kl\";
+";
+        let error = link_error(source);
+        assert_eq!(error.code, Some(messages::IMPORT_CYCLE.code));
+        assert_eq!(error.message, "import cycle: a.kl -> lib/b.kl -> a.kl");
+        assert_eq!(error.file.as_deref(), Some("lib/b.kl"));
+        assert_eq!(error.span.line, 1);
+    }
+
+    #[test]
+    fn errors_in_imported_files_tell_their_file() {
+        let source = "import \"utils.kl\";
+// klang:file utils.kl
+fn f() {}
+let x = ;
+";
+        let error = link_error(source);
+        assert_eq!(error.file.as_deref(), Some("utils.kl"));
+        assert_eq!(error.span.line, 2);
+    }
+
+    #[test]
+    fn unknown_modules_and_items_are_errors() {
+        let error = link_error("utils::f();");
+        assert_eq!(error.code, Some(messages::NOT_IMPORTED.code));
+        assert_eq!(error.file, None);
+        let source = "import \"utils.kl\";\nuse utils::{g};\n// klang:file utils.kl\nfn f() {}\n";
+        let error = link_error(source);
+        assert_eq!(error.code, Some(messages::NO_SUCH_ITEM.code));
+        assert_eq!(error.span.line, 2);
+    }
+
+    #[test]
+    fn constants_cant_be_assigned_from_other_modules() {
+        let source = "import \"utils.kl\";
+use utils::{X};
+X = 5;
+utils::X = 6;
+utils::Y = 7;
+utils::Z = 8;
+// klang:file utils.kl
+// klang:immutable
+const X = 1;
+let Y = 2;
+let mut Z = 3;
+";
+        let modules = link_source(source).unwrap();
+        let (utils, main) = (&modules[0], &modules[1]);
+        assert_eq!(
+            names(main),
+            vec!["utils::X", "utils::X", "utils::Y", "utils::Z"]
+        );
+        let errors = checker::check(
+            &main.stmts,
+            &main.directives,
+            &utils.read_only,
+            &main.declared,
+        )
+        .unwrap_err();
+        let codes: Vec<Option<u16>> = errors.iter().map(|e| e.code).collect();
+        assert_eq!(
+            codes,
+            vec![
+                Some(messages::ASSIGN_TO_CONSTANT.code),
+                Some(messages::ASSIGN_TO_CONSTANT.code),
+                Some(messages::ASSIGN_TO_IMMUTABLE.code),
+            ]
+        );
+        let declared: Vec<usize> = errors.iter().map(|e| e.labels[0].span.line).collect();
+        let x = utils.read_only[0].span.line;
+        assert_eq!(declared, vec![x, x, x + 1]);
+        assert!(checker::check(&utils.stmts, &utils.directives, &[], &utils.declared).is_ok());
+    }
+}
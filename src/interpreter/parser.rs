@@ -1,110 +1,399 @@
 #![allow(unused)]
 
 use super::expr::Expr;
-use super::stmt::Stmt;
-use crate::error::KlangError;
+use super::stmt::{Param, Pattern, Stmt};
+use crate::error::{Diagnostic, KlangError, Span};
+use crate::messages::{self, Message};
 use crate::scanner::Scanner;
-use crate::scanner::{Token, TokenType, Value};
+use crate::scanner::{Token, TokenType, Type, Value};
+use std::collections::HashMap;
+
+// how many parse errors are collected before the parser gives up on the file
+pub const MAX_ERRORS: usize = 20;
 
 pub struct Parser {
     pub tokens: Vec<Token>,
+    docs: HashMap<usize, String>, // doc comments, by the byte the token after them starts at
     current: usize,
+    errors: Vec<Diagnostic>,
+    max_errors: usize,
 }
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Parser {
-        Parser { tokens, current: 0 }
+        //comments only come from a scanner with trivia on, the grammar has no place for them
+        let tokens = tokens
+            .into_iter()
+            .filter(|t| t.tt != TokenType::Comment)
+            .collect();
+        Parser {
+            tokens,
+            docs: HashMap::new(),
+            current: 0,
+            errors: Vec::new(),
+            max_errors: MAX_ERRORS,
+        }
+    }
+    pub fn max_errors(mut self, max_errors: usize) -> Parser {
+        self.max_errors = max_errors.max(1);
+        self
+    }
+    pub fn docs(mut self, docs: HashMap<usize, String>) -> Parser {
+        self.docs = docs;
+        self
+    }
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<Diagnostic>> {
+        let (statements, errors) = self.parse_partial();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(statements)
     }
-    pub fn parse(&mut self) -> Result<Vec<Stmt>, String> {
+    fn parse_partial(&mut self) -> (Vec<Stmt>, Vec<Diagnostic>) {
+        //every statement that parsed, and the errors of the ones that did not
         let mut statements: Vec<Stmt> = Vec::new();
         while !self.is_at_end() {
-            statements.push(match self.declaration() {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            });
+            let from = self.current;
+            match self.declaration() {
+                Ok(t) => statements.push(t),
+                Err(s) => self.recover(s, from),
+            }
         }
-        Ok(statements)
+        (statements, std::mem::take(&mut self.errors))
     }
-    fn declaration(&mut self) -> Result<Stmt, String> {
+    fn recover(&mut self, error: Diagnostic, from: usize) {
+        //panic mode: keep the error and skip to where the next statement probably starts
+        if self.errors.len() >= self.max_errors {
+            return;
+        }
+        self.errors.push(error);
+        if self.errors.len() == self.max_errors {
+            let note = format!(
+                "stopped after {} errors, there may be more",
+                self.max_errors
+            );
+            let last = self.errors.pop().unwrap().note(&note);
+            self.errors.push(last);
+            self.current = self.tokens.len() - 1;
+            return;
+        }
+        if self.current == from {
+            self.advance();
+        }
+        //a { the broken statement opened is skipped with everything up to its }, or that } is left stray
+        let mut open =
+            usize::from(self.previous().tt == TokenType::LeftBrace && self.current > from);
+        while !self.is_at_end() {
+            if open > 0 {
+                match self.advance().tt {
+                    TokenType::LeftBrace => open += 1,
+                    TokenType::RightBrace if open == 1 => return,
+                    TokenType::RightBrace => open -= 1,
+                    _ => {}
+                }
+                continue;
+            }
+            if self.previous().tt == TokenType::Semicolon {
+                return;
+            }
+            match self.peek().tt {
+                TokenType::LeftBrace => open = 1,
+                TokenType::RightBrace
+                | TokenType::Let
+                | TokenType::Const
+                | TokenType::Fn
+                | TokenType::If
+                | TokenType::While
+                | TokenType::For
+                | TokenType::Print
+                | TokenType::Return
+                | TokenType::Yield
+                | TokenType::Try
+                | TokenType::Throw
+                | TokenType::Import
+                | TokenType::Use => return,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+    fn declaration(&mut self) -> Result<Stmt, Diagnostic> {
+        //a doc comment belongs to the let, const or fn right after it, any other one is just a comment
+        //they are found by where the token starts, the tokens of strings are spliced in and move the rest
+        let doc = match self.peek().tt {
+            TokenType::Let | TokenType::Const | TokenType::Fn => {
+                self.docs.remove(&self.peek().span.start)
+            }
+            _ => None,
+        };
         if self.match_tokens(&[TokenType::Let]) {
-            self.var_decl()
+            self.var_decl(doc)
+        } else if self.match_tokens(&[TokenType::Const]) {
+            self.const_decl(doc)
         } else if self.match_tokens(&[TokenType::Fn]) {
-            self.fn_decl()
+            self.fn_decl(doc)
+        } else if self.match_tokens(&[TokenType::Import]) {
+            self.import_decl()
+        } else if self.match_tokens(&[TokenType::Use]) {
+            self.use_decl()
         } else {
             self.statement()
         }
     }
 
-    fn fn_decl(&mut self) -> Result<Stmt, String> {
+    fn import_decl(&mut self) -> Result<Stmt, Diagnostic> {
+        let path = self.consume(
+            TokenType::String,
+            &messages::EXPECTED,
+            &["a string", "with the path of the file to import"],
+        )?;
+        self.consume(TokenType::Semicolon, &messages::MISSING_SEMICOLON, &[])?;
+        Ok(Stmt::Import(path))
+    }
+
+    fn use_decl(&mut self) -> Result<Stmt, Diagnostic> {
+        //use utils::{f, g}; use utils::f; use std::math; use std::math::{sin, cos};
+        let mut path: Vec<Token> = Vec::new();
+        if self.match_tokens(&[TokenType::NativeCall]) {
+            let mut std = self.previous();
+            std.lexeme = "std".to_string();
+            path.push(std);
+        } else {
+            path.push(self.consume(
+                TokenType::Identifier,
+                &messages::EXPECTED_NAME,
+                &["a module"],
+            )?);
+            self.consume(
+                TokenType::ColonColon,
+                &messages::EXPECTED,
+                &["::", "after the module name"],
+            )?;
+        }
+        let mut names: Vec<Token> = Vec::new();
+        loop {
+            if self.match_tokens(&[TokenType::LeftBrace]) {
+                loop {
+                    names.push(self.consume(
+                        TokenType::Identifier,
+                        &messages::EXPECTED_NAME,
+                        &["the item to use"],
+                    )?);
+                    if !self.match_tokens(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+                self.consume(
+                    TokenType::RightBrace,
+                    &messages::EXPECTED,
+                    &["}", "to close the items to use"],
+                )?;
+                break;
+            }
+            let name = self.consume(
+                TokenType::Identifier,
+                &messages::EXPECTED_NAME,
+                &["the item to use"],
+            )?;
+            if !self.match_tokens(&[TokenType::ColonColon]) {
+                names.push(name);
+                break;
+            }
+            path.push(name);
+        }
+        self.consume(TokenType::Semicolon, &messages::MISSING_SEMICOLON, &[])?;
+        let mut module = path.remove(0);
+        for i in path {
+            module.lexeme = format!("{}::{}", module.lexeme, i.lexeme);
+        }
+        Ok(Stmt::Use { module, names })
+    }
+
+    fn fn_decl(&mut self, doc: Option<String>) -> Result<Stmt, Diagnostic> {
         let return_t = self.previous();
-        let name = match self.consume(TokenType::Identifier, "must have a function name") {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
+        let generator = self.match_tokens(&[TokenType::Star]);
+        let name = self.consume(
+            TokenType::Identifier,
+            &messages::EXPECTED_NAME,
+            &["the function"],
+        )?;
         if self.match_tokens(&[TokenType::LeftParen]) {
-            if self.match_tokens(&[TokenType::RightParen]) {
-                return Ok(Stmt::Fn {
-                    name,
-                    params: Vec::new(),
-                    body: Box::new(match self.block() {
-                        Ok(t) => t,
-                        Err(s) => return Err(s),
-                    }),
-                });
-            }
-            let mut vec: Vec<Token> = Vec::new();
-            let mut iden =
-                match self.consume(TokenType::Identifier, "argument must be an identifier") {
-                    Ok(t) => t,
-                    Err(s) => return Err(s),
-                };
-            vec.push(iden);
-            while self.match_tokens(&[TokenType::Comma]) {
-                iden = match self.consume(TokenType::Identifier, "parameter must be an identifier")
-                {
-                    Ok(t) => t,
-                    Err(s) => return Err(s),
-                };
-                vec.push(iden);
+            let mut vec: Vec<Param> = Vec::new();
+            if !self.check(TokenType::RightParen) {
+                vec.push(self.param(&vec)?);
+                while self.match_tokens(&[TokenType::Comma]) {
+                    vec.push(self.param(&vec)?);
+                }
             }
-            match self.consume(TokenType::RightParen, "gotta close the call dude") {
-                Ok(t) => t,
-                Err(s) => return Err(s),
+            self.consume(
+                TokenType::RightParen,
+                &messages::EXPECTED,
+                &[")", "to close the call"],
+            )?;
+            let ret = if self.match_tokens(&[TokenType::Arrow]) {
+                Some(self.type_annotation()?)
+            } else {
+                None
             };
             return Ok(Stmt::Fn {
                 name,
                 params: vec,
-                body: Box::new(match self.block() {
-                    Ok(t) => t,
-                    Err(s) => return Err(s),
-                }),
+                ret,
+                body: Box::new(self.block()?),
+                generator,
+                doc,
             });
         }
-        Err(self.error("not possible!"))
+        Err(self.error(&messages::INTERNAL, &["a declaration that is not one"]))
     }
-    fn var_decl(&mut self) -> Result<Stmt, String> {
-        let name = match self.consume(TokenType::Identifier, "must define a variable name") {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
+    fn param(&mut self, previous: &[Param]) -> Result<Param, Diagnostic> {
+        if previous.last().is_some_and(|p| p.rest) {
+            return Err(self.error(&messages::REST_NOT_LAST, &[]));
+        }
+        let rest = self.match_tokens(&[TokenType::Ellipsis]);
+        let mutable = self.match_tokens(&[TokenType::Mut]);
+        let name = self.consume(
+            TokenType::Identifier,
+            &messages::EXPECTED_NAME,
+            &["the parameter"],
+        )?;
+        if previous.iter().any(|p| p.name.lexeme == name.lexeme) {
+            return Err(self.error(&messages::DUPLICATE_PARAMETER, &[&name.lexeme]));
+        }
+        let ty = self.optional_type(!rest)?;
+        if !rest && self.match_tokens(&[TokenType::Equal]) {
+            let default = self.logical()?;
+            return Ok(Param {
+                name,
+                mutable,
+                ty,
+                default: Some(default),
+                rest,
+            });
+        }
+        if !rest && previous.iter().any(|p| p.default.is_some()) {
+            return Err(self.error(&messages::MISSING_DEFAULT, &[&name.lexeme]));
+        }
+        Ok(Param {
+            name,
+            mutable,
+            ty,
+            default: None,
+            rest,
+        })
+    }
+    fn var_decl(&mut self, doc: Option<String>) -> Result<Stmt, Diagnostic> {
+        let pattern = self.pattern()?;
         if self.match_tokens(&[TokenType::Equal]) {
-            let value = self.logical();
-            self.consume(TokenType::Semicolon, "missing ; at the end of the line");
+            let value = self.logical()?;
+            self.consume(TokenType::Semicolon, &messages::MISSING_SEMICOLON, &[])?;
             return Ok(Stmt::Var {
-                name,
-                value: Some(match value {
-                    Ok(t) => t,
-                    Err(s) => return Err(s),
-                }),
+                pattern,
+                value: Some(value),
+                doc,
             });
         }
-        match self.consume(TokenType::Semicolon, "missing ; at the end of the line") {
-            Ok(t) => t,
-            Err(s) => return Err(s),
+        if matches!(pattern, Pattern::Vec { .. }) {
+            return Err(self.error(&messages::DESTRUCTURE_WITHOUT_VALUE, &[]));
+        }
+        self.consume(TokenType::Semicolon, &messages::MISSING_SEMICOLON, &[])?;
+        Ok(Stmt::Var {
+            pattern,
+            value: None,
+            doc,
+        })
+    }
+    fn const_decl(&mut self, doc: Option<String>) -> Result<Stmt, Diagnostic> {
+        let name = self.consume(
+            TokenType::Identifier,
+            &messages::EXPECTED_NAME,
+            &["the constant"],
+        )?;
+        let ty = self.optional_type(true)?;
+        self.consume(
+            TokenType::Equal,
+            &messages::EXPECTED,
+            &["=", "and a value for the constant"],
+        )?;
+        let value = self.logical()?;
+        self.consume(TokenType::Semicolon, &messages::MISSING_SEMICOLON, &[])?;
+        Ok(Stmt::Const {
+            name,
+            ty,
+            value,
+            doc,
+        })
+    }
+    fn pattern(&mut self) -> Result<Pattern, Diagnostic> {
+        if !self.match_tokens(&[TokenType::LeftSquare]) {
+            let mutable = self.match_tokens(&[TokenType::Mut]);
+            let name = self.consume(
+                TokenType::Identifier,
+                &messages::EXPECTED_NAME,
+                &["the variable"],
+            )?;
+            return match self.optional_type(true) {
+                Ok(ty) => Ok(Pattern::Identifier { name, mutable, ty }),
+                Err(s) => Err(s),
+            };
+        }
+        let start = self.previous().span;
+        let mut elements: Vec<Pattern> = Vec::new();
+        let mut rest: Option<Token> = None;
+        while !self.check(TokenType::RightSquare) {
+            if self.match_tokens(&[TokenType::Ellipsis]) {
+                rest = Some(self.consume(
+                    TokenType::Identifier,
+                    &messages::EXPECTED_NAME,
+                    &["the rest of the vector"],
+                )?);
+                break;
+            }
+            elements.push(self.pattern()?);
+            if !self.match_tokens(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(
+            TokenType::RightSquare,
+            &messages::EXPECTED,
+            &["]", "to close the pattern, the rest must come last"],
+        )?;
+        Ok(Pattern::Vec {
+            elements,
+            rest,
+            span: start.to(self.previous().span),
+        })
+    }
+
+    fn optional_type(&mut self, allowed: bool) -> Result<Option<Type>, Diagnostic> {
+        if !self.match_tokens(&[TokenType::Colon]) {
+            return Ok(None);
+        }
+        if !allowed {
+            return Err(self.error(&messages::REST_WITH_TYPE, &[]));
+        }
+        match self.type_annotation() {
+            Ok(t) => Ok(Some(t)),
+            Err(s) => Err(s),
+        }
+    }
+    fn type_annotation(&mut self) -> Result<Type, Diagnostic> {
+        let token = self.peek();
+        if token.literal.is_some() {
+            return Err(self.error(&messages::EXPECTED_TYPE, &[]));
+        }
+        let ty = match token.tt {
+            TokenType::Int => Type::Int,
+            TokenType::Float => Type::Float,
+            TokenType::String => Type::String,
+            TokenType::Bool => Type::Bool,
+            _ => return Err(self.error(&messages::EXPECTED_TYPE, &[])),
         };
-        Ok(Stmt::Var { name, value: None })
+        self.advance();
+        Ok(ty)
     }
 
-    fn statement(&mut self) -> Result<Stmt, String> {
+    fn statement(&mut self) -> Result<Stmt, Diagnostic> {
         if self.match_tokens(&[TokenType::Print]) {
             self.print_stmt()
         } else if self.check(TokenType::LeftBrace) {
@@ -117,132 +406,145 @@ impl Parser {
             self.for_stmt()
         } else if self.match_tokens(&[TokenType::Return]) {
             self.return_stmt()
+        } else if self.match_tokens(&[TokenType::Yield]) {
+            self.yield_stmt()
+        } else if self.match_tokens(&[TokenType::Try]) {
+            self.try_stmt()
+        } else if self.match_tokens(&[TokenType::Throw]) {
+            self.throw_stmt()
         } else {
             self.expr_stmt()
         }
     }
 
-    fn return_stmt(&mut self) -> Result<Stmt, String> {
+    fn yield_stmt(&mut self) -> Result<Stmt, Diagnostic> {
+        let keyword = self.previous().span;
+        let value = self.logical()?;
+        self.consume(TokenType::Semicolon, &messages::MISSING_SEMICOLON, &[])?;
+        let span = keyword.to(value.span());
+        Ok(Stmt::Yield(value, span))
+    }
+
+    fn return_stmt(&mut self) -> Result<Stmt, Diagnostic> {
+        let keyword = self.previous().span;
         if self.match_tokens(&[TokenType::Semicolon]) {
-            return Ok(Stmt::Return(None, self.previous().line));
+            return Ok(Stmt::Return(None, keyword.to(self.previous().span)));
         }
         let value = self.logical();
-        match self.consume(TokenType::Semicolon, "missing ; at the end of lien") {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
-        Ok(Stmt::Return(
-            Some(match value {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            }),
-            self.previous().line,
-        ))
-    }
-
-    fn for_stmt(&mut self) -> Result<Stmt, String> {
-        let identifier = match self.consume(TokenType::Identifier, "missing identifier 8=D") {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
-        let line = self.previous().line;
-        match self.consume(TokenType::In, "missing in") {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
-        let iterable = match self.range() {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
-        let block = Box::new(match self.block() {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        });
+        self.consume(TokenType::Semicolon, &messages::MISSING_SEMICOLON, &[])?;
+        Ok(Stmt::Return(Some(value?), keyword.to(self.previous().span)))
+    }
+
+    fn try_stmt(&mut self) -> Result<Stmt, Diagnostic> {
+        let block = self.block()?;
+        self.consume(
+            TokenType::Catch,
+            &messages::EXPECTED,
+            &["catch", "after the try block"],
+        )?;
+        let name = self.consume(
+            TokenType::Identifier,
+            &messages::EXPECTED_NAME,
+            &["the caught error"],
+        )?;
+        let catch = self.block()?;
+        Ok(Stmt::Try {
+            block: Box::new(block),
+            name,
+            catch: Box::new(catch),
+        })
+    }
+
+    fn throw_stmt(&mut self) -> Result<Stmt, Diagnostic> {
+        let keyword = self.previous().span;
+        let value = self.logical()?;
+        self.consume(TokenType::Semicolon, &messages::MISSING_SEMICOLON, &[])?;
+        let span = keyword.to(value.span());
+        Ok(Stmt::Throw(value, span))
+    }
+
+    fn for_stmt(&mut self) -> Result<Stmt, Diagnostic> {
+        let pattern = self.pattern()?;
+        let span = pattern.span();
+        self.consume(
+            TokenType::In,
+            &messages::EXPECTED,
+            &["in", "after the loop variables"],
+        )?;
+        let iterable = self.range()?;
+        let block = Box::new(self.block()?);
         Ok(Stmt::For {
-            identifier,
+            pattern,
             iterable,
             block,
-            line,
+            span,
         })
     }
 
-    fn if_stmt(&mut self) -> Result<Stmt, String> {
-        let condition = match self.logical() {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
-        let start = self.previous().line;
-        let block = Box::new(match self.block() {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        });
+    fn if_stmt(&mut self) -> Result<Stmt, Diagnostic> {
+        let condition = self.logical()?;
+        let start = condition.span();
+        let block = Box::new(self.block()?);
         if self.match_tokens(&[TokenType::Else]) {
-            let end = self.previous().line;
-            let elseblock = Some(Box::new(match self.block() {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            }));
+            let end = self.previous().span;
+            let elseblock = Some(Box::new(self.block()?));
             return Ok(Stmt::If {
                 condition,
                 block,
                 elseblock,
-                lines: (start, Some(end)),
+                spans: (start, Some(end)),
             });
         }
         Ok(Stmt::If {
             condition,
             block,
             elseblock: None,
-            lines: (start, None),
+            spans: (start, None),
         })
     }
 
-    fn while_stmt(&mut self) -> Result<Stmt, String> {
-        let condition = match self.logical() {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
-        let line = self.previous().line;
-        let block = match self.block() {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
+    fn while_stmt(&mut self) -> Result<Stmt, Diagnostic> {
+        let condition = self.logical()?;
+        let span = condition.span();
+        let block = self.block()?;
 
         Ok(Stmt::While {
             condition,
             block: Box::new(block),
-            line,
+            span,
         })
     }
 
-    fn block(&mut self) -> Result<Stmt, String> {
-        match self.consume(TokenType::LeftBrace, "must start block with a {") {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
-        let start = self.previous().line;
+    fn block(&mut self) -> Result<Stmt, Diagnostic> {
+        self.consume(
+            TokenType::LeftBrace,
+            &messages::EXPECTED,
+            &["{", "to start a block"],
+        )?;
+        let start = self.previous().span;
         let mut statements: Vec<Stmt> = Vec::new();
         while !self.is_at_end() && !self.check(TokenType::RightBrace) {
-            statements.push(match self.declaration() {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            });
+            let from = self.current;
+            match self.declaration() {
+                Ok(t) => statements.push(t),
+                Err(s) => self.recover(s, from),
+            }
         }
-        match self.consume(TokenType::RightBrace, "must end block with a }") {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
-        Ok(Stmt::Block(statements, (start, self.previous().line)))
+        self.consume(
+            TokenType::RightBrace,
+            &messages::EXPECTED,
+            &["}", "to close the block"],
+        )?;
+        Ok(Stmt::Block(statements, (start, self.previous().span)))
     }
 
-    fn print_stmt(&mut self) -> Result<Stmt, String> {
-        match self.consume(
+    fn print_stmt(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous().span;
+        self.consume(
             TokenType::LeftParen,
-            "gotta put ( after a print yk how it is..",
-        ) {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
+            &messages::EXPECTED,
+            &["(", "after print"],
+        )?;
         let stmt = Stmt::Print(
             match self.primary() {
                 Ok(Expr::Literal(Value::String { string, printables }, _)) => {
@@ -250,46 +552,29 @@ impl Parser {
                 }
                 Err(e) => return Err(e),
                 _ => {
-                    return Err(self.error("can only print strings"));
+                    return Err(self.error(&messages::PRINT_NOT_STRING, &[]));
                 }
             },
-            self.peek().line,
+            start.to(self.peek().span),
         );
-        match self.consume(
+        self.consume(
             TokenType::RightParen,
-            "gotta put ) at the end of a print yk how it is..",
-        ) {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
-        match self.consume(TokenType::Semicolon, "missing ; at the end of the line") {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
+            &messages::EXPECTED,
+            &[")", "at the end of the print"],
+        )?;
+        self.consume(TokenType::Semicolon, &messages::MISSING_SEMICOLON, &[])?;
         Ok(stmt)
     }
-    fn expr_stmt(&mut self) -> Result<Stmt, String> {
-        let stmt = Stmt::Expression(match self.assignment() {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        });
-        match self.consume(TokenType::Semicolon, "missing ; at the end of the line") {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
+    fn expr_stmt(&mut self) -> Result<Stmt, Diagnostic> {
+        let stmt = Stmt::Expression(self.assignment()?);
+        self.consume(TokenType::Semicolon, &messages::MISSING_SEMICOLON, &[])?;
         Ok(stmt)
     }
 
-    pub fn assignment(&mut self) -> Result<Expr, String> {
-        let identifier = match self.logical() {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
+    pub fn assignment(&mut self) -> Result<Expr, Diagnostic> {
+        let identifier = self.logical()?;
         if self.match_tokens(&[TokenType::Equal]) {
-            let value = match self.logical() {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            };
+            let value = self.logical()?;
             match identifier {
                 Expr::Variable(name) => {
                     return Ok(Expr::Assign {
@@ -297,23 +582,17 @@ impl Parser {
                         value: Box::new(value),
                     })
                 }
-                _ => return Err(self.error("cannot assign to a non variable")),
+                _ => return Err(self.error(&messages::INVALID_ASSIGNMENT, &[])),
             }
         }
         Ok(identifier)
     }
 
-    pub fn logical(&mut self) -> Result<Expr, String> {
-        let left: Expr = match self.equality() {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
+    pub fn logical(&mut self) -> Result<Expr, Diagnostic> {
+        let left: Expr = self.equality()?;
         if self.match_tokens(&[TokenType::And, TokenType::Or]) {
             let operator = self.previous();
-            let right: Expr = match self.logical() {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            };
+            let right: Expr = self.logical()?;
             return Ok(Expr::Binary {
                 left: Box::new(left),
                 operator,
@@ -323,17 +602,11 @@ impl Parser {
         Ok(left)
     }
 
-    fn equality(&mut self) -> Result<Expr, String> {
-        let left: Expr = match self.comparison() {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
+    fn equality(&mut self) -> Result<Expr, Diagnostic> {
+        let left: Expr = self.comparison()?;
         if self.match_tokens(&[TokenType::BangEqual, TokenType::EqualEqual]) {
             let operator = self.previous();
-            let right: Expr = match self.comparison() {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            };
+            let right: Expr = self.comparison()?;
             return Ok(Expr::Binary {
                 left: Box::new(left),
                 operator,
@@ -342,11 +615,8 @@ impl Parser {
         }
         Ok(left)
     }
-    fn comparison(&mut self) -> Result<Expr, String> {
-        let left: Expr = match self.range() {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
+    fn comparison(&mut self) -> Result<Expr, Diagnostic> {
+        let left: Expr = self.range()?;
         if self.match_tokens(&[
             TokenType::Greater,
             TokenType::GreaterEqual,
@@ -354,10 +624,7 @@ impl Parser {
             TokenType::LessEqual,
         ]) {
             let operator = self.previous();
-            let right: Expr = match self.term() {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            };
+            let right: Expr = self.term()?;
             return Ok(Expr::Binary {
                 left: Box::new(left),
                 operator,
@@ -366,48 +633,37 @@ impl Parser {
         }
         Ok(left)
     }
-    pub fn range(&mut self) -> Result<Expr, String> {
-        let start = match self.term() {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
-        if self.match_tokens(&[TokenType::Range]) {
-            let end = match self.term() {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            };
+    pub fn range(&mut self) -> Result<Expr, Diagnostic> {
+        let start = self.term()?;
+        if self.match_tokens(&[TokenType::Range, TokenType::RangeInclusive]) {
+            let inclusive = self.previous().tt == TokenType::RangeInclusive;
+            let operator = self.previous().span;
+            let end = self.term()?;
             if self.match_tokens(&[TokenType::Range]) {
-                let step = match self.term() {
-                    Ok(t) => t,
-                    Err(s) => return Err(s),
-                };
+                let step = self.term()?;
                 return Ok(Expr::Range {
                     min: Box::new(start),
                     max: Box::new(end),
                     step: Some(Box::new(step)),
-                    line: self.previous().line,
+                    inclusive,
+                    span: operator,
                 });
             }
             return Ok(Expr::Range {
                 min: Box::new(start),
                 max: Box::new(end),
                 step: None,
-                line: self.previous().line,
+                inclusive,
+                span: operator,
             });
         }
         Ok(start)
     }
-    fn term(&mut self) -> Result<Expr, String> {
-        let left: Expr = match self.factor() {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
+    fn term(&mut self) -> Result<Expr, Diagnostic> {
+        let left: Expr = self.factor()?;
         if self.match_tokens(&[TokenType::Plus, TokenType::Minus]) {
             let operator = self.previous();
-            let right: Expr = match self.term() {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            };
+            let right: Expr = self.term()?;
             return Ok(Expr::Binary {
                 left: Box::new(left),
                 operator,
@@ -416,17 +672,11 @@ impl Parser {
         }
         Ok(left)
     }
-    fn factor(&mut self) -> Result<Expr, String> {
-        let left: Expr = match self.unary() {
-            Ok(t) => t,
-            Err(s) => return Err(s),
-        };
+    fn factor(&mut self) -> Result<Expr, Diagnostic> {
+        let left: Expr = self.unary()?;
         if self.match_tokens(&[TokenType::Slash, TokenType::Star, TokenType::Modulo]) {
             let operator = self.previous();
-            let right: Expr = match self.factor() {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            };
+            let right: Expr = self.factor()?;
             return Ok(Expr::Binary {
                 left: Box::new(left),
                 operator,
@@ -435,103 +685,149 @@ impl Parser {
         }
         Ok(left)
     }
-    fn unary(&mut self) -> Result<Expr, String> {
+    fn unary(&mut self) -> Result<Expr, Diagnostic> {
         if self.match_tokens(&[TokenType::Bang, TokenType::Minus]) {
             let operator = self.previous();
-            let e = match self.primary() {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            };
+            let e = self.primary()?;
             return Ok(Expr::Unary {
                 operator,
                 expression: Box::new(e),
             });
         }
-        self.call(false)
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr, Diagnostic> {
+        let mut expr = self.call(false)?;
+        while self.match_tokens(&[TokenType::Question]) {
+            expr = Expr::Unwrap(Box::new(expr), self.previous().span);
+        }
+        Ok(expr)
     }
 
-    fn call(&mut self, native: bool) -> Result<Expr, String> {
-        let expr = match self.primary() {
-            Ok(t) => t,
-            Err(s) => return Err(s),
+    fn call(&mut self, native: bool) -> Result<Expr, Diagnostic> {
+        let start = match native {
+            true => self.previous().span, // std::
+            false => self.peek().span,
         };
+        let expr = self.primary()?;
         if self.match_tokens(&[TokenType::LeftParen]) {
             if !matches!(expr, Expr::Variable(_)) {
-                return Err(self.error("sir were you trying to call a function USING AN INTEGER?"));
+                return Err(self.error(&messages::CALL_ON_NUMBER, &[]));
             }
+            let mut vec: Vec<Expr> = Vec::new();
+            let mut named: Vec<(Token, Expr)> = Vec::new();
             if self.match_tokens(&[TokenType::RightParen]) {
                 return Ok(Expr::Call {
                     callee: Box::new(expr),
-                    arguments: Vec::new(),
+                    arguments: vec,
+                    named,
                     native,
+                    span: start.to(self.previous().span),
                 });
             }
-            let mut vec: Vec<Expr> = Vec::new();
-            vec.push(match self.logical() {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            });
+            if let Some(s) = self.argument(&mut vec, &mut named, native) {
+                return Err(s);
+            }
             while self.match_tokens(&[TokenType::Comma]) {
-                vec.push(match self.logical() {
-                    Ok(t) => t,
-                    Err(s) => return Err(s),
-                });
+                if let Some(s) = self.argument(&mut vec, &mut named, native) {
+                    return Err(s);
+                }
             }
-            match self.consume(TokenType::RightParen, "gotta close the call dude") {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            };
+            self.consume(
+                TokenType::RightParen,
+                &messages::EXPECTED,
+                &[")", "to close the call"],
+            )?;
             return Ok(Expr::Call {
                 callee: Box::new(expr),
                 arguments: vec,
+                named,
                 native,
+                span: start.to(self.previous().span),
             });
         }
         Ok(expr)
     }
+    fn argument(
+        &mut self,
+        vec: &mut Vec<Expr>,
+        named: &mut Vec<(Token, Expr)>,
+        native: bool,
+    ) -> Option<Diagnostic> {
+        if self.check(TokenType::Identifier) && self.check_next(TokenType::Equal) {
+            if native {
+                return Some(self.error(&messages::NAMED_NATIVE_ARGUMENTS, &[]));
+            }
+            let name = self.advance();
+            if named.iter().any(|(n, _)| n.lexeme == name.lexeme) {
+                return Some(self.error(&messages::DUPLICATE_ARGUMENT, &[&name.lexeme]));
+            }
+            self.advance(); // consume =
+            match self.logical() {
+                Ok(t) => named.push((name, t)),
+                Err(s) => return Some(s),
+            }
+            return None;
+        }
+        if !named.is_empty() {
+            return Some(self.error(&messages::POSITIONAL_AFTER_NAMED, &[]));
+        }
+        match self.logical() {
+            Ok(t) => vec.push(t),
+            Err(s) => return Some(s),
+        }
+        None
+    }
 
-    fn primary(&mut self) -> Result<Expr, String> {
+    fn primary(&mut self) -> Result<Expr, Diagnostic> {
+        if self.peek().literal.is_none()
+            && matches!(
+                self.peek().tt,
+                TokenType::Int | TokenType::Float | TokenType::String | TokenType::Bool
+            )
+        {
+            return Err(self.error(&messages::TYPE_AS_VALUE, &[&self.peek().lexeme]));
+        }
         if self.match_tokens(&[TokenType::Bool]) {
             if self.previous().lexeme == "true" {
-                return Ok(Expr::Literal(Value::Bool(true), self.previous().line));
+                return Ok(Expr::Literal(Value::Bool(true), self.previous().span));
             } else {
-                return Ok(Expr::Literal(Value::Bool(false), self.previous().line));
+                return Ok(Expr::Literal(Value::Bool(false), self.previous().span));
             }
         }
         if self.match_tokens(&[TokenType::LeftSquare]) {
+            let start = self.previous().span;
             let mut vec: Vec<Expr> = Vec::new();
             if self.match_tokens(&[TokenType::RightSquare]) {
-                return Ok(Expr::Vec(vec));
+                return Ok(Expr::Vec(vec, start.to(self.previous().span)));
             }
-            vec.push(match self.logical() {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            });
+            vec.push(self.logical()?);
             while self.match_tokens(&[TokenType::Comma]) {
-                vec.push(match self.logical() {
-                    Ok(t) => t,
-                    Err(s) => return Err(s),
-                });
+                vec.push(self.logical()?);
             }
-            match self.consume(TokenType::RightSquare, "gotta close the vec") {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            };
-            return Ok(Expr::Vec(vec));
+            let end = self.consume(
+                TokenType::RightSquare,
+                &messages::EXPECTED,
+                &["]", "to close the vector"],
+            )?;
+            return Ok(Expr::Vec(vec, start.to(end.span)));
         }
         if self.match_tokens(&[TokenType::String]) {
+            let span = self.previous().span;
             let string = self.previous().lexeme;
             let mut printables_t: Vec<Vec<Token>> = Vec::new();
             while self.match_tokens(&[TokenType::Printable]) {
                 let lexeme = self.previous().lexeme;
                 if lexeme.contains('"') {
-                    return Err(self
-                        .error("why would you use a string inside a string?? are you retarded??"));
+                    return Err(self.error(&messages::STRING_IN_INTERPOLATION, &[]));
                 }
                 let mut s = Scanner::new(&lexeme);
+                s.line = self.previous().span.line;
+                s.chars.offset = self.previous().span.start;
                 let mut s1 = match s.scan_tokens() {
                     Ok(s) => s,
-                    Err(err) => return Err(err),
+                    Err(mut err) => return Err(err.remove(0)),
                 };
                 s1.pop();
                 printables_t.push(s1);
@@ -540,44 +836,66 @@ impl Parser {
             let mut printables: Vec<Expr> = Vec::new();
             for i in printables_t {
                 self.tokens.splice(self.current..self.current, i);
-                printables.push(match self.logical() {
-                    Ok(t) => t,
-                    Err(s) => return Err(s),
-                });
+                printables.push(self.logical()?);
             }
-            return Ok(Expr::Literal(
-                Value::String { string, printables },
-                self.previous().line,
-            ));
+            return Ok(Expr::Literal(Value::String { string, printables }, span));
         }
 
         if self.match_tokens(&[TokenType::Int, TokenType::Float]) {
             return Ok(Expr::Literal(
                 self.previous().literal.unwrap(),
-                self.previous().line,
+                self.previous().span,
             ));
         }
         if self.match_tokens(&[TokenType::LeftParen]) {
-            let expression = match self.logical() {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            };
-            match self.consume(
+            let start = self.previous().span;
+            let expression = self.logical()?;
+            let end = self.consume(
                 TokenType::RightParen,
-                "expected \")\" after expression u piece of shit",
-            ) {
-                Ok(t) => t,
-                Err(s) => return Err(s),
-            };
-            return Ok(Expr::Grouping(Box::new(expression)));
+                &messages::EXPECTED,
+                &[")", "after the expression"],
+            )?;
+            return Ok(Expr::Grouping(Box::new(expression), start.to(end.span)));
         }
         if self.match_tokens(&[TokenType::NativeCall]) {
             return self.call(true);
         }
+        if self.match_tokens(&[TokenType::None]) {
+            return Ok(Expr::Literal(Value::None, self.previous().span));
+        }
+        if self.match_tokens(&[TokenType::Ok, TokenType::Err, TokenType::Some]) {
+            let variant = self.previous();
+            self.consume(
+                TokenType::LeftParen,
+                &messages::EXPECTED,
+                &["(", &format!("after {}", variant.lexeme)],
+            )?;
+            let value = self.logical()?;
+            self.consume(
+                TokenType::RightParen,
+                &messages::EXPECTED,
+                &[")", "to close the call"],
+            )?;
+            return Ok(Expr::Wrap {
+                variant,
+                value: Box::new(value),
+            });
+        }
         if self.match_tokens(&[TokenType::Identifier]) {
-            return Ok(Expr::Variable(self.previous()));
+            let mut name = self.previous();
+            while self.match_tokens(&[TokenType::ColonColon]) {
+                //a name from another module, like utils::f
+                let item = self.consume(
+                    TokenType::Identifier,
+                    &messages::EXPECTED_NAME,
+                    &["the item after ::"],
+                )?;
+                name.lexeme = format!("{}::{}", name.lexeme, item.lexeme);
+                name.span = name.span.to(item.span);
+            }
+            return Ok(Expr::Variable(name));
         }
-        Err(self.error(&format!("expected value found {}", self.peek().tt)))
+        Err(self.error(&messages::EXPECTED_VALUE, &[&self.peek().tt.to_string()]))
     }
 
     fn match_tokens(&mut self, types: &[TokenType]) -> bool {
@@ -596,6 +914,12 @@ impl Parser {
         }
         self.peek().tt == t_type
     }
+    fn check_next(&self, t_type: TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(t) => t.tt == t_type,
+            None => false,
+        }
+    }
 
     fn advance(&mut self) -> Token {
         if !self.is_at_end() {
@@ -612,13 +936,246 @@ impl Parser {
     fn previous(&self) -> Token {
         self.tokens[self.current - 1].clone()
     }
-    fn error(&self, msg: &str) -> String {
-        KlangError::error(KlangError::ParserError, msg, self.peek().line)
+    fn error(&self, message: &Message, args: &[&str]) -> Diagnostic {
+        KlangError::at(KlangError::ParserError, message, args, self.peek().span)
     }
-    fn consume(&mut self, t_type: TokenType, msg: &str) -> Result<Token, String> {
+    fn consume(
+        &mut self,
+        t_type: TokenType,
+        message: &Message,
+        args: &[&str],
+    ) -> Result<Token, Diagnostic> {
         if self.peek().tt == t_type {
             return Ok(self.advance());
         }
-        Err(self.error(msg))
+        if t_type == TokenType::Semicolon && self.current > 0 {
+            //a missing ; goes right after the token before it, not at the start of the next line
+            let previous = self.previous().span;
+            let span = Span::new(previous.line, previous.end, previous.end);
+            return Err(KlangError::at(KlangError::ParserError, message, args, span));
+        }
+        Err(self.error(message, args))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Span;
+
+    // the first error scanning and parsing the source finds
+    fn first_error(source: &str) -> Diagnostic {
+        let tokens = match Scanner::new(source).scan_tokens() {
+            Ok(tokens) => tokens,
+            Err(mut errors) => return errors.remove(0),
+        };
+        Parser::new(tokens).parse().unwrap_err().remove(0)
+    }
+
+    fn parse_partial(source: &str, max_errors: usize) -> (Vec<Stmt>, Vec<Diagnostic>) {
+        let tokens = Scanner::new(source).scan_tokens().unwrap();
+        Parser::new(tokens).max_errors(max_errors).parse_partial()
+    }
+
+    // the span of the nth time text is written in the source
+    fn at(source: &str, text: &str, nth: usize) -> Span {
+        let start = source.match_indices(text).nth(nth).unwrap().0;
+        let line = source[..start].matches('\n').count() + 1;
+        Span::new(line, start, start + text.len())
+    }
+
+    // the empty span right after the first time text is written in the source
+    fn after(source: &str, text: &str) -> Span {
+        let end = source.find(text).unwrap() + text.len();
+        let line = source[..end].matches('\n').count() + 1;
+        Span::new(line, end, end)
+    }
+
+    #[test]
+    fn bad_tokens_are_underlined() {
+        let source = "let a = 1;\nlet b = );";
+        let error = first_error(source);
+        assert_eq!(error.span, Span::new(2, 19, 20));
+        assert_eq!(error.span, at(source, ")", 0));
+        let source = "let a = 1;\nlet b = 2 $ 3;";
+        let error = first_error(source);
+        assert_eq!(error.kind, KlangError::ScannerError);
+        assert_eq!(error.span, at(source, "$", 0));
+    }
+
+    #[test]
+    fn unterminated_strings_are_underlined_to_the_end() {
+        let source = "let a = 1;\nprint(\"hi there);";
+        let error = first_error(source);
+        assert_eq!(error.message, "unterminated string");
+        assert_eq!(error.span, Span::new(2, 17, source.len()));
+    }
+
+    #[test]
+    fn spans_count_bytes_not_characters() {
+        let source = "let s = \"ü\";\nlet b = );";
+        let error = first_error(source);
+        assert_eq!(error.span, Span::new(2, 22, 23));
+        let source = "let é = 1;";
+        assert_eq!(first_error(source).span, Span::new(1, 4, 6));
+    }
+
+    #[test]
+    fn every_broken_statement_is_reported() {
+        let source = "let a = ;\nprint(\"ok\");\nlet = 2;\nlet c = 3;\nconst D;";
+        let (stmts, errors) = parse_partial(source, MAX_ERRORS);
+        let errors: Vec<(usize, &str)> = errors
+            .iter()
+            .map(|e| (e.span.line, e.message.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (1, "expected a value but found Semicolon"),
+                (3, "expected the name of the variable"),
+                (5, "expected = and a value for the constant"),
+            ]
+        );
+        assert_eq!(stmts.len(), 2);
+    }
+
+    #[test]
+    fn recovery_resumes_at_the_next_statement() {
+        let source = "let a = ;\nlet b = 2;\nprint(\"ok\")\nlet d = 4;";
+        let (stmts, errors) = parse_partial(source, MAX_ERRORS);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].span, at(source, ";", 0));
+        assert_eq!(errors[1].message, "missing ; at the end of the line");
+        assert_eq!(errors[1].span, after(source, "print(\"ok\")"));
+        let names: Vec<&str> = stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Var {
+                    pattern: Pattern::Identifier { name, .. },
+                    ..
+                } => Some(name.lexeme.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(names, vec!["b", "d"]);
+    }
+
+    #[test]
+    fn errors_stop_at_the_cap() {
+        let source = "let = 1;\n".repeat(5);
+        let (_, errors) = parse_partial(&source, 3);
+        assert_eq!(errors.len(), 3);
+        assert!(errors[..2].iter().all(|e| e.notes.is_empty()));
+        assert_eq!(
+            errors[2].to_string(),
+            "[ParserError] at line 3: expected the name of the variable\n  \
+            note: stopped after 3 errors, there may be more"
+        );
+        let (_, errors) = parse_partial(&source, 6);
+        assert_eq!(errors.len(), 5);
+        assert!(errors.iter().all(|e| e.notes.is_empty()));
+        let (_, errors) = parse_partial(&source, 0);
+        assert_eq!(errors.len(), 1);
+    }
+
+    fn parse(source: &str) -> Vec<Stmt> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        Parser::new(tokens)
+            .docs(std::mem::take(&mut scanner.docs))
+            .parse()
+            .unwrap()
+    }
+
+    // the doc comment of every let, const and fn, in order
+    fn docs(source: &str) -> Vec<Option<String>> {
+        parse(source)
+            .into_iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Var { doc, .. } | Stmt::Const { doc, .. } | Stmt::Fn { doc, .. } => Some(doc),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn doc_comments() {
+        let source = "/// first\n/// line two\nfn f() {}\nlet x = 1;\n/// the answer\nlet y = 2;";
+        assert_eq!(
+            docs(source),
+            vec![
+                Some("first\nline two".to_string()),
+                None,
+                Some("the answer".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn doc_comments_on_constants_and_destructuring() {
+        let source = "/// the answer\nconst K = 42;\n/// both halves\nlet [a, b] = [1, 2];";
+        assert_eq!(
+            docs(source),
+            vec![
+                Some("the answer".to_string()),
+                Some("both halves".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn doc_comments_after_interpolation() {
+        let source = "let x = 1;\nprint(\"{x} and {x + 1}\");\n/// doubles\nfn double(n) { return n * 2; }\n/// the answer\nlet answer = \"{double(21)}\";\nlet other = 3;";
+        assert_eq!(
+            docs(source),
+            vec![
+                None,
+                Some("doubles".to_string()),
+                Some("the answer".to_string()),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn doc_comment_before_other_statements() {
+        let source = "/// not for the print\nprint(\"hi\");\nlet x = 1;";
+        assert_eq!(docs(source), vec![None]);
+    }
+
+    #[test]
+    fn comment_tokens_are_skipped() {
+        let source = "/// doubles\nfn double(n) { return n * 2; } // twice\n/* a /* nested */ note */ let x = double(2);";
+        let mut scanner = Scanner::new(source).trivia(true);
+        let tokens = scanner.scan_tokens().unwrap();
+        let stmts = Parser::new(tokens)
+            .docs(std::mem::take(&mut scanner.docs))
+            .parse()
+            .unwrap();
+        assert_eq!(format!("{stmts:?}"), format!("{:?}", parse(source)));
+    }
+
+    #[test]
+    fn every_error_is_reported_once() {
+        let source = "let a = 1\nlet b = ;\nfn g( { }\nprint(\"ok\");\nlet c = 3\nfn h() {}";
+        let (stmts, errors) = parse_partial(source, MAX_ERRORS);
+        let errors: Vec<(Option<u16>, Span)> = errors.iter().map(|e| (e.code, e.span)).collect();
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    Some(messages::MISSING_SEMICOLON.code),
+                    after(source, "let a = 1")
+                ),
+                (Some(messages::EXPECTED_VALUE.code), at(source, ";", 0)),
+                (Some(messages::EXPECTED_NAME.code), at(source, "{", 0)),
+                (
+                    Some(messages::MISSING_SEMICOLON.code),
+                    after(source, "let c = 3")
+                ),
+            ]
+        );
+        //the print and fn h still parse after the broken statements before them
+        assert_eq!(stmts.len(), 2);
     }
 }
@@ -1,54 +1,81 @@
 #![allow(unused)]
 use super::expr::Expr;
-use crate::{error, KlangError};
+use crate::compiling::vm::Iter;
+use crate::error::{Diagnostic, KlangError, Span};
+use crate::messages::{self, Message};
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::fmt;
 use std::iter::Peekable;
+use std::rc::Rc;
 use std::str::Chars;
 
 #[derive(Debug, Clone)]
 pub struct Scanner<'a> {
     pub source: &'a str,
-    pub chars: Peekable<Chars<'a>>,
+    pub chars: Cursor<'a>,
     pub line: usize,
+    start: usize, // the byte the token being scanned starts at
     pub tokens: Vec<Token>,
-    had_error: bool,
+    pub directives: Vec<String>, // from comments like // klang:immutable
+    pub docs: HashMap<usize, String>, // from /// comments, by the byte the token right after them starts at
+    doc: Vec<String>,                 // the lines of the doc comment being read
+    trivia: bool, // comments are kept as tokens, for tools that write the source back
+    errors: Vec<Diagnostic>,
 }
 
 impl<'a> Scanner<'a> {
     pub fn new(source: &'a str) -> Scanner<'a> {
         Scanner {
             source,
-            chars: source.chars().peekable(),
+            chars: Cursor {
+                chars: source.chars().peekable(),
+                offset: 0,
+            },
             line: 1,
+            start: 0,
             tokens: Vec::new(),
-            had_error: false,
+            directives: Vec::new(),
+            docs: HashMap::new(),
+            doc: Vec::new(),
+            trivia: false,
+            errors: Vec::new(),
         }
     }
+    pub fn trivia(mut self, trivia: bool) -> Scanner<'a> {
+        self.trivia = trivia;
+        self
+    }
 
-    pub fn scan_tokens(&mut self) -> Result<Vec<Token>, String> {
-        let mut error_string = String::new();
-        while let Some(ch) = self.chars.next() {
+    pub fn scan_tokens(&mut self) -> Result<Vec<Token>, Vec<Diagnostic>> {
+        loop {
+            self.start = self.chars.offset;
+            let ch = match self.chars.next() {
+                Some(ch) => ch,
+                None => break,
+            };
             match ch {
                 '(' => self.make_token(TokenType::LeftParen, ch.to_string(), self.line, None),
                 ')' => self.make_token(TokenType::RightParen, ch.to_string(), self.line, None),
                 '{' => self.make_token(TokenType::LeftBrace, ch.to_string(), self.line, None),
                 '}' => self.make_token(TokenType::RightBrace, ch.to_string(), self.line, None),
                 ',' => self.make_token(TokenType::Comma, ch.to_string(), self.line, None),
+                ':' if self.is_next(':') => {
+                    self.chars.next();
+                    self.make_token(TokenType::ColonColon, "::".to_string(), self.line, None)
+                }
+                ':' => self.make_token(TokenType::Colon, ch.to_string(), self.line, None),
+                '-' if self.is_next('>') => {
+                    self.chars.next();
+                    self.make_token(TokenType::Arrow, "->".to_string(), self.line, None)
+                }
                 '-' => {
                     if self.tokens.len() >= 2
                         && self.tokens[self.tokens.len() - 1].tt == TokenType::Minus
                         && self.tokens[self.tokens.len() - 2].tt != TokenType::Int
                         && self.tokens[self.tokens.len() - 2].tt != TokenType::Float
                     {
-                        error_string += error::KlangError::error(
-                            KlangError::ScannerError,
-                            "we shall not allow minus spamming. use 1 bitch",
-                            self.line,
-                        )
-                        .as_str();
-                        error_string += "\n";
-                        self.had_error = true;
+                        self.error(&messages::REPEATED_MINUS, &[]);
                     }
                     self.make_token(TokenType::Minus, ch.to_string(), self.line, None)
                 }
@@ -57,15 +84,11 @@ impl<'a> Scanner<'a> {
                 '*' => self.make_token(TokenType::Star, ch.to_string(), self.line, None),
                 '%' => self.make_token(TokenType::Modulo, ch.to_string(), self.line, None),
                 '[' => self.make_token(TokenType::LeftSquare, ch.to_string(), self.line, None),
+                '?' => self.make_token(TokenType::Question, ch.to_string(), self.line, None),
                 ']' => self.make_token(TokenType::RightSquare, ch.to_string(), self.line, None),
-                '/' => {
-                    if self.is_next('/') {
-                        while self.chars.next() != Some('\n') && self.chars.peek().is_some() {}
-                        self.line += 1;
-                    } else {
-                        self.make_token(TokenType::Slash, ch.to_string(), self.line, None);
-                    }
-                }
+                '/' if self.is_next('/') => self.line_comment(),
+                '/' if self.is_next('*') => self.block_comment(),
+                '/' => self.make_token(TokenType::Slash, ch.to_string(), self.line, None),
                 '!' => {
                     if self.is_next('=') {
                         let next = self.chars.next().unwrap();
@@ -79,14 +102,7 @@ impl<'a> Scanner<'a> {
                         if !self.tokens.is_empty()
                             && self.tokens[self.tokens.len() - 1].tt == TokenType::Bang
                         {
-                            error_string += error::KlangError::error(
-                                KlangError::ScannerError,
-                                "we shall not allow bang spamming. use 1 bitch",
-                                self.line,
-                            )
-                            .as_str();
-                            error_string += "\n";
-                            self.had_error = true;
+                            self.error(&messages::REPEATED_BANG, &[]);
                         }
                         self.make_token(TokenType::Bang, ch.to_string(), self.line, None)
                     }
@@ -133,12 +149,29 @@ impl<'a> Scanner<'a> {
                 '.' => {
                     if self.is_next('.') {
                         let next = self.chars.next().unwrap();
-                        self.make_token(
-                            TokenType::Range,
-                            String::from(ch) + &String::from(next),
-                            self.line,
-                            None,
-                        );
+                        if self.is_next('.') {
+                            self.chars.next();
+                            self.make_token(TokenType::Ellipsis, "...".to_string(), self.line, None)
+                        } else if self.is_next('=') {
+                            self.chars.next();
+                            self.make_token(
+                                TokenType::RangeInclusive,
+                                "..=".to_string(),
+                                self.line,
+                                None,
+                            )
+                        } else {
+                            self.make_token(
+                                TokenType::Range,
+                                String::from(ch) + &String::from(next),
+                                self.line,
+                                None,
+                            )
+                        }
+                    } else if self.chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                        let mut number = String::from("0.");
+                        self.digits(&mut number);
+                        self.error(&messages::LEADING_DOT, &[&number]);
                     } else {
                         self.make_token(TokenType::Dot, ch.to_string(), self.line, None)
                     }
@@ -153,14 +186,7 @@ impl<'a> Scanner<'a> {
                             None,
                         )
                     } else {
-                        error_string += error::KlangError::error(
-                            KlangError::ScannerError,
-                            "missing a second & you fat fuck",
-                            self.line,
-                        )
-                        .as_str();
-                        error_string += "\n";
-                        self.had_error = true;
+                        self.error(&messages::SINGLE_AMPERSAND, &[]);
                     }
                 }
                 '|' => {
@@ -173,77 +199,143 @@ impl<'a> Scanner<'a> {
                             None,
                         )
                     } else {
-                        error_string += error::KlangError::error(
-                            KlangError::ScannerError,
-                            "missing a second | you stupid gay",
-                            self.line,
-                        )
-                        .as_str();
-                        error_string += "\n";
-                        self.had_error = true;
+                        self.error(&messages::SINGLE_PIPE, &[]);
                     }
                 }
-                '"' => error_string += self.string().as_str(),
+                '"' => self.string(),
                 ' ' => (),
                 '\r' => (),
                 '\t' => (),
                 '\n' => self.line += 1,
                 _ => {
                     if ch.is_ascii_digit() {
-                        error_string += self.number(ch).as_str();
-                    } else if ch.is_ascii_alphabetic() {
-                        error_string += self.identifier(ch).as_str();
+                        self.number(ch);
+                    } else if ch.is_ascii_alphabetic() || ch == '_' {
+                        self.identifier(ch);
                     } else {
-                        error_string += error::KlangError::error(
-                            KlangError::ScannerError,
-                            "unexpected character",
-                            self.line,
-                        )
-                        .as_str();
-                        error_string += "\n";
-                        self.had_error = true;
+                        self.error(&messages::UNEXPECTED_CHARACTER, &[]);
                     }
                 }
             }
         }
+        self.start = self.chars.offset;
         self.make_token(TokenType::Eof, String::from(""), self.line, None);
-        if self.had_error {
-            Err(error_string)
+        if !self.errors.is_empty() {
+            Err(std::mem::take(&mut self.errors))
         } else {
             Ok(std::mem::take(&mut self.tokens))
         }
     }
+    fn error(&mut self, message: &Message, args: &[&str]) {
+        let span = Span::new(self.line, self.start, self.chars.offset);
+        self.errors.push(KlangError::at(
+            KlangError::ScannerError,
+            message,
+            args,
+            span,
+        ));
+    }
     fn make_token(&mut self, tt: TokenType, text: String, line: usize, value: Option<Value>) {
+        if !self.doc.is_empty() && tt != TokenType::Comment {
+            self.docs.insert(self.start, self.doc.join("\n"));
+            self.doc.clear();
+        }
         self.tokens.push(Token {
             tt,
             lexeme: text,
             literal: value,
-            line,
+            span: Span::new(line, self.start, self.chars.offset),
         })
     }
+    fn line_comment(&mut self) {
+        //the newline is left for the loop to count, a comment can end the file without one
+        let mut comment = String::from("/");
+        while let Some(c) = self.chars.peek().copied().filter(|c| *c != '\n') {
+            comment.push(c);
+            self.chars.next();
+        }
+        let text = comment.trim_end_matches('\r');
+        //exactly three slashes, //// is a line of slashes
+        if let Some(doc) = text.strip_prefix("///").filter(|doc| !doc.starts_with('/')) {
+            self.doc
+                .push(doc.strip_prefix(' ').unwrap_or(doc).to_string());
+        } else if let Some(directive) = text.trim_start_matches('/').trim().strip_prefix("klang:") {
+            self.directives.push(directive.to_string());
+        }
+        if self.trivia {
+            let text = text.to_string();
+            self.make_token(TokenType::Comment, text, self.line, None);
+        }
+    }
+    fn block_comment(&mut self) {
+        //block comments nest, /* a /* b */ c */ is one comment
+        let line = self.line;
+        self.chars.next();
+        let mut depth = 1;
+        while depth > 0 {
+            match self.chars.next() {
+                Some('/') if self.is_next('*') => {
+                    self.chars.next();
+                    depth += 1;
+                }
+                Some('*') if self.is_next('/') => {
+                    self.chars.next();
+                    depth -= 1;
+                }
+                Some('\n') => self.line += 1,
+                Some(_) => (),
+                None => {
+                    //pointing at the start, the end of the file says nothing
+                    self.errors.push(KlangError::at(
+                        KlangError::ScannerError,
+                        &messages::UNTERMINATED_COMMENT,
+                        &[],
+                        Span::new(line, self.start, self.start + 2),
+                    ));
+                    return;
+                }
+            }
+        }
+        if self.trivia {
+            let text = self.source[self.start..self.chars.offset].to_string();
+            self.make_token(TokenType::Comment, text, line, None);
+        }
+    }
     fn is_next(&mut self, ch: char) -> bool {
         self.chars.peek() == Some(&ch)
     }
 
-    fn identifier(&mut self, ch: char) -> String {
+    fn identifier(&mut self, ch: char) {
         let mut word = String::from(ch);
-        while self.chars.peek().unwrap_or(&'\0').is_ascii_alphanumeric() {
+        while matches!(self.chars.peek(), Some(c) if c.is_ascii_alphanumeric() || *c == '_') {
             word.push(self.chars.next().unwrap());
         }
         match word.as_str() {
             "let" => self.make_token(TokenType::Let, "".to_string(), self.line, None),
+            "const" => self.make_token(TokenType::Const, "".to_string(), self.line, None),
+            "mut" => self.make_token(TokenType::Mut, "".to_string(), self.line, None),
             "in" => self.make_token(TokenType::In, "".to_string(), self.line, None),
             "else" => self.make_token(TokenType::Else, "".to_string(), self.line, None),
             "for" => self.make_token(TokenType::For, "".to_string(), self.line, None),
             "if" => self.make_token(TokenType::If, "".to_string(), self.line, None),
             "print" => self.make_token(TokenType::Print, "".to_string(), self.line, None),
             "while" => self.make_token(TokenType::While, "".to_string(), self.line, None),
-            "int" => self.make_token(TokenType::Int, "".to_string(), self.line, None),
-            "float" => self.make_token(TokenType::Float, "".to_string(), self.line, None),
-            "string" => self.make_token(TokenType::String, "".to_string(), self.line, None),
-            "bool" => self.make_token(TokenType::Bool, "".to_string(), self.line, None),
+            "int" => self.make_token(TokenType::Int, word.clone(), self.line, None),
+            "float" => self.make_token(TokenType::Float, word.clone(), self.line, None),
+            "string" => self.make_token(TokenType::String, word.clone(), self.line, None),
+            "bool" => self.make_token(TokenType::Bool, word.clone(), self.line, None),
             "fn" => self.make_token(TokenType::Fn, "".to_string(), self.line, None),
             "return" => self.make_token(TokenType::Return, "".to_string(), self.line, None),
+            "yield" => self.make_token(TokenType::Yield, "".to_string(), self.line, None),
+            "try" => self.make_token(TokenType::Try, "".to_string(), self.line, None),
+            "catch" => self.make_token(TokenType::Catch, "".to_string(), self.line, None),
+            "throw" => self.make_token(TokenType::Throw, "".to_string(), self.line, None),
+            "import" => self.make_token(TokenType::Import, "".to_string(), self.line, None),
+            "use" => self.make_token(TokenType::Use, "".to_string(), self.line, None),
+            "Ok" => self.make_token(TokenType::Ok, word.clone(), self.line, None),
+            "Err" => self.make_token(TokenType::Err, word.clone(), self.line, None),
+            "Some" => self.make_token(TokenType::Some, word.clone(), self.line, None),
+            "None" => self.make_token(TokenType::None, word.clone(), self.line, None),
             "true" => self.make_token(
                 TokenType::Bool,
                 "true".to_string(),
@@ -260,85 +352,109 @@ impl<'a> Scanner<'a> {
                 if self.chars.next().unwrap() == ':' && self.chars.next().unwrap() == ':' {
                     self.make_token(TokenType::NativeCall, "".to_string(), self.line, None)
                 } else {
-                    self.had_error = true;
-                    return error::KlangError::error(
-                        KlangError::ScannerError,
-                        "cannot use std without calling a native fn",
-                        self.line,
-                    ) + "\n";
+                    self.error(&messages::STD_WITHOUT_CALL, &[]);
                 }
             }
             _ => self.make_token(TokenType::Identifier, word, self.line, None),
         }
-        String::new()
     }
-    fn number(&mut self, ch: char) -> String {
-        let mut number = String::from(ch);
-        while self.chars.peek().unwrap_or(&'\0').is_ascii_digit() {
+    fn number(&mut self, ch: char) {
+        //0x, 0o and 0b numbers are ints in base 16, 8 and 2
+        let radix = match (ch, self.chars.peek()) {
+            ('0', Some('x')) => 16,
+            ('0', Some('o')) => 8,
+            ('0', Some('b')) => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            let mut number = String::from(ch);
             number.push(self.chars.next().unwrap());
+            //every letter is taken, so 0b102 is one wrong number instead of 0b10 and 2
+            while matches!(self.chars.peek(), Some(c) if c.is_ascii_alphanumeric() || *c == '_') {
+                number.push(self.chars.next().unwrap());
+            }
+            let value = separated(&number[2..], radix)
+                .and_then(|digits| i64::from_str_radix(&digits, radix).ok());
+            return match value {
+                Some(value) => self.make_token(
+                    TokenType::Int,
+                    "".to_string(),
+                    self.line,
+                    Some(Value::Number(value as f64)),
+                ),
+                None => self.error(&messages::INVALID_NUMBER, &[&number]),
+            };
         }
+        let mut number = String::from(ch);
+        self.digits(&mut number);
         if self.chars.peek().unwrap_or(&'\0') == &'.' {
             number.push(self.chars.next().unwrap());
             if self.chars.peek().unwrap_or(&'\0') == &'.' {
                 number.pop();
-                let value = match number.parse::<i64>() {
-                    Ok(e) => Some(Value::Number(e as f64)),
-                    Err(_) => {
-                        self.had_error = true;
-                        return error::KlangError::error(
-                            KlangError::ScannerError,
-                            "failed to parse integer",
-                            self.line,
-                        ) + "\n";
+                let value = match separated(&number, 10).and_then(|n| n.parse::<i64>().ok()) {
+                    Some(e) => Some(Value::Number(e as f64)),
+                    None => {
+                        return self.error(&messages::INVALID_NUMBER, &[&number]);
                     }
                 };
-                self.make_token(TokenType::Int, "".to_string(), self.line, value);
-                self.make_token(TokenType::Range, "..".to_string(), self.line, None);
+                //the first dot was already taken, it belongs to the range
+                let dot = self.chars.offset - 1;
+                self.tokens.push(Token {
+                    tt: TokenType::Int,
+                    lexeme: "".to_string(),
+                    literal: value,
+                    span: Span::new(self.line, self.start, dot),
+                });
+                self.start = dot;
                 self.chars.next(); //consume 2nd dot
-                String::new()
-            } else {
-                while self.chars.peek().unwrap_or(&'\0').is_ascii_digit() {
-                    number.push(self.chars.next().unwrap());
-                }
-                if number.ends_with('.') {
-                    self.had_error = true;
-                    return error::KlangError::error(
-                        KlangError::ScannerError,
-                        "float cant end with a dot",
+                if self.is_next('=') {
+                    self.chars.next();
+                    self.make_token(
+                        TokenType::RangeInclusive,
+                        "..=".to_string(),
                         self.line,
-                    ) + "\n";
+                        None,
+                    );
+                } else {
+                    self.make_token(TokenType::Range, "..".to_string(), self.line, None);
                 }
-                let value = match number.parse::<f64>() {
-                    Ok(e) => Some(Value::Number(e)),
-                    Err(_) => {
-                        self.had_error = true;
-                        return error::KlangError::error(
-                            KlangError::ScannerError,
-                            "failed to parse float",
-                            self.line,
-                        ) + "\n";
-                    }
-                };
-                self.make_token(TokenType::Float, "".to_string(), self.line, value);
-                String::new()
+                return;
             }
-        } else {
-            let value = match number.parse::<i64>() {
-                Ok(e) => Some(Value::Number(e as f64)),
-                Err(_) => {
-                    self.had_error = true;
-                    return error::KlangError::error(
-                        KlangError::ScannerError,
-                        "failed to parse integer",
-                        self.line,
-                    ) + "\n";
+            self.digits(&mut number);
+            if number.ends_with('.') {
+                return self.error(&messages::TRAILING_DOT, &[]);
+            }
+        } else if !matches!(self.chars.peek(), Some('e' | 'E')) {
+            let value = match separated(&number, 10).and_then(|n| n.parse::<i64>().ok()) {
+                Some(e) => Some(Value::Number(e as f64)),
+                None => {
+                    return self.error(&messages::INVALID_NUMBER, &[&number]);
                 }
             };
-            self.make_token(TokenType::Int, "".to_string(), self.line, value);
-            String::new()
+            return self.make_token(TokenType::Int, "".to_string(), self.line, value);
+        }
+        //1.5e-3 and 2e10 are floats, even when they have no fraction
+        if matches!(self.chars.peek(), Some('e' | 'E')) {
+            number.push(self.chars.next().unwrap());
+            if matches!(self.chars.peek(), Some('+' | '-')) {
+                number.push(self.chars.next().unwrap());
+            }
+            self.digits(&mut number);
         }
+        let value = match separated(&number, 10).and_then(|n| n.parse::<f64>().ok()) {
+            Some(e) => Some(Value::Number(e)),
+            None => {
+                return self.error(&messages::INVALID_NUMBER, &[&number]);
+            }
+        };
+        self.make_token(TokenType::Float, "".to_string(), self.line, value);
     }
-    fn string(&mut self) -> String {
+    fn digits(&mut self, number: &mut String) {
+        while matches!(self.chars.peek(), Some(c) if c.is_ascii_digit() || *c == '_') {
+            number.push(self.chars.next().unwrap());
+        }
+    }
+    fn string(&mut self) {
         let mut printables: Vec<Token> = Vec::new();
         let mut string = String::new();
         while self.chars.peek().unwrap_or(&'\0') != &'"' {
@@ -346,24 +462,15 @@ impl<'a> Scanner<'a> {
                 self.line += 1
             }
             if self.chars.peek().is_none() {
-                self.had_error = true;
-                return error::KlangError::error(
-                    KlangError::ScannerError,
-                    "unterminated string",
-                    self.line,
-                ) + "\n";
+                return self.error(&messages::UNTERMINATED_STRING, &[]);
             } else {
                 match self.chars.peek().unwrap() {
                     '{' => {
                         string.push(self.chars.next().unwrap());
                         let mut string1 = String::new();
+                        let start = self.chars.offset;
                         if self.chars.peek() == Some(&'}') {
-                            self.had_error = true;
-                            return error::KlangError::error(
-                                KlangError::ScannerError,
-                                "cannot print an empty identifier",
-                                self.line,
-                            ) + "\n";
+                            return self.error(&messages::EMPTY_INTERPOLATION, &[]);
                         }
                         let mut counter = 1;
                         while self.chars.peek().is_some() {
@@ -379,7 +486,7 @@ impl<'a> Scanner<'a> {
                                     tt: TokenType::Printable,
                                     lexeme: string1,
                                     literal: None,
-                                    line: self.line,
+                                    span: Span::new(self.line, start, self.chars.offset),
                                 });
                                 break;
                             }
@@ -390,14 +497,32 @@ impl<'a> Scanner<'a> {
             }
         }
         self.chars.next(); //consume the 2nd "
-        self.make_token(TokenType::String, string, self.line, None);
+        let value = Some(Value::String {
+            string: string.clone(),
+            printables: Vec::new(),
+        });
+        self.make_token(TokenType::String, string, self.line, value);
         for i in printables.into_iter() {
             self.tokens.push(i);
         }
-        String::new()
     }
 }
 
+// the digits of a number without its _ separators, when every _ is between two digits
+// of the radix, so the e of 1e_5 does not count as one
+fn separated(number: &str, radix: u32) -> Option<String> {
+    let chars: Vec<char> = number.chars().collect();
+    for (i, c) in chars.iter().enumerate() {
+        let between = i > 0
+            && chars[i - 1].is_digit(radix)
+            && chars.get(i + 1).is_some_and(|c| c.is_digit(radix));
+        if *c == '_' && !between {
+            return None;
+        }
+    }
+    Some(number.replace('_', ""))
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 pub enum TokenType {
     LeftParen,
@@ -405,6 +530,9 @@ pub enum TokenType {
     LeftBrace,
     RightBrace,
     Comma,
+    Colon,
+    ColonColon,
+    Arrow,
     Dot,
     Minus,
     Plus,
@@ -414,6 +542,7 @@ pub enum TokenType {
     Semicolon,
     LeftSquare,
     RightSquare,
+    Question,
 
     Bang,
     BangEqual,
@@ -427,6 +556,8 @@ pub enum TokenType {
     Or,
 
     Let,
+    Const,
+    Mut,
     Identifier,
     String,
     Int,
@@ -436,12 +567,25 @@ pub enum TokenType {
     Else,
     For,
     Range,
+    RangeInclusive,
+    Ellipsis,
     In,
     While,
     Print,
     Fn,
     Return,
+    Yield,
+    Try,
+    Catch,
+    Throw,
+    Import,
+    Use,
+    Ok,
+    Err,
+    Some,
+    None,
     Printable,
+    Comment, // only with trivia on
     NativeCall,
     Eof,
 }
@@ -453,9 +597,13 @@ impl fmt::Display for TokenType {
             TokenType::RightParen => write!(f, "RightParen"),
             TokenType::LeftSquare => write!(f, "LeftSquare"),
             TokenType::RightSquare => write!(f, "RightSquare"),
+            TokenType::Question => write!(f, "Question"),
             TokenType::LeftBrace => write!(f, "LeftBrace"),
             TokenType::RightBrace => write!(f, "RightBrace"),
             TokenType::Comma => write!(f, "Comma"),
+            TokenType::Colon => write!(f, "Colon"),
+            TokenType::ColonColon => write!(f, "ColonColon"),
+            TokenType::Arrow => write!(f, "Arrow"),
             TokenType::Dot => write!(f, "Dot"),
             TokenType::Minus => write!(f, "Minus"),
             TokenType::Plus => write!(f, "Plus"),
@@ -474,6 +622,8 @@ impl fmt::Display for TokenType {
             TokenType::And => write!(f, "And"),
             TokenType::Or => write!(f, "Or"),
             TokenType::Let => write!(f, "Let"),
+            TokenType::Const => write!(f, "Const"),
+            TokenType::Mut => write!(f, "Mut"),
             TokenType::Identifier => write!(f, "Identifier"),
             TokenType::String => write!(f, "String"),
             TokenType::Int => write!(f, "Int"),
@@ -483,13 +633,26 @@ impl fmt::Display for TokenType {
             TokenType::Else => write!(f, "Else"),
             TokenType::For => write!(f, "For"),
             TokenType::Range => write!(f, "Range"),
+            TokenType::RangeInclusive => write!(f, "RangeInclusive"),
+            TokenType::Ellipsis => write!(f, "Ellipsis"),
             TokenType::In => write!(f, "In"),
             TokenType::While => write!(f, "While"),
             TokenType::Print => write!(f, "Print"),
             TokenType::Fn => write!(f, "function"),
             TokenType::Return => write!(f, "return"),
+            TokenType::Yield => write!(f, "yield"),
+            TokenType::Try => write!(f, "try"),
+            TokenType::Catch => write!(f, "catch"),
+            TokenType::Throw => write!(f, "throw"),
+            TokenType::Import => write!(f, "import"),
+            TokenType::Use => write!(f, "use"),
+            TokenType::Ok => write!(f, "Ok"),
+            TokenType::Err => write!(f, "Err"),
+            TokenType::Some => write!(f, "Some"),
+            TokenType::None => write!(f, "None"),
             TokenType::Eof => write!(f, "Eof"),
             TokenType::Printable => write!(f, "Printable"),
+            TokenType::Comment => write!(f, "Comment"),
             TokenType::NativeCall => write!(f, "NativeCall"),
         }
     }
@@ -503,10 +666,35 @@ pub enum Value {
     },
     Number(f64),
     Bool(bool),
-    Vec(Vec<Value>),
+    Vec(Rc<RefCell<Vec<Value>>>), // vectors are shared, every copy of one is the same vector
+    Range {
+        start: f64,
+        end: f64,
+        step: f64,
+        inclusive: bool,
+    }, // a range is never built into a vector, unless it has to be
+    Iterator(Box<Iter>),
+    Error {
+        message: String,
+        line: usize,
+    },
+    Ok(Box<Value>),
+    Err(Box<Value>),
+    Some(Box<Value>),
     None,
 }
 
+impl Value {
+    pub fn vec(values: Vec<Value>) -> Value {
+        Value::Vec(Rc::new(RefCell::new(values)))
+    }
+}
+
+thread_local! {
+    // the vectors being printed, so a vector that contains itself prints as [...]
+    static PRINTING: RefCell<Vec<*const RefCell<Vec<Value>>>> = const { RefCell::new(Vec::new()) };
+}
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -514,7 +702,11 @@ impl fmt::Display for Value {
             Value::Number(i) => write!(f, "{}", i),
             Value::Bool(b) => write!(f, "{}", b),
             Value::Vec(v) => {
-                let mut vec = v.clone();
+                if PRINTING.with(|p| p.borrow().contains(&Rc::as_ptr(v))) {
+                    return write!(f, "[...]");
+                }
+                PRINTING.with(|p| p.borrow_mut().push(Rc::as_ptr(v)));
+                let mut vec = v.borrow().clone();
                 write!(f, "[");
                 let x = vec.pop();
                 for i in vec {
@@ -536,19 +728,106 @@ impl fmt::Display for Value {
                     },
                     None => Ok(()),
                 };
+                PRINTING.with(|p| p.borrow_mut().pop());
                 write!(f, "]")
             }
+            Value::Range {
+                start,
+                end,
+                step,
+                inclusive,
+            } => {
+                write!(f, "{start}{}{end}", if *inclusive { "..=" } else { ".." })?;
+                if *step != if end < start { -1.0 } else { 1.0 } {
+                    write!(f, "..{step}")?;
+                }
+                Ok(())
+            }
+            Value::Error { message, .. } => write!(f, "{}", message),
+            Value::Iterator(_) => write!(f, "iterator"),
+            Value::Ok(x) => write!(f, "Ok({})", x),
+            Value::Err(x) => write!(f, "Err({})", x),
+            Value::Some(x) => write!(f, "Some({})", x),
             Value::None => write!(f, "nada"),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Type {
+    Int,
+    Float,
+    String,
+    Bool,
+}
+
+impl Type {
+    pub fn matches(&self, value: &Value) -> bool {
+        match (self, value) {
+            (Type::Int, Value::Number(x)) => x.fract() == 0.0,
+            (Type::Float, Value::Number(_))
+            | (Type::String, Value::String { .. })
+            | (Type::Bool, Value::Bool(_)) => true,
+            _ => false,
+        }
+    }
+    pub fn name(value: &Value) -> &'static str {
+        //the name of the type of a value, as used in error messages
+        match value {
+            Value::Number(x) if x.fract() == 0.0 => "int",
+            Value::Number(_) => "float",
+            Value::String { .. } => "string",
+            Value::Bool(_) => "bool",
+            Value::Vec(_) => "vector",
+            Value::Range { .. } => "range",
+            Value::Iterator(_) => "iterator",
+            Value::Error { .. } => "error",
+            Value::Ok(_) | Value::Err(_) => "result",
+            Value::Some(_) => "option",
+            Value::None => "nada",
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::String => write!(f, "string"),
+            Type::Bool => write!(f, "bool"),
+        }
+    }
+}
+
+// the characters of the source, counting the bytes taken so tokens know where they are
+#[derive(Debug, Clone)]
+pub struct Cursor<'a> {
+    chars: Peekable<Chars<'a>>,
+    pub offset: usize,
+}
+
+impl Iterator for Cursor<'_> {
+    type Item = char;
+    fn next(&mut self) -> Option<char> {
+        let ch = self.chars.next()?;
+        self.offset += ch.len_utf8();
+        Some(ch)
+    }
+}
+
+impl Cursor<'_> {
+    pub fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub tt: TokenType,
     pub lexeme: String,
     pub literal: Option<Value>,
-    pub line: usize,
+    pub span: Span,
 }
 
 impl fmt::Display for Token {
@@ -556,7 +835,170 @@ impl fmt::Display for Token {
         write!(
             f,
             "Token: {}, Lexeme: {}, Literal: {:?}, Line: {}",
-            self.tt, self.lexeme, self.literal, self.line
+            self.tt, self.lexeme, self.literal, self.span.line
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(source: &str) -> Result<Vec<Token>, Vec<Diagnostic>> {
+        Scanner::new(source).scan_tokens()
+    }
+
+    // the value of a source that is a single number
+    fn number(source: &str) -> f64 {
+        let tokens = scan(source).unwrap();
+        assert_eq!(tokens.len(), 2, "{source} should be one token");
+        match tokens[0].literal {
+            Some(Value::Number(x)) => x,
+            _ => panic!("{source} is not a number"),
+        }
+    }
+
+    fn error(source: &str) -> u16 {
+        let errors = scan(source).unwrap_err();
+        errors[0].code.unwrap()
+    }
+
+    fn types(source: &str) -> Vec<TokenType> {
+        scan(source).unwrap().iter().map(|t| t.tt).collect()
+    }
+
+    #[test]
+    fn decimal() {
+        assert_eq!(number("42"), 42.0);
+        assert_eq!(number("3.25"), 3.25);
+        assert_eq!(types("42"), vec![TokenType::Int, TokenType::Eof]);
+        assert_eq!(types("3.25"), vec![TokenType::Float, TokenType::Eof]);
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(number("0xFF"), 255.0);
+        assert_eq!(number("0xff"), 255.0);
+        assert_eq!(number("0x0"), 0.0);
+        assert_eq!(types("0x10"), vec![TokenType::Int, TokenType::Eof]);
+        assert_eq!(error("0x"), messages::INVALID_NUMBER.code);
+        assert_eq!(error("0xFG"), messages::INVALID_NUMBER.code);
+    }
+
+    #[test]
+    fn binary() {
+        assert_eq!(number("0b1010"), 10.0);
+        assert_eq!(number("0b0"), 0.0);
+        assert_eq!(error("0b102"), messages::INVALID_NUMBER.code);
+        assert_eq!(error("0b"), messages::INVALID_NUMBER.code);
+    }
+
+    #[test]
+    fn octal() {
+        assert_eq!(number("0o17"), 15.0);
+        assert_eq!(number("0o777"), 511.0);
+        assert_eq!(error("0o8"), messages::INVALID_NUMBER.code);
+    }
+
+    #[test]
+    fn separators() {
+        assert_eq!(number("1_000_000"), 1_000_000.0);
+        assert_eq!(number("0xFF_FF"), 65535.0);
+        assert_eq!(number("0b1010_1010"), 170.0);
+        assert_eq!(number("1_000.000_5"), 1000.0005);
+        assert_eq!(error("1_"), messages::INVALID_NUMBER.code);
+        assert_eq!(error("1__0"), messages::INVALID_NUMBER.code);
+        assert_eq!(error("1_.5"), messages::INVALID_NUMBER.code);
+        assert_eq!(error("0x_FF"), messages::INVALID_NUMBER.code);
+    }
+
+    #[test]
+    fn scientific() {
+        assert_eq!(number("1.5e-3"), 0.0015);
+        assert_eq!(number("2e10"), 2e10);
+        assert_eq!(number("2E3"), 2000.0);
+        assert_eq!(number("1e+2"), 100.0);
+        assert_eq!(types("1e3"), vec![TokenType::Float, TokenType::Eof]);
+        assert_eq!(error("1e"), messages::INVALID_NUMBER.code);
+        assert_eq!(error("1.5e-"), messages::INVALID_NUMBER.code);
+        assert_eq!(number("1e1_0"), 1e10);
+        assert_eq!(error("1e_5"), messages::INVALID_NUMBER.code);
+        assert_eq!(error("1e+_5"), messages::INVALID_NUMBER.code);
+        assert_eq!(error("1E-_5"), messages::INVALID_NUMBER.code);
+        assert_eq!(error("1_e5"), messages::INVALID_NUMBER.code);
+    }
+
+    #[test]
+    fn dots() {
+        assert_eq!(error(".5"), messages::LEADING_DOT.code);
+        assert_eq!(
+            scan(".5").unwrap_err()[0].message,
+            "a number cannot start with a dot, write 0.5"
+        );
+        assert_eq!(error("1."), messages::TRAILING_DOT.code);
+    }
+
+    #[test]
+    fn too_big() {
+        assert_eq!(error("99999999999999999999"), messages::INVALID_NUMBER.code);
+        assert_eq!(error("0xFFFFFFFFFFFFFFFFF"), messages::INVALID_NUMBER.code);
+    }
+
+    #[test]
+    fn ranges() {
+        let range = vec![
+            TokenType::Int,
+            TokenType::Range,
+            TokenType::Int,
+            TokenType::Eof,
+        ];
+        assert_eq!(types("1..10"), range);
+        assert_eq!(types("0x1..0b11"), range);
+        assert_eq!(types("1_0..2_0"), range);
+        assert_eq!(
+            types("1..=10"),
+            vec![
+                TokenType::Int,
+                TokenType::RangeInclusive,
+                TokenType::Int,
+                TokenType::Eof
+            ]
+        );
+        assert_eq!(
+            types("1.5..3"),
+            vec![
+                TokenType::Float,
+                TokenType::Range,
+                TokenType::Int,
+                TokenType::Eof
+            ]
+        );
+        let tokens = scan("1..10").unwrap();
+        assert!(matches!(tokens[0].literal, Some(Value::Number(x)) if x == 1.0));
+        assert!(matches!(tokens[2].literal, Some(Value::Number(x)) if x == 10.0));
+        assert_eq!((tokens[0].span.start, tokens[0].span.end), (0, 1));
+        assert_eq!((tokens[1].span.start, tokens[1].span.end), (1, 3));
+    }
+
+    #[test]
+    fn trivia_keeps_comments() {
+        let source = "let x = 1; // one\n/* two /* nested */\nlines */ let y = 2;";
+        let comments = |tokens: Vec<Token>| -> Vec<String> {
+            tokens
+                .into_iter()
+                .filter(|t| t.tt == TokenType::Comment)
+                .map(|t| t.lexeme)
+                .collect()
+        };
+        assert!(comments(scan(source).unwrap()).is_empty());
+        let tokens = Scanner::new(source).trivia(true).scan_tokens().unwrap();
+        //the spans point at the comments in the source, so a formatter can write them back
+        for t in tokens.iter().filter(|t| t.tt == TokenType::Comment) {
+            assert_eq!(&source[t.span.start..t.span.end], t.lexeme);
+        }
+        assert_eq!(
+            comments(tokens),
+            vec!["// one", "/* two /* nested */\nlines */"]
+        );
+    }
+}
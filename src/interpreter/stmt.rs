@@ -1,37 +1,126 @@
 use super::{
     expr::*,
-    scanner::{Token, Value},
+    scanner::{Token, Type, Value},
 };
+use crate::error::Span;
 #[derive(Clone, Debug)]
 pub enum Stmt {
-    Print(Value, usize),
-    Block(Vec<Stmt>, (usize, usize)),
+    Print(Value, Span),
+    Block(Vec<Stmt>, (Span, Span)), // the { and the }
     Expression(Expr),
     If {
         condition: Expr,
         block: Box<Stmt>,
         elseblock: Option<Box<Stmt>>,
-        lines: (usize, Option<usize>),
+        spans: (Span, Option<Span>), // the if and the else
     },
     Var {
-        name: Token,
+        pattern: Pattern,
         value: Option<Expr>,
+        doc: Option<String>, // the /// comment right before it
+    },
+    Const {
+        name: Token,
+        ty: Option<Type>,
+        value: Expr,
+        doc: Option<String>,
     },
     While {
         condition: Expr,
         block: Box<Stmt>,
-        line: usize,
+        span: Span,
     },
     For {
-        identifier: Token,
+        pattern: Pattern,
         iterable: Expr,
         block: Box<Stmt>,
-        line: usize,
+        span: Span,
     },
     Fn {
         name: Token,
-        params: Vec<Token>,
+        params: Vec<Param>,
+        ret: Option<Type>, // fn f() -> int
         body: Box<Stmt>,
+        generator: bool, // fn* f() yields its values one at a time
+        doc: Option<String>,
+    },
+    Return(Option<Expr>, Span),
+    Yield(Expr, Span),
+    Try {
+        block: Box<Stmt>,
+        name: Token, // catch e
+        catch: Box<Stmt>,
+    },
+    Throw(Expr, Span),
+    Import(Token), // import "utils.kl";
+    Use {
+        module: Token,
+        names: Vec<Token>,
+    }, // use utils::{f, g};
+}
+
+#[derive(Clone, Debug)]
+pub struct Param {
+    pub name: Token,
+    pub mutable: bool,
+    pub ty: Option<Type>,
+    pub default: Option<Expr>, // fn f(a, b = 2)
+    pub rest: bool,            // fn f(a, ...rest)
+}
+
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    Identifier {
+        name: Token,
+        mutable: bool,    // let mut x
+        ty: Option<Type>, // let x: int
     },
-    Return(Option<Expr>, usize),
+    Vec {
+        elements: Vec<Pattern>,
+        rest: Option<Token>, // [head, ...tail]
+        span: Span,
+    },
+}
+
+impl Pattern {
+    pub fn span(&self) -> Span {
+        match self {
+            Pattern::Identifier { name, .. } => name.span,
+            Pattern::Vec { span, .. } => *span,
+        }
+    }
+    pub fn names(&self) -> Vec<String> {
+        //the names the pattern declares
+        match self {
+            Pattern::Identifier { name, .. } => vec![name.lexeme.clone()],
+            Pattern::Vec { elements, rest, .. } => elements
+                .iter()
+                .flat_map(Pattern::names)
+                .chain(rest.iter().map(|rest| rest.lexeme.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        //where the statement starts, for diagnostics about the whole statement
+        match self {
+            Stmt::Print(_, span)
+            | Stmt::Block(_, (span, _))
+            | Stmt::If {
+                spans: (span, _), ..
+            }
+            | Stmt::While { span, .. }
+            | Stmt::For { span, .. }
+            | Stmt::Return(_, span)
+            | Stmt::Yield(_, span)
+            | Stmt::Throw(_, span) => *span,
+            Stmt::Expression(expr) => expr.span(),
+            Stmt::Var { pattern, .. } => pattern.span(),
+            Stmt::Const { name, .. } | Stmt::Fn { name, .. } | Stmt::Import(name) => name.span,
+            Stmt::Try { block, .. } => block.span(),
+            Stmt::Use { module, .. } => module.span,
+        }
+    }
 }
@@ -1,12 +1,58 @@
-use error::KlangError;
+use error::{Diagnostic, Severity};
 mod compiling;
-use compiling::{compiler, vm};
+use compiling::native::NativeRegistry;
+use compiling::{checker, compiler, lint, vm};
 mod error;
 mod interpreter;
+mod messages;
+mod render;
+use interpreter::modules::{self, FileLoader, Loader, MemoryLoader};
+use interpreter::stmt::{Pattern, Stmt};
 use interpreter::{parser, scanner};
 #[macro_use]
 extern crate rocket;
+use rocket::fairing::AdHoc;
+use rocket::http::Header;
 use rocket::response::content::RawHtml;
+use rocket::State;
+use std::collections::HashSet;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// the playground runs programs for strangers, so they get a budget
+const FUEL: u64 = 10_000_000;
+const TIMEOUT: Duration = Duration::from_secs(5);
+const MEMORY: usize = 64 * 1024 * 1024;
+
+// what running a program gave back: its output or its errors, the warnings about it,
+// and the most memory it used
+struct Run {
+    output: String,
+    warnings: String,
+    peak: usize,
+    errors: usize, // how many diagnostics the output is made of
+    warned: usize, // how many diagnostics the warnings are made of
+}
+
+impl Run {
+    fn exit_code(&self) -> ExitCode {
+        //warnings alone still let the program succeed
+        match self.errors {
+            0 => ExitCode::SUCCESS,
+            _ => ExitCode::FAILURE,
+        }
+    }
+}
+
+#[derive(Responder)]
+struct Response {
+    output: String,
+    peak: Header<'static>,
+    errors: Header<'static>,
+    warnings: Header<'static>,
+}
 
 #[get("/")]
 fn index() -> RawHtml<&'static str> {
@@ -18,57 +64,402 @@ fn info() -> RawHtml<&'static str> {
     RawHtml(include_str!("info.html"))
 }
 
-#[launch]
-async fn rocket() -> _ {
-    rocket::build().mount("/", routes![index, run, info]).into()
+#[rocket::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut args = args.into_iter().peekable();
+    let mut limits = vm::Limits::default();
+    let mut max_errors = parser::MAX_ERRORS;
+    let mut report = false;
+    //klplayground [--fuel n] [--timeout seconds] [--memory bytes] [--depth calls] [--max-errors n] [--playful] file.kl args... runs the file instead of serving the playground
+    //klplayground --explain K0201 explains an error
+    //klplayground --doc file.kl lists what the file declares, with its doc comments
+    while let Some(flag) = args.next_if(|arg| arg.starts_with("--")) {
+        if flag == "--playful" {
+            messages::PLAYFUL.store(true, Ordering::Relaxed);
+            continue;
+        }
+        let value = args.next().unwrap_or_default();
+        match flag.as_str() {
+            "--explain" => {
+                return match messages::find(&value) {
+                    Some(message) => {
+                        println!("{}", message.explain());
+                        ExitCode::SUCCESS
+                    }
+                    None => bad_flag(format!("there is no error {value}")),
+                }
+            }
+            "--doc" => {
+                return match document(&value) {
+                    Ok(doc) => {
+                        print!("{doc}");
+                        ExitCode::SUCCESS
+                    }
+                    Err(errors) => {
+                        print!("{errors}");
+                        ExitCode::FAILURE
+                    }
+                }
+            }
+            "--fuel" => match value.parse() {
+                Ok(fuel) => limits.fuel = Some(fuel),
+                Err(_) => {
+                    return bad_flag(format!(
+                        "--fuel expects a whole number but found \"{value}\""
+                    ))
+                }
+            },
+            "--timeout" => match value
+                .parse()
+                .ok()
+                .and_then(|s| Duration::try_from_secs_f64(s).ok())
+            {
+                Some(time) => limits.time = Some(time),
+                None => {
+                    return bad_flag(format!(
+                        "--timeout expects a number of seconds but found \"{value}\""
+                    ))
+                }
+            },
+            "--memory" => match value.parse() {
+                Ok(memory) => {
+                    limits.memory = Some(memory);
+                    report = true;
+                }
+                Err(_) => {
+                    return bad_flag(format!(
+                        "--memory expects a number of bytes but found \"{value}\""
+                    ))
+                }
+            },
+            "--depth" => match value.parse() {
+                Ok(depth) if depth > 0 => limits.depth = depth,
+                _ => {
+                    return bad_flag(format!(
+                        "--depth expects a whole number above 0 but found \"{value}\""
+                    ))
+                }
+            },
+            "--max-errors" => match value.parse() {
+                Ok(max) if max > 0 => max_errors = max,
+                _ => {
+                    return bad_flag(format!(
+                        "--max-errors expects a whole number above 0 but found \"{value}\""
+                    ))
+                }
+            },
+            _ => {
+                return bad_flag(format!(
+                    "unknown flag {flag}, the flags are --fuel, --timeout, --memory, --depth, --max-errors, --playful, --explain and --doc"
+                ))
+            }
+        }
+    }
+    if let Some(path) = args.next() {
+        let path = std::path::Path::new(&path);
+        let loader = FileLoader {
+            root: path.parent().unwrap_or(path).to_path_buf(),
+        };
+        let main = path.file_name().unwrap_or_default().to_string_lossy();
+        let mut natives = NativeRegistry::standard();
+        let args: Vec<scanner::Value> = args
+            .map(|string| scanner::Value::String {
+                string,
+                printables: vec![],
+            })
+            .collect();
+        natives.register("io::args", 0, Some(0), vec![], move |_, _| {
+            Ok(scanner::Value::vec(args.clone()))
+        });
+        let run = execute(
+            &main,
+            &loader,
+            natives,
+            limits,
+            max_errors,
+            render::Mode::Plain,
+        );
+        eprint!("{}", run.warnings);
+        print!("{}", run.output);
+        if report {
+            eprintln!("\npeak memory: {}", vm::bytes_to_string(run.peak));
+        }
+        return run.exit_code();
+    }
+    let cancel = Arc::new(AtomicBool::new(false));
+    if let Err(err) = rocket::build()
+        .manage(cancel.clone())
+        .attach(AdHoc::on_shutdown("cancel programs", |_| {
+            //programs still running would hold up the shutdown until they time out
+            Box::pin(async move { cancel.store(true, Ordering::Relaxed) })
+        }))
+        .mount("/", routes![index, run, info])
+        .launch()
+        .await
+    {
+        eprintln!("{err}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+// a flag the command line could not use, which stops it before it runs anything
+fn bad_flag(message: String) -> ExitCode {
+    eprintln!("{message}");
+    ExitCode::FAILURE
 }
 
 #[post("/", data = "<source>")]
-fn run(source: String) -> String {
-    let mut scanner = scanner::Scanner::new(&source);
-    let tokens = match scanner.scan_tokens() {
-        Ok(t) => t,
-        Err(err) => return err,
+async fn run(source: String, cancel: &State<Arc<AtomicBool>>) -> Response {
+    let limits = vm::Limits {
+        fuel: Some(FUEL),
+        time: Some(TIMEOUT),
+        cancel: Arc::clone(cancel),
+        memory: Some(MEMORY),
+        depth: vm::DEPTH,
     };
-    let mut parser = parser::Parser::new(tokens);
-    let ast = match parser.parse() {
+    //programs run on their own thread so they dont block the server while they run
+    let program = rocket::tokio::task::spawn_blocking(move || {
+        let mut natives = NativeRegistry::standard();
+        natives.disable("io"); //the server has no one to read input from
+        execute(
+            MemoryLoader::MAIN,
+            &MemoryLoader::split(&source),
+            natives,
+            limits,
+            parser::MAX_ERRORS,
+            render::Mode::Html,
+        )
+    });
+    let run = program.await.unwrap_or_else(|err| Run {
+        output: err.to_string(),
+        warnings: String::new(),
+        peak: 0,
+        errors: 1,
+        warned: 0,
+    });
+    //everything comes as html, what the program printed is escaped and put after the warnings
+    let output = match run.errors {
+        0 => run.warnings + &render::text(&run.output, render::Mode::Html),
+        _ => run.output,
+    };
+    Response {
+        output,
+        peak: Header::new("X-Peak-Memory", run.peak.to_string()),
+        errors: Header::new("X-Errors", run.errors.to_string()),
+        warnings: Header::new("X-Warnings", run.warned.to_string()),
+    }
+}
+
+fn execute(
+    main: &str,
+    loader: &dyn Loader,
+    natives: NativeRegistry,
+    limits: vm::Limits,
+    max_errors: usize,
+    mode: render::Mode,
+) -> Run {
+    //warnings come before the errors of the run, they might be why it failed
+    let failed = |errors: Vec<Diagnostic>, sources: &modules::SourceMap| Run {
+        output: render::render(&errors, sources, mode),
+        warnings: String::new(),
+        peak: 0,
+        errors: errors
+            .iter()
+            .filter(|e| e.severity == Severity::Error)
+            .count(),
+        warned: errors
+            .iter()
+            .filter(|e| e.severity == Severity::Warning)
+            .count(),
+    };
+    let (modules, sources) = match modules::link(main, loader, max_errors) {
         Ok(t) => t,
+        Err((errors, sources)) => return failed(errors, &sources),
+    };
+    let declared: HashSet<String> = modules
+        .iter()
+        .flat_map(|m| m.declared.iter().cloned())
+        .collect();
+    for (i, module) in modules.iter().enumerate() {
+        let imported: Vec<modules::ReadOnly> = modules
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .flat_map(|(_, m)| m.read_only.iter().cloned())
+            .collect();
+        if let Err(errors) = checker::check(&module.stmts, &module.directives, &imported, &declared)
+        {
+            return failed(errors, &sources);
+        }
+    }
+    let mut warnings = lint::lint(&modules);
+    if warnings.iter().any(|w| w.severity == Severity::Error) {
+        return failed(warnings, &sources);
+    }
+    let ast = modules.into_iter().flat_map(|m| m.stmts).collect();
+    let mut chunk = compiler::Chunk::new(compiler::compile(ast));
+    if let Err(errors) = compiler::resolve(&mut chunk, &natives) {
+        return failed(errors, &sources);
+    }
+    let mut vm = vm::VM::new(chunk, natives);
+    vm.sources = sources.clone();
+    vm.limit(limits);
+    let result = run_vm(&mut vm);
+    vm.measure();
+    match result {
+        Ok(output) => {
+            let warned = failed(warnings, &sources);
+            Run {
+                output,
+                warnings: warned.output + if warned.warned > 0 { "\n" } else { "" },
+                peak: vm.peak,
+                ..warned
+            }
+        }
         Err(err) => {
-            return err;
+            warnings.push(err);
+            Run {
+                peak: vm.peak,
+                ..failed(warnings, &sources)
+            }
+        }
+    }
+}
+
+// the functions, variables and constants at the top of a file, each with its /// comment under it
+fn document(path: &str) -> Result<String, String> {
+    let path = std::path::Path::new(path);
+    let loader = FileLoader {
+        root: path.parent().unwrap_or(path).to_path_buf(),
+    };
+    let main = path.file_name().unwrap_or_default().to_string_lossy();
+    let modules = match modules::link(&main, &loader, parser::MAX_ERRORS) {
+        Ok((modules, _)) => modules,
+        Err((errors, sources)) => {
+            return Err(render::render(&errors, &sources, render::Mode::Plain))
         }
     };
-    let chunk = compiler::Chunk::new(compiler::compile(ast));
-    let vm = vm::VM::new(chunk);
-    match run_vm(vm) {
-        Ok(s) => s,
-        Err(s) => s,
+    let mut out = String::new();
+    //the main file comes last, after what it imports
+    for stmt in modules.last().map_or(&[][..], |m| &m.stmts) {
+        let (item, doc) = match stmt {
+            Stmt::Fn {
+                name,
+                params,
+                ret,
+                generator,
+                doc,
+                ..
+            } => {
+                let params: Vec<String> = params
+                    .iter()
+                    .map(|p| {
+                        let rest = if p.rest { "..." } else { "" };
+                        let mutable = if p.mutable { "mut " } else { "" };
+                        let ty = p.ty.map(|ty| format!(": {ty}")).unwrap_or_default();
+                        let default = if p.default.is_some() { " = ..." } else { "" };
+                        format!("{rest}{mutable}{}{ty}{default}", p.name.lexeme)
+                    })
+                    .collect();
+                let star = if *generator { "*" } else { "" };
+                let ret = ret.map(|ty| format!(" -> {ty}")).unwrap_or_default();
+                let item = format!("fn{star} {}({}){ret}", name.lexeme, params.join(", "));
+                (item, doc)
+            }
+            Stmt::Var { pattern, doc, .. } => (format!("let {}", signature(pattern)), doc),
+            Stmt::Const { name, ty, doc, .. } => {
+                let ty = ty.map(|ty| format!(": {ty}")).unwrap_or_default();
+                (format!("const {}{ty}", name.lexeme), doc)
+            }
+            _ => continue,
+        };
+        out += &format!("{item}\n");
+        for line in doc.iter().flat_map(|doc| doc.lines()) {
+            out += &format!("    {line}\n");
+        }
     }
+    Ok(out)
 }
 
-fn run_vm(mut vm: vm::VM) -> Result<String, String> {
+// a pattern as it is written after let, like mut x: int or [a, ...rest]
+fn signature(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::Identifier { name, mutable, ty } => {
+            let mutable = if *mutable { "mut " } else { "" };
+            let ty = ty.map(|ty| format!(": {ty}")).unwrap_or_default();
+            format!("{mutable}{}{ty}", name.lexeme)
+        }
+        Pattern::Vec { elements, rest, .. } => {
+            let elements = elements.iter().map(signature);
+            let rest = rest.iter().map(|rest| format!("...{}", rest.lexeme));
+            format!("[{}]", elements.chain(rest).collect::<Vec<_>>().join(", "))
+        }
+    }
+}
+
+fn run_vm(vm: &mut vm::VM) -> Result<String, Diagnostic> {
     let mut output = String::new();
-    let mut jumps = 0;
 
     while vm.index < vm.chunk.code.len() as i32 {
-        match vm.once(&mut jumps) {
-            Ok(s) => output.push_str(&s),
-            Err(s) => {
-                return Err(error::KlangError::error(
-                    KlangError::RuntimeError,
-                    s.as_str(),
-                    0,
-                ))
-            }
-        }
+        output.push_str(&vm.once().map_err(|err| vm.uncaught(err))?);
         vm.index += 1;
-        if jumps > 10000 {
-            return Err(error::KlangError::error(
-                KlangError::RuntimeError,
-                "infinite loop detected",
-                0,
-            ));
-        }
     }
 
     Ok(output)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_source(source: &str, max_errors: usize) -> Run {
+        execute(
+            MemoryLoader::MAIN,
+            &MemoryLoader::split(source),
+            NativeRegistry::standard(),
+            vm::Limits::default(),
+            max_errors,
+            render::Mode::Plain,
+        )
+    }
+
+    #[test]
+    fn max_errors_caps_what_is_reported() {
+        let source = "let = 1;\n".repeat(4);
+        let run = run_source(&source, 2);
+        assert_eq!(run.errors, 2);
+        assert!(run
+            .output
+            .ends_with("= note: stopped after 2 errors, there may be more\n"));
+        let run = run_source(&source, parser::MAX_ERRORS);
+        assert_eq!(run.errors, 4);
+        assert!(!run.output.contains("stopped after"));
+    }
+
+    #[test]
+    fn deep_recursion_is_traced_in_a_few_lines() {
+        let source =
+            "fn down(n) {\n    if n == 0 { return 1 / 0; }\n    return down(n - 1);\n}\ndown(500);";
+        let run = run_source(source, parser::MAX_ERRORS);
+        assert_eq!(run.errors, 1);
+        assert!(run.output.ends_with(
+            "  = calls, innermost first:\n      \
+            down at main.kl:2:24\n      \
+            down at main.kl:3:12 (and 499 more times)\n      \
+            top level at main.kl:5:1\n"
+        ));
+    }
+
+    #[test]
+    fn only_errors_fail_the_run() {
+        let run = run_source("let unused = 1;", parser::MAX_ERRORS);
+        assert_eq!(run.warned, 1);
+        assert_eq!(run.exit_code(), ExitCode::SUCCESS);
+        let run = run_source("let x = 1 / 0;", parser::MAX_ERRORS);
+        assert_eq!(run.exit_code(), ExitCode::FAILURE);
+        let run = run_source("let = 1;", parser::MAX_ERRORS);
+        assert_eq!(run.exit_code(), ExitCode::FAILURE);
+    }
+}
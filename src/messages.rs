@@ -0,0 +1,875 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// set by --playful, for the messages klang was first written with
+pub static PLAYFUL: AtomicBool = AtomicBool::new(false);
+
+// a diagnostic klang can report: its code, what it says, and the long explanation --explain prints.
+// {0}, {1}... in the text are replaced by the arguments the error is made with
+pub struct Message {
+    pub code: u16,
+    pub text: &'static str,
+    pub playful: Option<&'static str>,
+    pub explanation: &'static str,
+}
+
+impl Message {
+    pub fn text(&self, args: &[&str]) -> String {
+        match self.playful {
+            Some(playful) if PLAYFUL.load(Ordering::Relaxed) => fill(playful, args),
+            _ => fill(self.text, args),
+        }
+    }
+    pub fn explain(&self) -> String {
+        format!("K{:04}: {}\n\n{}", self.code, self.text, self.explanation)
+    }
+}
+
+// a help line under a diagnostic, filled in like the text of a message
+pub struct Help(pub &'static str);
+
+impl Help {
+    pub fn text(&self, args: &[&str]) -> String {
+        fill(self.0, args)
+    }
+}
+
+pub static DID_YOU_MEAN: Help = Help("did you mean \"{0}\"?");
+pub static OWN_FUNCTION: Help = Help("\"{0}\" is your own function, call it without std::");
+pub static CALL_NATIVE: Help = Help("to call the native function, write std::{0}");
+
+fn fill(template: &str, args: &[&str]) -> String {
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        text += &rest[..open];
+        rest = &rest[open..];
+        let arg = rest
+            .find('}')
+            .and_then(|close| Some((close, args.get(rest[1..close].parse::<usize>().ok()?)?)));
+        match arg {
+            Some((close, arg)) => {
+                text += arg;
+                rest = &rest[close + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    text + rest
+}
+
+// the message with a code, written like K0101, k0101 or just 101
+pub fn find(code: &str) -> Option<&'static Message> {
+    let code: u16 = code.trim_start_matches(['K', 'k']).parse().ok()?;
+    MESSAGES
+        .iter()
+        .copied()
+        .find(|message| message.code == code)
+}
+
+pub static MESSAGES: &[&Message] = &[
+    &UNEXPECTED_CHARACTER,
+    &REPEATED_MINUS,
+    &REPEATED_BANG,
+    &SINGLE_AMPERSAND,
+    &SINGLE_PIPE,
+    &STD_WITHOUT_CALL,
+    &INVALID_NUMBER,
+    &TRAILING_DOT,
+    &UNTERMINATED_STRING,
+    &EMPTY_INTERPOLATION,
+    &UNTERMINATED_COMMENT,
+    &LEADING_DOT,
+    &MISSING_SEMICOLON,
+    &EXPECTED,
+    &EXPECTED_NAME,
+    &EXPECTED_VALUE,
+    &EXPECTED_TYPE,
+    &TYPE_AS_VALUE,
+    &PRINT_NOT_STRING,
+    &INVALID_ASSIGNMENT,
+    &CALL_ON_NUMBER,
+    &STRING_IN_INTERPOLATION,
+    &REST_NOT_LAST,
+    &REST_WITH_TYPE,
+    &DUPLICATE_PARAMETER,
+    &MISSING_DEFAULT,
+    &DESTRUCTURE_WITHOUT_VALUE,
+    &NAMED_NATIVE_ARGUMENTS,
+    &DUPLICATE_ARGUMENT,
+    &POSITIONAL_AFTER_NAMED,
+    &LOAD_FAILED,
+    &DUPLICATE_IMPORT,
+    &IMPORT_CYCLE,
+    &NESTED_IMPORT,
+    &NESTED_USE,
+    &NOT_IMPORTED,
+    &NO_SUCH_ITEM,
+    &ASSIGN_TO_CONSTANT,
+    &REDECLARE_CONSTANT,
+    &ASSIGN_TO_IMMUTABLE,
+    &MISMATCHED_TYPES,
+    &RETURN_IN_GENERATOR,
+    &YIELD_OUTSIDE_GENERATOR,
+    &UNKNOWN_NATIVE,
+    &UNKNOWN_FUNCTION,
+    &UNKNOWN_VARIABLE,
+    &RECURSION_LIMIT,
+    &WRONG_ARGUMENT_COUNT,
+    &UNKNOWN_PARAMETER,
+    &PARAMETER_TWICE,
+    &RETURN_OUTSIDE_FUNCTION,
+    &INVALID_OPERANDS,
+    &DIVISION_BY_ZERO,
+    &MODULO_BY_ZERO,
+    &RANGE_NOT_NUMBER,
+    &RANGE_NOT_FINITE,
+    &RANGE_ZERO_STEP,
+    &NOT_ITERABLE,
+    &INVALID_ITERATOR,
+    &NOT_A_VECTOR,
+    &DESTRUCTURE_LENGTH,
+    &INVALID_UNWRAP,
+    &UNWRAP_OUTSIDE_FUNCTION,
+    &OUT_OF_FUEL,
+    &TIMED_OUT,
+    &OUT_OF_MEMORY,
+    &CANCELLED,
+    &THROWN,
+    &NATIVE_ARGUMENT_COUNT,
+    &NATIVE_ARGUMENT_TYPE,
+    &NATIVE_EXPECTED,
+    &INDEX_OUT_OF_BOUNDS,
+    &EMPTY_RANDOM_RANGE,
+    &NEGATIVE_SLEEP,
+    &FILTER_NOT_BOOL,
+    &UNWRAP_FAILED,
+    &NO_ITERATOR_FUNCTION,
+    &INPUT_FAILED,
+    &INTERNAL,
+    &UNUSED_VARIABLE,
+    &UNUSED_FUNCTION,
+    &UNREACHABLE,
+    &SHADOWED,
+    &UNDECLARED_ASSIGNMENT,
+    &CONSTANT_CONDITION,
+    &EMPTY_BLOCK,
+];
+
+// scanning
+
+pub static UNEXPECTED_CHARACTER: Message = Message {
+    code: 101,
+    text: "unexpected character",
+    playful: None,
+    explanation:
+        "The character is not part of any token Klang knows, like @ or $ outside of a string.\n\
+        Remove it, or put it inside a string if it was meant as text.",
+};
+
+pub static REPEATED_MINUS: Message = Message {
+    code: 102,
+    text: "a minus sign cannot follow another one",
+    playful: Some("we shall not allow minus spamming. use 1"),
+    explanation:
+        "Two minus signs in a row, like --x, are not allowed. Use one minus to negate a value,\n\
+        or wrap the inner one in parentheses: -(-x).",
+};
+
+pub static REPEATED_BANG: Message = Message {
+    code: 103,
+    text: "a ! cannot follow another one",
+    playful: Some("we shall not allow bang spamming. use 1"),
+    explanation:
+        "Two ! in a row, like !!x, are not allowed. A bool negated twice is the same bool,\n\
+        so use x, or !(!x) if you really mean it.",
+};
+
+pub static SINGLE_AMPERSAND: Message = Message {
+    code: 104,
+    text: "expected && but found a single &",
+    playful: Some("missing a second &"),
+    explanation:
+        "The logical AND operator is written with two ampersands: a && b. Klang has no bitwise &.",
+};
+
+pub static SINGLE_PIPE: Message = Message {
+    code: 105,
+    text: "expected || but found a single |",
+    playful: Some("missing a second |"),
+    explanation:
+        "The logical OR operator is written with two pipes: a || b. Klang has no bitwise |.",
+};
+
+pub static STD_WITHOUT_CALL: Message = Message {
+    code: 106,
+    text: "std must be followed by :: and the name of a native function",
+    playful: Some("cannot use std without calling a native fn"),
+    explanation: "std is the module of the native functions and cannot be used on its own.\n\
+        Call a native through it, like std::math::sqrt(2), or import some with use std::math;.",
+};
+
+pub static INVALID_NUMBER: Message = Message {
+    code: 107,
+    text: "{0} is not a valid number",
+    playful: None,
+    explanation: "The number could not be read, because it is too big to fit in an int (they go up to\n\
+        9223372036854775807), it has a digit its base does not have, like 0b102, or a _ that is not\n\
+        between two digits. Numbers are written 42, 1_000_000, 3.14, 1.5e-3, 0xff, 0o17 or 0b1010.",
+};
+
+pub static TRAILING_DOT: Message = Message {
+    code: 108,
+    text: "a float cannot end with a dot",
+    playful: Some("float cant end with a dot"),
+    explanation: "Floats need a digit after the dot: write 1.0 instead of 1.",
+};
+
+pub static UNTERMINATED_STRING: Message = Message {
+    code: 109,
+    text: "unterminated string",
+    playful: None,
+    explanation: "The string starts with \" but the file ends before the closing \".\n\
+        Add the closing quote where the string should end.",
+};
+
+pub static EMPTY_INTERPOLATION: Message = Message {
+    code: 110,
+    text: "{} inside a string must hold an expression",
+    playful: Some("cannot print an empty identifier"),
+    explanation: "Braces inside a string print the expression between them, like \"x is {x}\".\n\
+        Empty braces have nothing to print: put an expression inside them or remove them.",
+};
+
+pub static UNTERMINATED_COMMENT: Message = Message {
+    code: 111,
+    text: "this comment is never closed",
+    playful: None,
+    explanation:
+        "A block comment starts with /* and ends with */. Block comments nest, so every /*\n\
+        inside one needs its own */ too.",
+};
+
+pub static LEADING_DOT: Message = Message {
+    code: 112,
+    text: "a number cannot start with a dot, write {0}",
+    playful: None,
+    explanation: "Floats need a digit before the dot: write 0.5 instead of .5.",
+};
+
+// parsing
+
+pub static MISSING_SEMICOLON: Message = Message {
+    code: 201,
+    text: "missing ; at the end of the line",
+    playful: None,
+    explanation: "Every statement ends with a semicolon: let x = 1; print(\"{x}\");\n\
+        The error points at the token right after where the ; is missing, often on the next line.",
+};
+
+pub static EXPECTED: Message = Message {
+    code: 202,
+    text: "expected {0} {1}",
+    playful: None,
+    explanation: "The parser needed a specific token here, like the ) that closes a call or the { that\n\
+        starts a block, and found something else. The message tells which token and where it belongs.",
+};
+
+pub static EXPECTED_NAME: Message = Message {
+    code: 203,
+    text: "expected the name of {0}",
+    playful: None,
+    explanation:
+        "Variables, constants, functions, parameters and caught errors need a name here.\n\
+        Names start with a letter or _, and cannot be keywords like let or fn.",
+};
+
+pub static EXPECTED_VALUE: Message = Message {
+    code: 204,
+    text: "expected a value but found {0}",
+    playful: None,
+    explanation: "An expression was expected, like a number, a string, a variable or a call,\n\
+        but the parser found a token that cannot start one. Often something before it is missing,\n\
+        like the right side of an operator in let x = 1 +;.",
+};
+
+pub static EXPECTED_TYPE: Message = Message {
+    code: 205,
+    text: "expected a type (int, float, string or bool)",
+    playful: None,
+    explanation: "Type annotations, like let x: int = 1;, can only be int, float, string or bool.",
+};
+
+pub static TYPE_AS_VALUE: Message = Message {
+    code: 206,
+    text: "{0} is a type, not a value",
+    playful: None,
+    explanation:
+        "int, float, string and bool name types, and can only be used in annotations like\n\
+        let x: int = 1;. They cannot be used as values in expressions.",
+};
+
+pub static PRINT_NOT_STRING: Message = Message {
+    code: 207,
+    text: "print only takes a string",
+    playful: Some("can only print strings"),
+    explanation:
+        "print takes a string literal. To print other values, put them inside the string:\n\
+        print(\"{x}\"); instead of print(x);.",
+};
+
+pub static INVALID_ASSIGNMENT: Message = Message {
+    code: 208,
+    text: "can only assign to a variable",
+    playful: Some("cannot assign to a non variable"),
+    explanation: "The left side of = must be the name of a variable, like x = 1;.\n\
+        To change an element of a vector, use std::vec::set(vec, index, value).",
+};
+
+pub static CALL_ON_NUMBER: Message = Message {
+    code: 209,
+    text: "a number cannot be called like a function",
+    playful: Some("sir were you trying to call a function USING AN INTEGER?"),
+    explanation: "Parentheses right after a number, like 2(3), call it as if it were a function.\n\
+        Klang does not multiply implicitly: write 2 * (3).",
+};
+
+pub static STRING_IN_INTERPOLATION: Message = Message {
+    code: 210,
+    text: "a string cannot be used inside {} of another string",
+    playful: Some("why would you use a string inside a string??"),
+    explanation:
+        "The expressions inside the braces of a string cannot contain strings themselves.\n\
+        Store the inner string in a variable first and use the variable inside the braces.",
+};
+
+pub static REST_NOT_LAST: Message = Message {
+    code: 211,
+    text: "the rest parameter must be the last parameter",
+    playful: None,
+    explanation: "A rest parameter (...args) takes every argument left over, so no parameter can come after it.",
+};
+
+pub static REST_WITH_TYPE: Message = Message {
+    code: 212,
+    text: "the rest parameter cannot have a type",
+    playful: None,
+    explanation: "A rest parameter is always a vector of the extra arguments, so it takes no type annotation.",
+};
+
+pub static DUPLICATE_PARAMETER: Message = Message {
+    code: 213,
+    text: "parameter \"{0}\" is declared twice",
+    playful: None,
+    explanation: "Every parameter of a function needs its own name.",
+};
+
+pub static MISSING_DEFAULT: Message = Message {
+    code: 214,
+    text: "parameter \"{0}\" needs a default value since it comes after one",
+    playful: None,
+    explanation: "Once a parameter has a default value, the ones after it need one too,\n\
+        otherwise a call could not leave out the earlier one: fn f(a, b = 1, c = 2).",
+};
+
+pub static DESTRUCTURE_WITHOUT_VALUE: Message = Message {
+    code: 215,
+    text: "cannot destructure without a value",
+    playful: None,
+    explanation: "let [a, b]; has nothing to unpack. Give it a vector: let [a, b] = [1, 2];.",
+};
+
+pub static NAMED_NATIVE_ARGUMENTS: Message = Message {
+    code: 216,
+    text: "native functions do not take named arguments",
+    playful: None,
+    explanation:
+        "Only functions declared with fn take arguments by name. Pass the arguments of a\n\
+        native function in order.",
+};
+
+pub static DUPLICATE_ARGUMENT: Message = Message {
+    code: 217,
+    text: "argument \"{0}\" is given twice",
+    playful: None,
+    explanation: "A named argument can only be given once in a call.",
+};
+
+pub static POSITIONAL_AFTER_NAMED: Message = Message {
+    code: 218,
+    text: "positional arguments must come before named arguments",
+    playful: None,
+    explanation: "In a call, the arguments without names go first: f(1, 2, c = 3).",
+};
+
+// linking and checking, before the program runs
+
+pub static LOAD_FAILED: Message = Message {
+    code: 301,
+    text: "{0}",
+    playful: None,
+    explanation: "A file could not be loaded: the main file, or one imported with import \"file.kl\";.\n\
+        Imports are relative to the file that imports them. In the playground, more files are added\n\
+        after the main program with a line like // file: utils.kl.",
+};
+
+pub static DUPLICATE_IMPORT: Message = Message {
+    code: 302,
+    text: "a module named \"{0}\" is already imported",
+    playful: None,
+    explanation: "A module is named after its file, so two imports of files with the same name\n\
+        in different folders would both be called the same thing.",
+};
+
+pub static IMPORT_CYCLE: Message = Message {
+    code: 303,
+    text: "import cycle: {0}",
+    playful: None,
+    explanation: "Files import each other in a loop, so none of them can run first.\n\
+        Move what they share into a file that imports neither.",
+};
+
+pub static NESTED_IMPORT: Message = Message {
+    code: 304,
+    text: "imports must be at the top level of a file",
+    playful: None,
+    explanation: "import cannot be used inside a block or function. Put it at the top of the file.",
+};
+
+pub static NESTED_USE: Message = Message {
+    code: 305,
+    text: "use must be at the top level of a file",
+    playful: None,
+    explanation: "use cannot be used inside a block or function. Put it at the top of the file.",
+};
+
+pub static NOT_IMPORTED: Message = Message {
+    code: 306,
+    text: "module \"{0}\" is not imported",
+    playful: None,
+    explanation: "Names like utils::f need the module to be imported first: import \"utils.kl\";.",
+};
+
+pub static NO_SUCH_ITEM: Message = Message {
+    code: 307,
+    text: "module \"{0}\" has no item named \"{1}\"",
+    playful: None,
+    explanation:
+        "Only the functions, variables and constants declared at the top level of a module\n\
+        can be used from other files.",
+};
+
+pub static ASSIGN_TO_CONSTANT: Message = Message {
+    code: 308,
+    text: "cannot assign to constant \"{0}\"",
+    playful: None,
+    explanation:
+        "Constants keep the value they are declared with. Declare it with let instead of const\n\
+        if it needs to change.",
+};
+
+pub static REDECLARE_CONSTANT: Message = Message {
+    code: 309,
+    text: "cannot redeclare constant \"{0}\"",
+    playful: None,
+    explanation:
+        "A constant cannot be shadowed by another declaration with the same name, not even in\n\
+        an inner block. Pick another name for the new variable.",
+};
+
+pub static ASSIGN_TO_IMMUTABLE: Message = Message {
+    code: 310,
+    text: "cannot assign twice to immutable variable \"{0}\"",
+    playful: None,
+    explanation:
+        "With // klang:immutable at the top of a file, variables declared without mut can only\n\
+        be given a value once. Declare it with let mut to allow changing it.",
+};
+
+pub static MISMATCHED_TYPES: Message = Message {
+    code: 311,
+    text: "mismatched types: expected {0} for {1} but found {2}",
+    playful: None,
+    explanation:
+        "A value does not match the type it is annotated with, like let x: int = \"one\";.\n\
+        With // klang:typecheck this is checked before the program runs when the type is known,\n\
+        otherwise it is checked when the value is stored.",
+};
+
+pub static RETURN_IN_GENERATOR: Message = Message {
+    code: 312,
+    text: "generator \"{0}\" cannot return a value, yield it instead",
+    playful: None,
+    explanation:
+        "Generators (fn* name()) hand out their values with yield. return can end one early,\n\
+        but without a value.",
+};
+
+pub static YIELD_OUTSIDE_GENERATOR: Message = Message {
+    code: 313,
+    text: "yield can only be used inside a generator (fn* name() { ... })",
+    playful: None,
+    explanation:
+        "yield hands a value to the loop running a generator. Declare the function with fn*\n\
+        to make it a generator, or use return in a normal function.",
+};
+
+pub static UNKNOWN_NATIVE: Message = Message {
+    code: 314,
+    text: "there is no native function std::{0}",
+    playful: None,
+    explanation: "The native functions are listed on the info page, like std::math::sqrt or std::vec::push.\n\
+        In the playground, std::io is not available.",
+};
+
+pub static UNKNOWN_FUNCTION: Message = Message {
+    code: 315,
+    text: "there is no function \"{0}\"",
+    playful: Some("please call a real function next time"),
+    explanation:
+        "No function with this name is declared anywhere in the program. Check the spelling,\n\
+        or call a native function with its std:: path.",
+};
+
+// running
+
+pub static UNKNOWN_VARIABLE: Message = Message {
+    code: 401,
+    text: "variable \"{0}\" does not exist",
+    playful: Some("variable \"{0}\" do not exist"),
+    explanation: "The variable is not declared in any scope that is open where it is used.\n\
+        Declare it with let before using it, and check the spelling. A name that is not declared\n\
+        or assigned anywhere in the program is reported before the program runs.",
+};
+
+pub static RECURSION_LIMIT: Message = Message {
+    code: 402,
+    text: "too much recursion, more than {0} calls are running",
+    playful: Some("your recursion is {0} layers deep- bro thinks he can crash klang :skull:"),
+    explanation:
+        "Functions can only call each other so many levels deep, 1000 unless the command line\n\
+        sets it with --depth. A function that calls itself needs a case where it stops,\n\
+        like if n == 0 { return 1; }.",
+};
+
+pub static WRONG_ARGUMENT_COUNT: Message = Message {
+    code: 403,
+    text: "function \"{0}\" takes {1} but {2} given{3}",
+    playful: None,
+    explanation:
+        "A call gives a function more or fewer arguments than it has parameters. Parameters\n\
+        with default values can be left out, and a rest parameter (...args) takes any number more.",
+};
+
+pub static UNKNOWN_PARAMETER: Message = Message {
+    code: 404,
+    text: "function \"{0}\" has no parameter named \"{1}\"",
+    playful: None,
+    explanation:
+        "A named argument (name = value) must use the name of one of the function's parameters.",
+};
+
+pub static PARAMETER_TWICE: Message = Message {
+    code: 405,
+    text: "parameter \"{0}\" of function \"{1}\" was given twice",
+    playful: None,
+    explanation: "The call gives the parameter a value by position and again by name.",
+};
+
+pub static RETURN_OUTSIDE_FUNCTION: Message = Message {
+    code: 406,
+    text: "cannot return outside of a function",
+    playful: None,
+    explanation:
+        "return ends a function and gives back its value, so it can only be used inside one.",
+};
+
+pub static INVALID_OPERANDS: Message = Message {
+    code: 407,
+    text: "can only {0}",
+    playful: None,
+    explanation: "An operator was used on values it does not work on, like 1 + true.\n\
+        Arithmetic and comparisons take numbers, && || and ! take bools.",
+};
+
+pub static DIVISION_BY_ZERO: Message = Message {
+    code: 408,
+    text: "division by zero",
+    playful: None,
+    explanation: "A number was divided by 0. Check the divisor before dividing.",
+};
+
+pub static MODULO_BY_ZERO: Message = Message {
+    code: 409,
+    text: "modulo by zero",
+    playful: Some("no modulo by zero"),
+    explanation: "The remainder of a division by 0 does not exist. Check the divisor first.",
+};
+
+pub static RANGE_NOT_NUMBER: Message = Message {
+    code: 410,
+    text: "the {0} of a range must be a number",
+    playful: Some("{0} is not a number"),
+    explanation: "Ranges are made of numbers: 0..10, or 0..10..2 with a step.",
+};
+
+pub static RANGE_NOT_FINITE: Message = Message {
+    code: 411,
+    text: "a range must start, end and step by finite numbers",
+    playful: None,
+    explanation:
+        "A range cannot start, end or step at infinity or at a number that is not a number.",
+};
+
+pub static RANGE_ZERO_STEP: Message = Message {
+    code: 412,
+    text: "the step of a range cannot be zero",
+    playful: None,
+    explanation: "A range with a step of 0 would never reach its end.",
+};
+
+pub static NOT_ITERABLE: Message = Message {
+    code: 413,
+    text: "cannot loop over {0}, it is not iterable",
+    playful: None,
+    explanation: "for loops go over vectors, ranges, strings and iterators, not over other values.",
+};
+
+pub static INVALID_ITERATOR: Message = Message {
+    code: 414,
+    text: "the iterator \"{0}\" must return Some(value) or None but returned {1}",
+    playful: None,
+    explanation:
+        "The function given to std::iter::from_fn is called for every value of the loop,\n\
+        and must return Some(value) for the next one, or None when there are no more.",
+};
+
+pub static NOT_A_VECTOR: Message = Message {
+    code: 415,
+    text: "cannot destructure {0}, it is not a vector",
+    playful: None,
+    explanation: "Patterns like let [a, b] = value; unpack vectors, and the value is not one.",
+};
+
+pub static DESTRUCTURE_LENGTH: Message = Message {
+    code: 416,
+    text: "expected {0} values to destructure but found {1}",
+    playful: None,
+    explanation: "The pattern has a different number of names than the vector has values.\n\
+        Add ...rest at the end of the pattern to take the values left over.",
+};
+
+pub static INVALID_UNWRAP: Message = Message {
+    code: 417,
+    text: "can only use ? on Ok, Err, Some or None but found {0}",
+    playful: None,
+    explanation: "? unwraps results and options. Other values have nothing to unwrap.",
+};
+
+pub static UNWRAP_OUTSIDE_FUNCTION: Message = Message {
+    code: 418,
+    text: "cannot use ? outside of a function (found {0})",
+    playful: None,
+    explanation: "? returns an Err or None from the function it is in, so on those it only works\n\
+        inside a function. At the top level, check the value with an if instead.",
+};
+
+pub static OUT_OF_FUEL: Message = Message {
+    code: 419,
+    text: "out of fuel: the program ran more than {0} instructions",
+    playful: None,
+    explanation: "The program ran more instructions than it is allowed, usually because of a loop that\n\
+        never ends. The playground gives programs ten million, the command line sets it with --fuel.",
+};
+
+pub static TIMED_OUT: Message = Message {
+    code: 420,
+    text: "timed out: the program ran longer than {0} seconds",
+    playful: None,
+    explanation:
+        "The program ran longer than it is allowed. The playground gives programs 5 seconds,\n\
+        the command line sets it with --timeout.",
+};
+
+pub static OUT_OF_MEMORY: Message = Message {
+    code: 421,
+    text: "out of memory: the program used more than {0}",
+    playful: None,
+    explanation:
+        "The values of the program took more memory than it is allowed, often because a vector\n\
+        grows in a loop that never ends. The command line sets the limit with --memory.",
+};
+
+pub static CANCELLED: Message = Message {
+    code: 422,
+    text: "the program was cancelled",
+    playful: None,
+    explanation:
+        "The program was stopped from outside before it finished, like when the playground\n\
+        closes the request.",
+};
+
+pub static THROWN: Message = Message {
+    code: 424,
+    text: "{0}",
+    playful: None,
+    explanation: "A value was thrown with throw and no try around it caught it.\n\
+        Wrap the code in try { ... } catch e { ... } to handle it.",
+};
+
+pub static NATIVE_ARGUMENT_COUNT: Message = Message {
+    code: 425,
+    text: "std::{0} takes {1} but {2} given",
+    playful: None,
+    explanation: "A native function was called with too few or too many arguments.\n\
+        The info page lists what each native takes.",
+};
+
+pub static NATIVE_ARGUMENT_TYPE: Message = Message {
+    code: 426,
+    text: "std::{0} expects {1} for argument {2} but found {3}",
+    playful: Some("can only use {0} on {1}!"),
+    explanation: "A native function was given a value of the wrong type, like a string to\n\
+        std::math::sin. Arguments are counted from 1.",
+};
+
+pub static NATIVE_EXPECTED: Message = Message {
+    code: 427,
+    text: "std::{0} expects {1}",
+    playful: None,
+    explanation: "A native function that takes any value was given one it can't use, like\n\
+        std::result::unwrap with a number instead of an Ok, Err, Some or None.",
+};
+
+pub static INDEX_OUT_OF_BOUNDS: Message = Message {
+    code: 428,
+    text: "index {0} is out of bounds",
+    playful: None,
+    explanation: "The index given to std::vec::get, set, remove or insert is not a whole number\n\
+        from 0 to one less than the length of the vector. insert also takes the length itself.\n\
+        std::vec::try_get gives None instead of this error.",
+};
+
+pub static EMPTY_RANDOM_RANGE: Message = Message {
+    code: 429,
+    text: "std::rand::range needs a min smaller than its max, but got {0} and {1}",
+    playful: None,
+    explanation:
+        "std::rand::range(min, max) picks a number from min up to but not including max,\n\
+        so there is nothing to pick when min is not smaller than max.",
+};
+
+pub static NEGATIVE_SLEEP: Message = Message {
+    code: 430,
+    text: "cannot sleep for {0} seconds",
+    playful: None,
+    explanation: "std::time::sleep takes a number of seconds that is 0 or more.",
+};
+
+pub static FILTER_NOT_BOOL: Message = Message {
+    code: 431,
+    text: "\"{0}\" must return a bool to filter with but returned {1}",
+    playful: None,
+    explanation: "std::vec::filter calls the function for every element and keeps the ones\n\
+        it returns true for, so the function must return true or false.",
+};
+
+pub static UNWRAP_FAILED: Message = Message {
+    code: 432,
+    text: "called unwrap on {0}",
+    playful: None,
+    explanation:
+        "std::result::unwrap gives the value inside an Ok or Some, Err and None have none.\n\
+        Check with std::result::is_ok or is_some first, or use std::result::unwrap_or.",
+};
+
+pub static NO_ITERATOR_FUNCTION: Message = Message {
+    code: 433,
+    text: "there is no function named \"{0}\" to iterate with",
+    playful: None,
+    explanation: "std::iter::from_fn takes the name of a function, as a string, that returns\n\
+        Some(value) for every value and None at the end. No function has this name.",
+};
+
+pub static INPUT_FAILED: Message = Message {
+    code: 434,
+    text: "cannot read the input: {0}",
+    playful: None,
+    explanation: "std::io::input could not read a line from the standard input.",
+};
+
+pub static INTERNAL: Message = Message {
+    code: 499,
+    text: "internal error: {0}",
+    playful: None,
+    explanation: "Something went wrong inside Klang itself, not in the program. Please report it\n\
+        with the program that caused it.",
+};
+
+// linting, these are warnings unless the program denies them with // klang:deny(...)
+
+pub static UNUSED_VARIABLE: Message = Message {
+    code: 501,
+    text: "\"{0}\" is never used",
+    playful: None,
+    explanation:
+        "The variable is declared but nothing reads it. Remove it, or start its name with _\n\
+        to keep it. Turn this off with // klang:allow(unused_variables).",
+};
+
+pub static UNUSED_FUNCTION: Message = Message {
+    code: 502,
+    text: "the function \"{0}\" is never called",
+    playful: None,
+    explanation:
+        "Nothing calls the function, and no string names it for a native function to call back.\n\
+        Remove it, or start its name with _ to keep it. Turn this off with\n\
+        // klang:allow(unused_functions).",
+};
+
+pub static UNREACHABLE: Message = Message {
+    code: 503,
+    text: "unreachable code after {0}",
+    playful: None,
+    explanation: "Statements right after a return or a throw in the same block never run.\n\
+        Turn this off with // klang:allow(unreachable).",
+};
+
+pub static SHADOWED: Message = Message {
+    code: 504,
+    text: "\"{0}\" shadows a variable of an outer block",
+    playful: None,
+    explanation:
+        "A let in a block declares a new variable with the name of one outside the block, which\n\
+        is hidden until the block ends. Assigning without let changes the outer one.\n\
+        Turn this off with // klang:allow(shadowing).",
+};
+
+pub static UNDECLARED_ASSIGNMENT: Message = Message {
+    code: 505,
+    text: "\"{0}\" is assigned but never declared",
+    playful: None,
+    explanation:
+        "Assigning to a name no let declares creates a new variable, which is often a typo of\n\
+        another one. Declare it with let. Turn this off with // klang:allow(undeclared).",
+};
+
+pub static CONSTANT_CONDITION: Message = Message {
+    code: 506,
+    text: "this condition is always {0}",
+    playful: None,
+    explanation:
+        "The condition of the if or while does not depend on anything that can change, so the same\n\
+        branch is taken every time. while true is left alone, it is how loops that end with\n\
+        return are written. Turn this off with // klang:allow(constant_condition).",
+};
+
+pub static EMPTY_BLOCK: Message = Message {
+    code: 507,
+    text: "empty {0} block",
+    playful: None,
+    explanation: "The block has nothing in it, so it does nothing. Often code that was left out.\n\
+        Turn this off with // klang:allow(empty_block).",
+};
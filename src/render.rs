@@ -0,0 +1,356 @@
+use crate::error::{Diagnostic, Frame, Note, Severity, Span};
+use crate::interpreter::modules::SourceMap;
+
+// how diagnostics are written: plain text for the terminal, html without any ansi for the playground
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Plain,
+    Html,
+}
+
+// prints each diagnostic like rustc does, with the lines it points at underlined:
+//
+// [RuntimeError] at line 2: can only add numbers
+//  --> main.kl:2:9
+//   |
+// 2 | let b = a + true;
+//   |         ^^^^^^^^
+pub fn render(diagnostics: &[Diagnostic], sources: &SourceMap, mode: Mode) -> String {
+    let renderer = Renderer { sources, mode };
+    diagnostics
+        .iter()
+        .map(|diagnostic| renderer.diagnostic(diagnostic))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// what a program printed, escaped to go after its warnings
+pub fn text(output: &str, mode: Mode) -> String {
+    let renderer = Renderer {
+        sources: &SourceMap::default(),
+        mode,
+    };
+    renderer.paint(output, "printed")
+}
+
+// the line a span is on, as it is written in its file
+struct Snippet<'a> {
+    file: &'a str,
+    line: usize,   // inside its own file
+    column: usize, // in characters, from 0
+    width: usize,  // how many characters to underline, at least 1
+    text: &'a str,
+}
+
+struct Renderer<'a> {
+    sources: &'a SourceMap,
+    mode: Mode,
+}
+
+impl Renderer<'_> {
+    fn diagnostic(&self, diagnostic: &Diagnostic) -> String {
+        let class = match diagnostic.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let header = self.sources.annotate(diagnostic.clone()).header();
+        let mut out = self.paint(&header, class) + "\n";
+        let primary = self.snippet(diagnostic.span);
+        let labels: Vec<(Option<Snippet>, &str)> = diagnostic
+            .labels
+            .iter()
+            .map(|label| (self.snippet(label.span), label.message.as_str()))
+            .collect();
+        let gutter = primary
+            .iter()
+            .chain(labels.iter().filter_map(|(snippet, _)| snippet.as_ref()))
+            .map(|snippet| snippet.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let pad = " ".repeat(gutter);
+        if let Some(primary) = &primary {
+            out += &format!(
+                "{pad}--> {}:{}:{}\n",
+                self.escape(primary.file),
+                primary.line,
+                primary.column + 1
+            );
+            out += &format!("{pad} |\n");
+            out += &self.source_line(primary, gutter);
+            out += &self.underline(primary, '^', "", "caret", gutter);
+        }
+        for (snippet, message) in labels.iter() {
+            let snippet = match snippet {
+                Some(x) => x,
+                None => continue,
+            };
+            let same_line = primary
+                .as_ref()
+                .is_some_and(|p| p.file == snippet.file && p.line == snippet.line);
+            if !same_line {
+                if primary.as_ref().is_none_or(|p| p.file != snippet.file) {
+                    out += &format!(
+                        "{pad}::: {}:{}:{}\n",
+                        self.escape(snippet.file),
+                        snippet.line,
+                        snippet.column + 1
+                    );
+                }
+                out += &format!("{pad} |\n");
+                out += &self.source_line(snippet, gutter);
+            }
+            out += &self.underline(snippet, '-', message, "label", gutter);
+        }
+        for note in diagnostic.notes.iter() {
+            let note = match note {
+                Note::Note(note) => format!("note: {note}"),
+                Note::Help(help) => format!("help: {help}"),
+                Note::Frame(_) => continue,
+            };
+            out += &format!("{pad} = {}\n", self.paint(&note, "note"));
+        }
+        if diagnostic.traced() {
+            out += &format!(
+                "{pad} = {}\n",
+                self.paint("calls, innermost first:", "note")
+            );
+        }
+        for note in diagnostic.notes.iter() {
+            if let Note::Frame(frame) = note {
+                out += &format!("{pad}     {}\n", self.paint(&self.frame(frame), "note"));
+            }
+        }
+        out
+    }
+    fn frame(&self, frame: &Frame) -> String {
+        let place = match self.snippet(frame.span) {
+            Some(s) => format!("{}:{}:{}", s.file, s.line, s.column + 1),
+            None => format!("line {}", frame.span.line),
+        };
+        let mut line = format!("{} at {place}", frame.function);
+        if frame.repeats > 0 {
+            line += &format!(" (and {} more times)", frame.repeats);
+        }
+        line
+    }
+    fn snippet(&self, span: Span) -> Option<Snippet<'_>> {
+        //spans on line 0 come from code that has no place in the source
+        if span.line == 0 {
+            return None;
+        }
+        let file = self.sources.file(span.line)?;
+        let source = file.source.as_str();
+        let before = source.get(..span.start)?;
+        let start = before.rfind('\n').map_or(0, |i| i + 1);
+        let end = source[span.start..]
+            .find('\n')
+            .map_or(source.len(), |i| span.start + i);
+        let width = source
+            .get(span.start..span.end.clamp(span.start, end))
+            .map_or(0, |s| s.chars().count());
+        Some(Snippet {
+            file: &file.name,
+            line: before.matches('\n').count() + 1,
+            column: before[start..].chars().count(),
+            width: width.max(1),
+            text: source[start..end].trim_end_matches('\r'),
+        })
+    }
+    fn source_line(&self, snippet: &Snippet, gutter: usize) -> String {
+        format!(
+            "{:>gutter$} | {}\n",
+            snippet.line,
+            self.escape(snippet.text)
+        )
+    }
+    fn underline(
+        &self,
+        snippet: &Snippet,
+        mark: char,
+        message: &str,
+        class: &str,
+        gutter: usize,
+    ) -> String {
+        //tabs stay tabs so the marks line up under the characters they point at
+        let indent: String = snippet
+            .text
+            .chars()
+            .take(snippet.column)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let mut marks = mark.to_string().repeat(snippet.width);
+        if !message.is_empty() {
+            marks = format!("{marks} {message}");
+        }
+        format!(
+            "{} | {indent}{}\n",
+            " ".repeat(gutter),
+            self.paint(&marks, class)
+        )
+    }
+    fn paint(&self, text: &str, class: &str) -> String {
+        match self.mode {
+            Mode::Plain => text.to_string(),
+            Mode::Html => format!("<span class=\"{class}\">{}</span>", self.escape(text)),
+        }
+    }
+    fn escape(&self, text: &str) -> String {
+        match self.mode {
+            Mode::Plain => text.to_string(),
+            Mode::Html => text
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::KlangError;
+    use crate::interpreter::modules::SourceFile;
+
+    // a source map of files that follow each other, like the linker builds it
+    fn sources(files: &[(&str, &str)]) -> SourceMap {
+        let mut base = 0;
+        let files = files
+            .iter()
+            .map(|(name, source)| {
+                let file = SourceFile {
+                    name: name.to_string(),
+                    base,
+                    source: source.to_string(),
+                };
+                base += source.lines().count();
+                file
+            })
+            .collect();
+        SourceMap { files }
+    }
+
+    // an error on the nth time text is written in the source, the lines are counted from base
+    fn error(source: &str, base: usize, text: &str, nth: usize, message: &str) -> Diagnostic {
+        Diagnostic::new(
+            KlangError::RuntimeError,
+            message,
+            span(source, base, text, nth),
+        )
+    }
+
+    fn span(source: &str, base: usize, text: &str, nth: usize) -> Span {
+        let start = source.match_indices(text).nth(nth).unwrap().0;
+        let line = base + source[..start].matches('\n').count() + 1;
+        Span::new(line, start, start + text.len())
+    }
+
+    #[test]
+    fn carets_line_up_under_tabs_and_wide_characters() {
+        let source = "let s = \"ü\";\n\tlet b = s + true;";
+        let diagnostic = error(source, 0, "s + true", 0, "can only add numbers");
+        assert_eq!(
+            render(&[diagnostic], &sources(&[("main.kl", source)]), Mode::Plain),
+            "[RuntimeError] at line 2: can only add numbers\n \
+            --> main.kl:2:10\n  \
+             |\n\
+            2 | \tlet b = s + true;\n  \
+             | \t        ^^^^^^^^\n"
+        );
+        let source = "let s = \"üü\" + true;";
+        let diagnostic = error(source, 0, "+ true", 0, "can only add numbers");
+        assert_eq!(
+            render(&[diagnostic], &sources(&[("main.kl", source)]), Mode::Plain),
+            "[RuntimeError] at line 1: can only add numbers\n \
+            --> main.kl:1:14\n  \
+             |\n\
+            1 | let s = \"üü\" + true;\n  \
+             |              ^^^^^^\n"
+        );
+    }
+
+    #[test]
+    fn the_gutter_fits_the_longest_line_number() {
+        let source = "let a = 1;\n".repeat(8) + "const LIMIT = 1;\nLIMIT = 2;";
+        let diagnostic = error(&source, 0, "LIMIT", 1, "cannot assign to constant")
+            .label(span(&source, 0, "LIMIT", 0), "declared here");
+        assert_eq!(
+            render(
+                &[diagnostic],
+                &sources(&[("main.kl", &source)]),
+                Mode::Plain
+            ),
+            "[RuntimeError] at line 10: cannot assign to constant\n  \
+            --> main.kl:10:1\n   \
+              |\n\
+            10 | LIMIT = 2;\n   \
+              | ^^^^^\n   \
+              |\n \
+             9 | const LIMIT = 1;\n   \
+              |       ----- declared here\n"
+        );
+    }
+
+    #[test]
+    fn labels_show_their_own_line_and_file() {
+        let main = "import \"utils.kl\";\nuse utils::{LIMIT};\nLIMIT = 2;";
+        let utils = "let x = 1;\nconst LIMIT = 1;";
+        let sources = sources(&[("main.kl", main), ("utils.kl", utils)]);
+        let diagnostic = error(main, 0, "LIMIT = 2", 0, "cannot assign to constant")
+            .label(span(utils, 3, "LIMIT", 0), "declared here")
+            .note("constants never change")
+            .help("use let instead");
+        assert_eq!(
+            render(&[diagnostic], &sources, Mode::Plain),
+            "[RuntimeError] at line 3: cannot assign to constant\n \
+            --> main.kl:3:1\n  \
+             |\n\
+            3 | LIMIT = 2;\n  \
+             | ^^^^^^^^^\n \
+            ::: utils.kl:2:7\n  \
+             |\n\
+            2 | const LIMIT = 1;\n  \
+             |       ----- declared here\n  \
+             = note: constants never change\n  \
+             = help: use let instead\n"
+        );
+    }
+
+    #[test]
+    fn errors_in_other_files_point_at_them() {
+        let main = "import \"utils.kl\";";
+        let utils = "fn f() {\n    return 1 + true;\n}";
+        let sources = sources(&[("main.kl", main), ("utils.kl", utils)]);
+        let diagnostic = error(utils, 1, "1 + true", 0, "can only add numbers");
+        assert_eq!(
+            render(&[diagnostic], &sources, Mode::Plain),
+            "[RuntimeError] in utils.kl at line 2: can only add numbers\n \
+            --> utils.kl:2:12\n  \
+             |\n\
+            2 |     return 1 + true;\n  \
+             |            ^^^^^^^^\n"
+        );
+    }
+
+    #[test]
+    fn html_escapes_the_message_source_and_file() {
+        let main = "import \"<b>.kl\";\nlet a = 1 < \"&\";";
+        let utils = "const LIMIT = 1;";
+        let sources = sources(&[("<i>.kl", main), ("<b>.kl", utils)]);
+        let diagnostic = error(main, 0, "1 < \"&\"", 0, "can't compare <int> & <string>")
+            .label(span(utils, 2, "LIMIT", 0), "<declared>");
+        let html = render(&[diagnostic], &sources, Mode::Html);
+        assert_eq!(
+            html,
+            "<span class=\"error\">[RuntimeError] at line 2: can't compare &lt;int&gt; &amp; &lt;string&gt;</span>\n \
+            --> &lt;i&gt;.kl:2:9\n  \
+             |\n\
+            2 | let a = 1 &lt; \"&amp;\";\n  \
+             |         <span class=\"caret\">^^^^^^^</span>\n \
+            ::: &lt;b&gt;.kl:1:7\n  \
+             |\n\
+            1 | const LIMIT = 1;\n  \
+             |       <span class=\"label\">----- &lt;declared&gt;</span>\n"
+        );
+        assert!(!html.contains("<i>") && !html.contains("<b>"));
+    }
+}